@@ -130,6 +130,31 @@ pub struct PythonConfig<'a> {
     /// hash seed.
     pub use_hash_seed: bool,
 
+    /// Fixed seed used to randomize hashes of str and bytes objects.
+    pub hash_seed: Option<u64>,
+
+    /// Maximum depth of the Python interpreter stack.
+    ///
+    /// If set, `sys.setrecursionlimit()` is called during interpreter
+    /// initialization.
+    pub recursion_limit: Option<u32>,
+
+    /// Stack size, in bytes, of threads created by Python.
+    ///
+    /// If set, `_thread.stack_size()` is called during interpreter
+    /// initialization.
+    pub thread_stack_size: Option<u64>,
+
+    /// Whether to install `faulthandler` handlers for fatal signals.
+    ///
+    /// Equivalent to setting `PYTHONFAULTHANDLER`.
+    pub fault_handler: bool,
+
+    /// Whether to enable Python Development Mode.
+    ///
+    /// Equivalent to `-X dev`.
+    pub development_mode: bool,
+
     /// Controls the level of the verbose mode for the interpreter.
     pub verbose: i32,
 
@@ -143,6 +168,13 @@ pub struct PythonConfig<'a> {
     /// meta path importer during interpreter initialization.
     pub packed_resources: &'a [u8],
 
+    /// Path to a file containing packed resources data.
+    ///
+    /// Relative paths are resolved against the directory of the current
+    /// executable. If set, the file is read during interpreter initialization
+    /// and its content is used instead of `packed_resources`.
+    pub packed_resources_path: Option<PathBuf>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -180,6 +212,11 @@ pub struct PythonConfig<'a> {
     /// loaded in ``sys.modules``.
     pub write_modules_directory_env: Option<String>,
 
+    /// Python code to run before any module is imported.
+    ///
+    /// See `OxidizedPythonInterpreterConfig.pre_import_code`.
+    pub pre_import_code: Option<String>,
+
     /// Defines what code to run by default.
     ///
     pub run: PythonRunMode,
@@ -210,8 +247,14 @@ impl<'a> Default for PythonConfig<'a> {
             parser_debug: false,
             quiet: false,
             use_hash_seed: false,
+            hash_seed: None,
+            recursion_limit: None,
+            thread_stack_size: None,
+            fault_handler: false,
+            development_mode: false,
             verbose: 0,
             packed_resources: &[],
+            packed_resources_path: None,
             extra_extension_modules: vec![],
             argvb: false,
             sys_frozen: false,
@@ -219,6 +262,7 @@ impl<'a> Default for PythonConfig<'a> {
             raw_allocator: PythonRawAllocator::default(),
             terminfo_resolution: TerminfoResolution::Dynamic,
             write_modules_directory_env: None,
+            pre_import_code: None,
             run: PythonRunMode::None,
         }
     }
@@ -589,6 +633,13 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// meta path importer during interpreter initialization.
     pub packed_resources: Option<&'a [u8]>,
 
+    /// Path to a file containing packed resources data.
+    ///
+    /// Relative paths are resolved against the directory of the current
+    /// executable. If set, the file is read during interpreter initialization
+    /// and its content is used instead of `packed_resources`.
+    pub packed_resources_path: Option<PathBuf>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -612,6 +663,18 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// of setting this attribute.
     pub sys_meipass: bool,
 
+    /// Maximum depth of the Python interpreter stack.
+    ///
+    /// If set, `sys.setrecursionlimit()` is called during interpreter
+    /// initialization.
+    pub recursion_limit: Option<u32>,
+
+    /// Stack size, in bytes, of threads created by Python.
+    ///
+    /// If set, `_thread.stack_size()` is called during interpreter
+    /// initialization.
+    pub thread_stack_size: Option<u64>,
+
     /// How to resolve the `terminfo` database.
     pub terminfo_resolution: TerminfoResolution,
 
@@ -623,6 +686,22 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// loaded in ``sys.modules``.
     pub write_modules_directory_env: Option<String>,
 
+    /// Python code to run before any module is imported.
+    ///
+    /// The code runs after PyOxidizer's importer is installed but before the
+    /// main phase of interpreter initialization. At that point `sys.meta_path`
+    /// holds only PyOxidizer's importer, which also services builtin and frozen
+    /// modules. If that importer is disabled, it holds the builtin and frozen
+    /// importers instead. `sys.meta_path.insert(0, finder)` registers a finder that
+    /// takes precedence over PyOxidizer's importer and `append()` registers one
+    /// consulted after it. The filesystem importer is registered later, after
+    /// any appended finders.
+    ///
+    /// Only builtin modules and modules resolvable by already registered
+    /// finders can be imported. `sys.argv`, `sys.path`, and signal handlers
+    /// are not yet configured. Failure aborts interpreter initialization.
+    pub pre_import_code: Option<String>,
+
     /// Defines what code to run by default.
     ///
     pub run: PythonRunMode,
@@ -639,12 +718,16 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             oxidized_importer: false,
             filesystem_importer: true,
             packed_resources: None,
+            packed_resources_path: None,
             extra_extension_modules: None,
             argvb: false,
             sys_frozen: false,
             sys_meipass: false,
+            recursion_limit: None,
+            thread_stack_size: None,
             terminfo_resolution: TerminfoResolution::Dynamic,
             write_modules_directory_env: None,
+            pre_import_code: None,
             run: PythonRunMode::Repl,
         }
     }
@@ -690,18 +773,34 @@ impl<'a> From<PythonConfig<'a>> for OxidizedPythonInterpreterConfig<'a> {
                 parser_debug: Some(config.parser_debug),
                 quiet: Some(config.quiet),
                 verbose: Some(config.verbose != 0),
+                hash_seed: config.hash_seed.map(|seed| seed as c_ulong),
+                // Leave disabled flags unset so environment variables are still honored.
+                fault_handler: if config.fault_handler {
+                    Some(true)
+                } else {
+                    None
+                },
+                development_mode: if config.development_mode {
+                    Some(true)
+                } else {
+                    None
+                },
                 ..PythonInterpreterConfig::default()
             },
             raw_allocator: Some(config.raw_allocator),
             oxidized_importer: config.use_custom_importlib,
             filesystem_importer: config.filesystem_importer,
             packed_resources: Some(config.packed_resources),
+            packed_resources_path: config.packed_resources_path,
             extra_extension_modules: Some(config.extra_extension_modules),
             argvb: config.argvb,
             sys_frozen: config.sys_frozen,
             sys_meipass: config.sys_meipass,
+            recursion_limit: config.recursion_limit,
+            thread_stack_size: config.thread_stack_size,
             terminfo_resolution: config.terminfo_resolution,
             write_modules_directory_env: config.write_modules_directory_env,
+            pre_import_code: config.pre_import_code,
             run: config.run,
         }
     }
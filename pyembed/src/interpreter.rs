@@ -356,9 +356,24 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                     .map_err(|err| NewInterpreterError::Simple(err))?,
             ));
 
+            let packed_resources = if let Some(path) = &self.config.packed_resources_path {
+                let data = std::fs::read(origin.join(path)).map_err(|_| {
+                    NewInterpreterError::Simple("unable to read packed resources file")
+                })?;
+
+                // The parsed resources reference this data and must outlive the
+                // interpreter. Since the interpreter generally lives for the
+                // remainder of the process, leaking is acceptable.
+                let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+
+                Some(data)
+            } else {
+                self.config.packed_resources
+            };
+
             if let Some(ref mut resources_state) = self.resources_state {
                 resources_state
-                    .load(self.config.packed_resources)
+                    .load(packed_resources)
                     .map_err(|err| NewInterpreterError::Simple(err))?;
 
                 let oxidized_importer = py.import(OXIDIZED_IMPORTER_NAME_STR).map_err(|err| {
@@ -379,6 +394,22 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        if let Some(code) = &self.config.pre_import_code {
+            let globals = PyDict::new(py);
+            let builtins = py.import("builtins").map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "obtaining builtins module")
+            })?;
+            globals
+                .set_item(py, "__builtins__", builtins)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "populating globals")
+                })?;
+
+            py.run(code, Some(&globals), None).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "running pre-import code")
+            })?;
+        }
+
         // Now proceed with the Python main initialization. This will initialize
         // importlib. And if the custom importlib bytecode was registered above,
         // our extension module will get imported and initialized.
@@ -502,6 +533,28 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        if let Some(limit) = self.config.recursion_limit {
+            let sys_module = py.import("sys").map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "obtaining sys module")
+            })?;
+            sys_module
+                .call(py, "setrecursionlimit", (limit,), None)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "sys.setrecursionlimit()")
+                })?;
+        }
+
+        if let Some(size) = self.config.thread_stack_size {
+            let thread_module = py.import("_thread").map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "obtaining _thread module")
+            })?;
+            thread_module
+                .call(py, "stack_size", (size,), None)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "_thread.stack_size()")
+                })?;
+        }
+
         Ok(())
     }
 
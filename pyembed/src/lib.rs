@@ -39,8 +39,9 @@ interfacing.
 **It is an explicit goal of this crate to rely on as few external dependencies
 as possible.** This is because we want to minimize bloat in produced binaries.
 At this time, we have required direct dependencies on published versions of the
-`anyhow`, `lazy_static`, `libc`, `memmap`, `python-packed-resources`, and `uuid`
-crates. On Windows, this list is extended by `memory-module-sys` and `winapi`,
+`anyhow`, `lazy_static`, `libc`, `memmap`, `python-packed-resources`, `uuid`,
+and `zstd` crates. (`zstd` is used to decompress individually compressed
+resources.) On Windows, this list is extended by `memory-module-sys` and `winapi`,
 which are required to support loading DLLs from memory. We also have an optional
 direct dependency on the `jemalloc-sys` crate.
 
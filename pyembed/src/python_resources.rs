@@ -29,6 +29,44 @@ use {
     std::path::{Path, PathBuf},
 };
 
+/// Decompress zstd compressed data.
+fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    zstd::stream::decode_all(data).map_err(|_| "error decompressing resource data")
+}
+
+/// Decompress every in-memory data field of a resource compressed with zstd.
+///
+/// The resource is no longer flagged as compressed afterwards.
+fn decompress_resource(resource: &mut Resource<u8>) -> Result<(), &'static str> {
+    for field in vec![
+        &mut resource.in_memory_source,
+        &mut resource.in_memory_bytecode,
+        &mut resource.in_memory_bytecode_opt1,
+        &mut resource.in_memory_bytecode_opt2,
+        &mut resource.in_memory_extension_module_shared_library,
+        &mut resource.in_memory_shared_library,
+    ] {
+        if let Some(data) = field {
+            *data = Cow::Owned(zstd_decompress(data)?);
+        }
+    }
+
+    for field in vec![
+        &mut resource.in_memory_package_resources,
+        &mut resource.in_memory_distribution_resources,
+    ] {
+        if let Some(resources) = field {
+            for data in resources.values_mut() {
+                *data = Cow::Owned(zstd_decompress(data)?);
+            }
+        }
+    }
+
+    resource.zstd_compressed = false;
+
+    Ok(())
+}
+
 /// Python bytecode optimization level.
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum OptimizeLevel {
@@ -821,7 +859,11 @@ impl<'a> PythonResourcesState<'a, u8> {
         self.resources.reserve(resources.expected_resources_count());
 
         for resource in resources {
-            let resource = resource?;
+            let mut resource = resource?;
+
+            if resource.zstd_compressed {
+                decompress_resource(&mut resource)?;
+            }
 
             self.resources.insert(resource.name.clone(), resource);
         }
@@ -5,7 +5,13 @@
 use {
     crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
     anyhow::{anyhow, Result},
-    cpython::{ObjectProtocol, PyObject},
+    cpython::{NoArgs, ObjectProtocol, PyBytes, PyObject},
+    python_packed_resources::{
+        data::{Resource, ResourceFlavor},
+        writer::write_packed_resources_v1,
+    },
+    std::borrow::Cow,
+    std::collections::HashMap,
     std::path::PathBuf,
 };
 
@@ -97,6 +103,58 @@ fn find_spec_missing() -> Result<()> {
     Ok(())
 }
 
+/// Resources flagged as zstd compressed are decompressed when loaded.
+#[test]
+fn zstd_compressed_resources() -> Result<()> {
+    let source = b"VALUE = 42\n";
+    let resource_data = b"resource data".to_vec();
+
+    let mut package_resources = HashMap::new();
+    package_resources.insert(
+        Cow::Borrowed("data.txt"),
+        Cow::Owned(zstd::stream::encode_all(&resource_data[..], 3)?),
+    );
+
+    let resource = Resource {
+        flavor: ResourceFlavor::Module,
+        name: Cow::Borrowed("compressed_module"),
+        is_package: true,
+        in_memory_source: Some(Cow::Owned(zstd::stream::encode_all(&source[..], 3)?)),
+        in_memory_package_resources: Some(package_resources),
+        zstd_compressed: true,
+        ..Resource::default()
+    };
+
+    let mut packed_resources = vec![];
+    write_packed_resources_v1(&[resource], &mut packed_resources, None)?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources = Some(&packed_resources);
+    let mut interp = MainPythonInterpreter::new(config)?;
+
+    let py = interp.acquire_gil().unwrap();
+
+    let module = py.import("compressed_module").unwrap();
+    let value = module.get(py, "VALUE").unwrap();
+    assert_eq!(value.extract::<i64>(py).unwrap(), 42);
+
+    let loader = module.get(py, "__loader__").unwrap();
+    let reader = loader
+        .call_method(py, "get_resource_reader", ("compressed_module",), None)
+        .unwrap();
+    let fh = reader
+        .call_method(py, "open_resource", ("data.txt",), None)
+        .unwrap();
+    let data = fh.call_method(py, "read", NoArgs, None).unwrap();
+    assert_eq!(
+        data.cast_as::<PyBytes>(py).unwrap().data(py),
+        &resource_data[..]
+    );
+
+    Ok(())
+}
+
 /// Run test_importer_builtins.py.
 #[test]
 fn builtins_py() -> Result<()> {
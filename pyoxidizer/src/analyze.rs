@@ -469,3 +469,44 @@ pub fn find_pe_dependencies_path(path: &Path) -> Result<Vec<String>> {
     let data = std::fs::read(path)?;
     find_pe_dependencies(&data)
 }
+
+fn machine_architecture(machine: u16) -> Option<&'static str> {
+    match machine {
+        goblin::pe::header::COFF_MACHINE_X86 => Some("i686"),
+        goblin::pe::header::COFF_MACHINE_X86_64 => Some("x86_64"),
+        goblin::pe::header::COFF_MACHINE_ARM64 => Some("aarch64"),
+        _ => None,
+    }
+}
+
+/// Resolve the CPU architecture an object file is compiled for.
+///
+/// ELF, Mach-O, PE, and COFF object files are recognized. Architecture names
+/// match the first component of Rust target triples, with all 32-bit x86
+/// variants reported as `i686`.
+///
+/// Returns `None` if the file format or architecture isn't recognized.
+pub fn object_file_architecture(data: &[u8]) -> Result<Option<&'static str>> {
+    Ok(match goblin::Object::parse(data)? {
+        goblin::Object::Elf(elf) => match elf.header.e_machine {
+            goblin::elf::header::EM_386 => Some("i686"),
+            goblin::elf::header::EM_X86_64 => Some("x86_64"),
+            goblin::elf::header::EM_ARM => Some("arm"),
+            goblin::elf::header::EM_AARCH64 => Some("aarch64"),
+            _ => None,
+        },
+        goblin::Object::Mach(goblin::mach::Mach::Binary(macho)) => match macho.header.cputype {
+            goblin::mach::cputype::CPU_TYPE_X86 => Some("i686"),
+            goblin::mach::cputype::CPU_TYPE_X86_64 => Some("x86_64"),
+            goblin::mach::cputype::CPU_TYPE_ARM => Some("arm"),
+            goblin::mach::cputype::CPU_TYPE_ARM64 => Some("aarch64"),
+            _ => None,
+        },
+        goblin::Object::PE(pe) => machine_architecture(pe.header.coff_header.machine),
+        // COFF object files have no magic. They begin with the machine type.
+        goblin::Object::Unknown(_) if data.len() >= 20 => {
+            machine_architecture(u16::from_le_bytes([data[0], data[1]]))
+        }
+        _ => None,
+    })
+}
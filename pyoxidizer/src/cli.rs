@@ -346,13 +346,13 @@ pub fn run_cli() -> Result<()> {
         ("python-distribution-info", Some(args)) => {
             let dist_path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_info(dist_path)
+            projectmgmt::python_distribution_info(&logger_context.logger, dist_path)
         }
 
         ("python-distribution-licenses", Some(args)) => {
             let path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_licenses(path)
+            projectmgmt::python_distribution_licenses(&logger_context.logger, path)
         }
 
         ("run-build-script", Some(args)) => {
@@ -15,6 +15,17 @@ use {
 
 pub const PYOXIDIZER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Environment variable defining the prefix of temporary directory names.
+pub const TEMP_DIR_PREFIX_ENV: &str = "PYOXIDIZER_TEMP_DIR_PREFIX";
+
+/// Environment variable enabling fast iteration builds.
+///
+/// See `fast_build_enabled()`.
+pub const FAST_BUILD_ENV: &str = "PYOXIDIZER_FAST_BUILD";
+
+/// Default prefix for temporary directory names.
+const DEFAULT_TEMP_DIR_PREFIX: &str = "pyoxidizer";
+
 /// Canonical Git repository for PyOxidizer.
 const CANONICAL_GIT_REPO_URL: &str = "https://github.com/indygreg/PyOxidizer.git";
 
@@ -66,6 +77,51 @@ pub fn canonicalize_path(path: &Path) -> Result<PathBuf, std::io::Error> {
     Ok(p)
 }
 
+/// Resolve the prefix to use for temporary directory names.
+///
+/// The prefix can be overridden via the `PYOXIDIZER_TEMP_DIR_PREFIX`
+/// environment variable.
+pub fn temp_dir_prefix() -> String {
+    match env::var(TEMP_DIR_PREFIX_ENV) {
+        Ok(value) if !value.is_empty() => value,
+        _ => DEFAULT_TEMP_DIR_PREFIX.to_string(),
+    }
+}
+
+/// Create a new temporary directory whose name has our temporary directory prefix.
+pub fn new_temp_dir(name: &str) -> Result<tempdir::TempDir, std::io::Error> {
+    tempdir::TempDir::new(&format!("{}-{}", temp_dir_prefix(), name))
+}
+
+/// Create a new temporary directory with our prefix, optionally under a base directory.
+///
+/// If `base` is `None`, the directory is created in the system temporary
+/// directory, like `new_temp_dir()`.
+pub fn new_temp_dir_in(
+    base: Option<&Path>,
+    name: &str,
+) -> Result<tempdir::TempDir, std::io::Error> {
+    let prefix = format!("{}-{}", temp_dir_prefix(), name);
+
+    match base {
+        Some(base) => tempdir::TempDir::new_in(base, &prefix),
+        None => tempdir::TempDir::new(&prefix),
+    }
+}
+
+/// Whether fast iteration builds are enabled.
+///
+/// Fast iteration builds don't compile Python bytecode. Source is embedded
+/// instead and compiled at run-time, making imports slower. This is meant for
+/// development only. Enabled by setting `PYOXIDIZER_FAST_BUILD` to a value
+/// other than an empty string or `0`.
+pub fn fast_build_enabled() -> bool {
+    match env::var(FAST_BUILD_ENV) {
+        Ok(value) => !value.is_empty() && value != "0",
+        Err(_) => false,
+    }
+}
+
 /// Describes the location of the PyOxidizer source files.
 pub enum PyOxidizerSource {
     /// A local filesystem path.
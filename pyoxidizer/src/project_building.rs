@@ -3,7 +3,8 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::environment::{canonicalize_path, MINIMUM_RUST_VERSION},
+    crate::app_packaging::resource::{FileContent, FileManifest},
+    crate::environment::{canonicalize_path, new_temp_dir, MINIMUM_RUST_VERSION},
     crate::project_layout::initialize_project,
     crate::py_packaging::binary::{EmbeddedPythonBinaryData, PythonBinaryBuilder},
     crate::starlark::eval::{eval_starlark_config_file, EvalResult},
@@ -81,6 +82,138 @@ pub struct BuiltExecutable {
 
     /// Holds state generated from building.
     pub binary_data: EmbeddedPythonBinaryData,
+
+    /// Files holding debug info split from the executable.
+    ///
+    /// Paths are relative to the directory of the executable. Empty unless
+    /// split debug info is enabled.
+    pub debug_files: FileManifest,
+}
+
+/// Run a tool used to post-process a built executable, requiring it to succeed.
+fn run_tool(logger: &slog::Logger, program: &str, args: &[&str]) -> Result<()> {
+    warn!(logger, "running {} {}", program, args.join(" "));
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("running {}", program))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} {} failed: {}", program, args.join(" "), status))
+    }
+}
+
+/// Move debug info of the executable at `exe_path` into separate files.
+///
+/// On Linux, debug info is copied to `<exe>.debug` with `objcopy`, stripped
+/// from the executable, and a `.gnu_debuglink` section referencing the debug
+/// file is added. On macOS, `dsymutil` writes a `<exe>.dSYM` bundle and the
+/// executable is stripped of debug symbols. On Windows, the linker already
+/// wrote debug info to a PDB file next to the executable.
+///
+/// Returns the produced debug files relative to the directory of `exe_path`.
+fn split_executable_debug_info(
+    logger: &slog::Logger,
+    exe_path: &Path,
+    bin_name: &str,
+    target: &str,
+) -> Result<FileManifest> {
+    let exe_dir = exe_path
+        .parent()
+        .ok_or_else(|| anyhow!("unable to resolve parent directory of executable"))?;
+    let exe_name = exe_path
+        .file_name()
+        .ok_or_else(|| anyhow!("unable to resolve executable filename"))?
+        .to_string_lossy()
+        .to_string();
+    let exe = exe_path.display().to_string();
+
+    let debug_path = if target.contains("-linux-") {
+        let debug_path = exe_dir.join(format!("{}.debug", exe_name));
+        let debug = debug_path.display().to_string();
+
+        run_tool(logger, "objcopy", &["--only-keep-debug", &exe, &debug])?;
+        run_tool(
+            logger,
+            "objcopy",
+            &[
+                "--strip-debug",
+                &format!("--add-gnu-debuglink={}", debug),
+                &exe,
+            ],
+        )?;
+
+        debug_path
+    } else if target.contains("-apple-") {
+        let debug_path = exe_dir.join(format!("{}.dSYM", exe_name));
+        let debug = debug_path.display().to_string();
+
+        if debug_path.exists() {
+            std::fs::remove_dir_all(&debug_path)
+                .with_context(|| format!("removing {}", debug_path.display()))?;
+        }
+
+        run_tool(logger, "dsymutil", &[&exe, "-o", &debug])?;
+        run_tool(logger, "strip", &["-S", &exe])?;
+
+        debug_path
+    } else if target.contains("-windows-msvc") {
+        // Cargo names PDBs after the crate, which can't contain hyphens.
+        exe_dir.join(format!("{}.pdb", bin_name.replace('-', "_")))
+    } else {
+        return Err(anyhow!(
+            "splitting debug info is not supported for {}",
+            target
+        ));
+    };
+
+    if !debug_path.exists() {
+        return Err(anyhow!("{} does not exist", debug_path.display()));
+    }
+
+    let mut manifest = FileManifest::default();
+
+    for entry in walkdir::WalkDir::new(&debug_path) {
+        let entry = entry?;
+
+        if entry.file_type().is_file() {
+            manifest.add_file(
+                entry.path().strip_prefix(exe_dir)?,
+                &FileContent {
+                    data: std::fs::read(entry.path())
+                        .with_context(|| format!("reading {}", entry.path().display()))?,
+                    executable: false,
+                },
+            )?;
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Derive a `RUSTFLAGS` value passing `linker_args` to the linker.
+///
+/// Build scripts of library crates like `pyembed` can't pass arguments to the
+/// linker of binaries depending on them. So linker arguments are passed to
+/// rustc with `-C link-arg`, appended to `existing` flags. Returns `None` if
+/// there are no linker arguments.
+///
+/// Setting `RUSTFLAGS` takes precedence over `build.rustflags` in Cargo
+/// configuration files.
+fn linker_args_rustflags(existing: Option<&str>, linker_args: &[String]) -> Option<String> {
+    if linker_args.is_empty() {
+        return None;
+    }
+
+    let mut flags: Vec<String> = existing
+        .map(|s| s.split_whitespace().map(|s| s.to_string()).collect())
+        .unwrap_or_else(Vec::new);
+    flags.extend(linker_args.iter().map(|arg| format!("-Clink-arg={}", arg)));
+
+    Some(flags.join(" "))
 }
 
 /// Build an executable embedding Python using an existing Rust project.
@@ -101,6 +234,8 @@ pub fn build_executable_with_rust_project(
     create_dir_all(&artifacts_path)
         .with_context(|| "creating directory for PyOxidizer build artifacts")?;
 
+    exe.hint_unoptimized_distribution(logger, release);
+
     // Derive and write the artifacts needed to build a binary embedding Python.
     let embedded_data = exe.as_embedded_python_binary_data(logger, opt_level)?;
     embedded_data.write_files(&artifacts_path)?;
@@ -193,6 +328,13 @@ pub fn build_executable_with_rust_project(
         }
     }
 
+    if let Some(rustflags) = linker_args_rustflags(
+        std::env::var("RUSTFLAGS").ok().as_deref(),
+        &embedded_data.linking_info.linker_args,
+    ) {
+        envs.push(("RUSTFLAGS", rustflags));
+    }
+
     // static-nobundle link kind requires nightly Rust compiler until
     // https://github.com/rust-lang/rust/issues/37403 is resolved.
     if cfg!(windows) {
@@ -221,6 +363,12 @@ pub fn build_executable_with_rust_project(
         return Err(anyhow!("{} does not exist", exe_path.display()));
     }
 
+    let debug_files = if embedded_data.split_debug_info {
+        split_executable_debug_info(logger, &exe_path, bin_name, target)?
+    } else {
+        FileManifest::default()
+    };
+
     let exe_data = std::fs::read(&exe_path)?;
     let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
 
@@ -229,6 +377,7 @@ pub fn build_executable_with_rust_project(
         exe_name,
         exe_data,
         binary_data: embedded_data,
+        debug_files,
     })
 }
 
@@ -246,7 +395,7 @@ pub fn build_python_executable(
     let env = crate::environment::resolve_environment()?;
     let pyembed_location = env.as_pyembed_location();
 
-    let temp_dir = tempdir::TempDir::new("pyoxidizer")?;
+    let temp_dir = new_temp_dir("build")?;
 
     // Directory needs to have name of project.
     let project_path = temp_dir.path().join(bin_name);
@@ -517,10 +666,34 @@ fn artifacts_current(logger: &slog::Logger, config_path: &Path, artifacts_path:
 mod tests {
     use {
         super::*,
+        crate::py_packaging::config::{EmbeddedPythonConfig, RunMode},
+        crate::py_packaging::distribution::{BinaryLibpythonLinkMode, PythonDistribution},
         crate::py_packaging::standalone_distribution::tests::get_standalone_executable_builder,
         crate::testutil::*,
+        std::collections::BTreeSet,
     };
 
+    #[test]
+    fn test_linker_args_rustflags() {
+        assert_eq!(linker_args_rustflags(Some("-Copt-level=1"), &[]), None);
+        assert_eq!(
+            linker_args_rustflags(
+                None,
+                &[
+                    "-Wl,--disable-new-dtags".to_string(),
+                    "-Wl,-rpath,$ORIGIN/lib".to_string()
+                ]
+            ),
+            Some(
+                "-Clink-arg=-Wl,--disable-new-dtags -Clink-arg=-Wl,-rpath,$ORIGIN/lib".to_string()
+            )
+        );
+        assert_eq!(
+            linker_args_rustflags(Some(" -Copt-level=1  -g "), &["/DEBUG".to_string()]),
+            Some("-Copt-level=1 -g -Clink-arg=/DEBUG".to_string())
+        );
+    }
+
     #[test]
     fn test_empty_project() -> Result<()> {
         let logger = get_logger()?;
@@ -530,4 +703,128 @@ mod tests {
 
         Ok(())
     }
+
+    /// Write a built executable and its extra files to `dest_dir` and run it.
+    fn run_built_executable(
+        build: &BuiltExecutable,
+        dest_dir: &Path,
+    ) -> Result<std::process::Output> {
+        use std::io::Write;
+
+        build.binary_data.extra_files.write_to_path(dest_dir)?;
+
+        let exe_path = dest_dir.join(&build.exe_name);
+        let mut fh = std::fs::File::create(&exe_path)?;
+        fh.write_all(&build.exe_data)?;
+        crate::app_packaging::resource::set_executable(&mut fh)?;
+        drop(fh);
+
+        Ok(std::process::Command::new(&exe_path).output()?)
+    }
+
+    #[test]
+    fn test_main_module_source() -> Result<()> {
+        let logger = get_logger()?;
+        let mut exe = get_standalone_executable_builder()?;
+        exe.set_main_module_source(
+            "import sys\nprint(__name__, sys.modules['__main__'].__dict__ is globals())\n",
+        )?;
+
+        let build = build_python_executable(&logger, "myapp", &exe, env!("HOST"), "0", false)?;
+        let temp_dir = new_temp_dir("test")?;
+        let output = run_built_executable(&build, temp_dir.path())?;
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim_end(),
+            "__main__ True"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bootstrap_stdlib_runs() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+
+        let mut policy = distribution.create_packaging_policy()?;
+        policy.set_include_distribution_stdlib(false);
+
+        let mut config = EmbeddedPythonConfig::default();
+        config.run_mode = RunMode::Eval {
+            code: "import os, sys; print(os.path.__name__ in sys.modules)".to_string(),
+        };
+
+        let exe = distribution.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            env!("HOST"),
+            "myapp",
+            BinaryLibpythonLinkMode::Default,
+            &policy,
+            &config,
+        )?;
+
+        let build = build_python_executable(&logger, "myapp", &*exe, env!("HOST"), "0", false)?;
+        let temp_dir = new_temp_dir("test")?;
+        let output = run_built_executable(&build, temp_dir.path())?;
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), "True");
+
+        Ok(())
+    }
+
+    /// Obtain the names of sections in ELF data.
+    #[cfg(target_os = "linux")]
+    fn elf_section_names(data: &[u8]) -> Result<BTreeSet<String>> {
+        let elf = goblin::elf::Elf::parse(data)?;
+
+        elf.section_headers
+            .iter()
+            .map(|header| {
+                Ok(elf
+                    .shdr_strtab
+                    .get(header.sh_name)
+                    .ok_or_else(|| anyhow!("invalid section name offset"))??
+                    .to_string())
+            })
+            .collect()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_split_debug_info() -> Result<()> {
+        let logger = get_logger()?;
+        let mut exe = get_standalone_executable_builder()?;
+        exe.set_split_debug_info(true);
+        exe.set_run_code("print('hello')");
+
+        let build = build_python_executable(&logger, "myapp", &exe, env!("HOST"), "0", false)?;
+
+        let debug_files = build.debug_files.entries().collect::<Vec<_>>();
+        assert_eq!(debug_files.len(), 1);
+        assert_eq!(debug_files[0].0, Path::new("myapp.debug"));
+
+        let exe_sections = elf_section_names(&build.exe_data)?;
+        assert!(exe_sections.contains(".gnu_debuglink"));
+        assert!(!exe_sections.contains(".debug_info"));
+        assert!(elf_section_names(&debug_files[0].1.data)?.contains(".debug_info"));
+
+        let temp_dir = new_temp_dir("test")?;
+        let output = run_built_executable(&build, temp_dir.path())?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), "hello");
+
+        Ok(())
+    }
 }
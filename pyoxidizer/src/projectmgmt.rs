@@ -5,6 +5,7 @@
 //! Manage PyOxidizer projects.
 
 use {
+    crate::environment::new_temp_dir,
     crate::project_building::find_pyoxidizer_config_file_env,
     crate::project_layout::{initialize_project, write_new_pyoxidizer_config_file},
     crate::py_packaging::standalone_distribution::StandaloneDistribution,
@@ -223,14 +224,14 @@ pub fn python_distribution_extract(dist_path: &str, dest_path: &str) -> Result<(
     Ok(())
 }
 
-pub fn python_distribution_info(dist_path: &str) -> Result<()> {
+pub fn python_distribution_info(logger: &slog::Logger, dist_path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(dist_path))?;
     let reader = std::io::BufReader::new(fh);
 
-    let temp_dir = tempdir::TempDir::new("python-distribution")?;
+    let temp_dir = new_temp_dir("python-distribution")?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst_with_logger(logger, reader, temp_dir_path)?;
 
     println!("High-Level Metadata");
     println!("===================");
@@ -293,14 +294,14 @@ pub fn python_distribution_info(dist_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn python_distribution_licenses(path: &str) -> Result<()> {
+pub fn python_distribution_licenses(logger: &slog::Logger, path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(path))?;
     let reader = std::io::BufReader::new(fh);
 
-    let temp_dir = tempdir::TempDir::new("python-distribution")?;
+    let temp_dir = new_temp_dir("python-distribution")?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst_with_logger(logger, reader, temp_dir_path)?;
 
     println!(
         "Python Distribution Licenses: {}",
@@ -7,24 +7,184 @@ Defining and manipulating binaries embedding Python.
 */
 
 use {
-    super::config::EmbeddedPythonConfig,
-    super::embedded_resource::EmbeddedPythonResources,
-    super::pyembed::{derive_python_config, write_default_python_config_rs},
+    super::config::{EmbeddedPythonConfig, RunMode},
+    super::embedded_resource::{
+        EmbeddedPythonResources, ResourceCompressionOptions, UnresolvableResourcePolicy,
+    },
+    super::import_graph::{resolve_imports, unreachable_modules},
+    super::pyembed::{
+        derive_python_config, packed_resources_symbol, write_default_python_config_rs,
+    },
+    super::resource_snapshot::{ResourceDelta, ResourceSnapshot},
     crate::app_packaging::resource::FileManifest,
-    anyhow::Result,
-    python_packaging::policy::{PythonPackagingPolicy, PythonResourcesPolicy},
+    anyhow::{anyhow, Context, Result},
+    python_packaging::policy::{
+        ExtensionModuleFilter, PythonPackagingPolicy, PythonResourcesPolicy,
+    },
     python_packaging::resource::{
-        PythonExtensionModule, PythonModuleBytecodeFromSource, PythonModuleSource,
-        PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+        BytecodeOptimizationLevel, DataLocation, PythonExtensionModule,
+        PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
+        PythonPackageResource, PythonResource,
     },
     python_packaging::resource_collection::PrePackagedResource,
-    std::collections::HashMap,
+    slog::info,
+    std::collections::{BTreeMap, BTreeSet, HashMap},
     std::convert::TryFrom,
     std::fs::File,
     std::io::Write,
     std::path::{Path, PathBuf},
+    std::process::{Command, Stdio},
 };
 
+/// Ensure a prefix for the names of generated build artifacts is valid.
+///
+/// The prefix is used in filenames and in the names of generated symbols, so
+/// it must be empty or a valid identifier consisting of ASCII letters, digits,
+/// and underscores.
+pub fn validate_artifact_name_prefix(prefix: &str) -> Result<()> {
+    let valid = match prefix.chars().next() {
+        None => true,
+        Some(c) => {
+            (c.is_ascii_alphabetic() || c == '_')
+                && prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "invalid artifact name prefix {:?}: must be an identifier consisting of ASCII letters, digits, and underscores",
+            prefix
+        ))
+    }
+}
+
+/// Name of the module holding source registered with `set_main_module_source()`.
+pub const MAIN_MODULE_NAME: &str = "__pyoxidizer_main__";
+
+/// A C extension module paired with the Python module wrapping it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AcceleratorModulePair {
+    /// Name of the C extension module.
+    pub extension: &'static str,
+    /// Name of the Python module importing the extension.
+    pub wrapper: &'static str,
+    /// Whether the wrapper fails to import without the extension.
+    ///
+    /// Otherwise the wrapper falls back to a slower pure Python implementation.
+    pub required: bool,
+}
+
+/// C extension modules of the standard library and the Python modules wrapping them.
+///
+/// CPython provides C implementations of some standard library modules. Some
+/// wrappers can't function without them. Others silently fall back to a pure
+/// Python implementation, which is correct but slower.
+pub const ACCELERATOR_MODULE_PAIRS: &[AcceleratorModulePair] = &[
+    AcceleratorModulePair {
+        extension: "_asyncio",
+        wrapper: "asyncio",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_bisect",
+        wrapper: "bisect",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_bz2",
+        wrapper: "bz2",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_csv",
+        wrapper: "csv",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_ctypes",
+        wrapper: "ctypes",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_datetime",
+        wrapper: "datetime",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_decimal",
+        wrapper: "decimal",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_elementtree",
+        wrapper: "xml.etree.ElementTree",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_heapq",
+        wrapper: "heapq",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_json",
+        wrapper: "json",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_lzma",
+        wrapper: "lzma",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_pickle",
+        wrapper: "pickle",
+        required: false,
+    },
+    AcceleratorModulePair {
+        extension: "_sqlite3",
+        wrapper: "sqlite3",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_ssl",
+        wrapper: "ssl",
+        required: true,
+    },
+    AcceleratorModulePair {
+        extension: "_struct",
+        wrapper: "struct",
+        required: true,
+    },
+];
+
+/// Describe pairs in `ACCELERATOR_MODULE_PAIRS` having only one member in `names`.
+pub fn accelerator_pair_problems(names: &BTreeSet<String>) -> Vec<String> {
+    ACCELERATOR_MODULE_PAIRS
+        .iter()
+        .filter_map(
+            |pair| match (names.contains(pair.extension), names.contains(pair.wrapper)) {
+                (true, false) => Some(format!(
+                    "{} is present without {}",
+                    pair.extension, pair.wrapper
+                )),
+                (false, true) if pair.required => Some(format!(
+                    "{} is present without {}, which it requires",
+                    pair.wrapper, pair.extension
+                )),
+                (false, true) => Some(format!(
+                    "{} is present without {}; its slower pure Python implementation will be used",
+                    pair.wrapper, pair.extension
+                )),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
 /// How a binary should link against libpython.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LibpythonLinkMode {
@@ -34,6 +194,67 @@ pub enum LibpythonLinkMode {
     Dynamic,
 }
 
+/// Counts of distribution resources included or excluded by a packaging policy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PolicyTallies {
+    pub extension_modules_included: usize,
+    pub extension_modules_excluded: usize,
+    pub module_sources_included: usize,
+    pub module_sources_excluded: usize,
+    pub module_bytecode_included: usize,
+    pub module_bytecode_excluded: usize,
+    pub package_resources_included: usize,
+    pub package_resources_excluded: usize,
+}
+
+/// Describes a packaging policy and the effects of applying it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyReport {
+    /// The active extension module filter.
+    pub extension_module_filter: ExtensionModuleFilter,
+
+    /// The active resources policy.
+    pub resources_policy: PythonResourcesPolicy,
+
+    /// Extensions blocked for the builder's target triple.
+    pub broken_extensions: Vec<String>,
+
+    /// Counts of distribution resources the policy included or excluded.
+    pub tallies: PolicyTallies,
+}
+
+/// Ensure Python source code compiles using a Python interpreter.
+///
+/// The returned error contains the compiler's error message.
+pub fn check_source_compiles(python_exe: &Path, source: &str, filename: &str) -> Result<()> {
+    let mut child = Command::new(python_exe)
+        .args(&[
+            "-c",
+            "import sys; compile(sys.stdin.read(), sys.argv[1], 'exec')",
+            filename,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(source.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()));
+    }
+
+    Ok(())
+}
+
 /// Describes a generic way to build a Python binary.
 ///
 /// Binary here means an executable or library containing or linking to a
@@ -64,6 +285,24 @@ pub trait PythonBinaryBuilder {
     /// returned executable.
     fn python_exe_path(&self) -> &Path;
 
+    /// Obtain the optimizations the Python distribution was built with.
+    ///
+    /// e.g. `noopt` or `pgo+lto`.
+    fn distribution_optimizations(&self) -> &str;
+
+    /// Log a hint if a release build embeds an unoptimized Python distribution.
+    ///
+    /// Distributions built without PGO or LTO are significantly slower. They
+    /// are fine for development but probably shouldn't be shipped.
+    fn hint_unoptimized_distribution(&self, logger: &slog::Logger, release: bool) {
+        if release && self.distribution_optimizations() == "noopt" {
+            info!(
+                logger,
+                "Python distribution is not built with PGO or LTO; consider using an optimized distribution for release builds"
+            );
+        }
+    }
+
     /// Obtain an iterator over all resource entries that will be embedded in the binary.
     ///
     /// This likely does not return extension modules that are statically linked
@@ -72,6 +311,20 @@ pub trait PythonBinaryBuilder {
         &'a self,
     ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a>;
 
+    /// Determine whether an embedded module is a package.
+    ///
+    /// Returns `None` if no module with the given name will be embedded. This
+    /// consults resources that were already added rather than the filesystem.
+    fn is_embedded_package(&self, name: &str) -> Option<bool>;
+
+    /// Obtain the names of C symbols the produced binary is known to export.
+    ///
+    /// This includes the initialization functions of builtin extension modules
+    /// and, if libpython's symbols are visible, the public embedding API of
+    /// libpython. It is not an exhaustive list of exported symbols, as that
+    /// would require reading object files.
+    fn exported_symbols(&self) -> Vec<String>;
+
     /// Obtain an iterator of extension modules that are built-in to the binary.
     ///
     /// These extension modules will be statically linked into the binary.
@@ -79,11 +332,81 @@ pub trait PythonBinaryBuilder {
     /// `iter_resources()` because they are stored in a different location.
     fn builtin_extension_module_names<'a>(&'a self) -> Box<dyn Iterator<Item = &'a String> + 'a>;
 
+    /// Find C accelerator modules and wrapping Python modules missing their counterpart.
+    ///
+    /// Returns a description of each pair in `ACCELERATOR_MODULE_PAIRS` having
+    /// only one of its members present. See `accelerator_pair_problems()`.
+    fn check_accelerator_pairs(&self) -> Vec<String> {
+        let mut names = self
+            .iter_resources()
+            .map(|(name, _)| name.clone())
+            .collect::<BTreeSet<_>>();
+        names.extend(self.builtin_extension_module_names().cloned());
+
+        accelerator_pair_problems(&names)
+    }
+
+    /// Summarize the packaging policy and the effects of applying it.
+    ///
+    /// Tallies cover resources considered when adding the distribution's
+    /// standard library via `add_full_stdlib()`.
+    fn effective_policy_report(&self) -> PolicyReport;
+
+    /// Verify that explicitly provided bytecode matches the module's embedded source.
+    ///
+    /// Modules having both source and precompiled bytecode have their source
+    /// recompiled and compared against the bytecode. Returns a description of
+    /// each inconsistency found.
+    fn verify_source_bytecode_consistency(&self, logger: &slog::Logger) -> Result<Vec<String>>;
+
+    /// Obtain a serializable record of resources that will be embedded in the binary.
+    fn resource_snapshot(&self) -> Result<ResourceSnapshot> {
+        ResourceSnapshot::from_resources(self.iter_resources())
+    }
+
+    /// Compare resources in this builder against a previously recorded snapshot.
+    fn diff_snapshot(&self, previous: &ResourceSnapshot) -> Result<ResourceDelta> {
+        Ok(self.resource_snapshot()?.diff(previous))
+    }
+
+    /// Find resources that aren't reachable from entry point modules.
+    ///
+    /// Python source of each resource is scanned for `import` statements and
+    /// the transitive closure of modules imported by `entrypoints` is computed.
+    /// Resources outside this closure are returned.
+    ///
+    /// This analysis is advisory, not authoritative. Modules imported dynamically
+    /// (e.g. via `importlib.import_module()`) or implicitly by the interpreter
+    /// during startup are reported as unreachable even though they may be needed.
+    fn unreachable_resources(
+        &self,
+        logger: &slog::Logger,
+        entrypoints: &[String],
+    ) -> Result<Vec<String>> {
+        let graph = resolve_imports(self.python_exe_path(), self.iter_resources())?;
+        let res = unreachable_modules(
+            &graph,
+            self.iter_resources().map(|(name, _)| name),
+            entrypoints,
+        );
+
+        if !res.is_empty() {
+            info!(
+                logger,
+                "{} resources are not reachable from {}; dynamically imported modules may be needed anyway",
+                res.len(),
+                entrypoints.join(", ")
+            );
+        }
+
+        Ok(res)
+    }
+
     /// Runs `pip install` using the binary builder's settings.
     ///
     /// Returns resources discovered as part of performing an install.
     fn pip_install(
-        &self,
+        &mut self,
         logger: &slog::Logger,
         verbose: bool,
         install_args: &[String],
@@ -92,20 +415,35 @@ pub trait PythonBinaryBuilder {
 
     /// Reads Python resources from the filesystem.
     fn read_package_root(
-        &self,
+        &mut self,
+        logger: &slog::Logger,
+        path: &Path,
+        packages: &[String],
+    ) -> Result<Vec<PythonResource>>;
+
+    /// Reads Python resources from a tar archive of a package tree.
+    ///
+    /// This is like `read_package_root()` except the package root is the
+    /// content of an uncompressed, gzip, or zstd compressed tar archive.
+    fn read_package_tarball(
+        &mut self,
         logger: &slog::Logger,
         path: &Path,
         packages: &[String],
     ) -> Result<Vec<PythonResource>>;
 
     /// Read Python resources from a populated virtualenv directory.
-    fn read_virtualenv(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>>;
+    fn read_virtualenv(
+        &mut self,
+        logger: &slog::Logger,
+        path: &Path,
+    ) -> Result<Vec<PythonResource>>;
 
     /// Runs `python setup.py install` using the binary builder's settings.
     ///
     /// Returns resources discovered as part of performing an install.
     fn setup_py_install(
-        &self,
+        &mut self,
         logger: &slog::Logger,
         package_path: &Path,
         verbose: bool,
@@ -197,6 +535,28 @@ pub trait PythonBinaryBuilder {
         }
     }
 
+    /// Add a package resource whose content is produced by a generator function.
+    ///
+    /// The generator is invoked immediately and its output is added as a
+    /// resource named `name` in `package` using the builder's resource policy.
+    fn add_generated_resource(
+        &mut self,
+        package: &str,
+        name: &str,
+        generator: &dyn Fn() -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let data = generator()
+            .with_context(|| format!("generating content for resource {} in {}", name, package))?;
+
+        self.add_package_resource(&PythonPackageResource {
+            leaf_package: package.to_string(),
+            relative_name: name.to_string(),
+            data: DataLocation::Memory(data),
+            is_stdlib: false,
+            is_test: false,
+        })
+    }
+
     /// Add a package distribution resource to be loaded from memory.
     fn add_in_memory_package_distribution_resource(
         &mut self,
@@ -292,18 +652,335 @@ pub trait PythonBinaryBuilder {
         extension_module_data: &PythonExtensionModule,
     ) -> Result<()>;
 
+    /// Add the Python distribution's standard library to this builder.
+    ///
+    /// This adds extension modules, module source, bytecode, and package
+    /// resources from the distribution. Each is subject to filtering by a
+    /// packaging policy: `policy_override` if given or this builder's
+    /// policy otherwise. Extension modules the policy would include but that
+    /// are broken on the target are excluded with a warning.
+    fn add_full_stdlib(
+        &mut self,
+        logger: &slog::Logger,
+        policy_override: Option<&PythonPackagingPolicy>,
+    ) -> Result<()>;
+
+    /// Add resources from multiple package roots.
+    ///
+    /// Each root is scanned via `read_package_root()`. Module source is added
+    /// along with bytecode compiled from it. If multiple roots provide the same
+    /// resource, an error is returned when `fail_on_conflict` is true. Otherwise
+    /// a warning is logged and the resource from the earliest root is used, as
+    /// if the roots were entries in `sys.path`.
+    fn add_package_roots(
+        &mut self,
+        logger: &slog::Logger,
+        roots: &[&Path],
+        packages: &[String],
+        fail_on_conflict: bool,
+    ) -> Result<()> {
+        let mut providers: BTreeMap<String, &Path> = BTreeMap::new();
+        let mut resources = Vec::new();
+
+        for root in roots {
+            let mut seen = BTreeSet::new();
+
+            for resource in self.read_package_root(logger, root, packages)? {
+                let name = resource.full_name();
+
+                if let Some(provider) = providers.get(&name) {
+                    let message = format!(
+                        "{} is provided by both {} and {}",
+                        name,
+                        provider.display(),
+                        root.display()
+                    );
+
+                    if fail_on_conflict {
+                        return Err(anyhow!(message));
+                    }
+
+                    self.add_warning(logger, &format!("{}; using the former", message));
+                    continue;
+                }
+
+                seen.insert(name);
+                resources.push(resource);
+            }
+
+            // Register names after processing the root so resources sharing a
+            // name within a root, such as source and bytecode, don't conflict.
+            for name in seen {
+                providers.insert(name, root);
+            }
+        }
+
+        for resource in resources {
+            match resource {
+                PythonResource::ModuleSource(module) => {
+                    self.add_module_source(&module)?;
+                    self.add_module_bytecode(
+                        &module.as_bytecode_module(BytecodeOptimizationLevel::Zero),
+                    )?;
+                }
+                PythonResource::ModuleBytecodeRequest(module) => {
+                    self.add_module_bytecode(&module)?;
+                }
+                PythonResource::Resource(resource) => {
+                    self.add_package_resource(&resource)?;
+                }
+                PythonResource::DistributionResource(resource) => {
+                    self.add_package_distribution_resource(&resource)?;
+                }
+                PythonResource::ExtensionModuleDynamicLibrary(em) => {
+                    self.add_dynamic_extension_module(&em)?;
+                }
+                PythonResource::ExtensionModuleStaticallyLinked(em) => {
+                    self.add_static_extension_module(&em)?;
+                }
+                // Bytecode is compiled from source instead.
+                PythonResource::ModuleBytecode(_)
+                | PythonResource::EggFile(_)
+                | PythonResource::PathExtension(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
     /// Filter embedded resources against names in files.
     ///
     /// `files` is files to read names from.
     ///
     /// `glob_patterns` is file patterns of files to read names from.
+    ///
+    /// `exclude_regex_patterns` is regular expressions. Resources whose dotted
+    /// name matches any of them are removed even if their name was read.
     fn filter_resources_from_files(
         &mut self,
         logger: &slog::Logger,
         files: &[&Path],
         glob_patterns: &[&str],
+        exclude_regex_patterns: &[&str],
     ) -> Result<()>;
 
+    /// Change the install prefix of resources added with a relative path.
+    ///
+    /// All relative path resources recorded with prefix `old` will be installed
+    /// with prefix `new` instead. Returns the number of resources changed.
+    fn rewrite_relative_prefix(&mut self, old: &str, new: &str) -> Result<usize>;
+
+    /// Set whether a resource should be loaded eagerly at run-time.
+    ///
+    /// All resources are loaded lazily by default. The setting is recorded in
+    /// the packed resources data as a hint to the run-time importer.
+    fn set_resource_eager_load(&mut self, name: &str, eager: bool) -> Result<()>;
+
+    /// Set the order of resources in the packed resources data.
+    ///
+    /// Resources are written in the order given, followed by remaining
+    /// resources in the default order. Placing frequently imported modules
+    /// first improves locality when resources are loaded at run-time.
+    fn set_resource_priority(&mut self, names: &[String]);
+
+    /// Set whether a shared library extension modules depend on is provided by the host.
+    ///
+    /// By default, shared library dependencies of extension modules that aren't
+    /// linked into libpython are bundled. Marking a library such as a ubiquitous
+    /// system library as host provided avoids bundling it. This only affects
+    /// extension modules added afterwards.
+    fn set_library_host_provided(&mut self, name: &str, host_provided: bool);
+
+    /// Exclude an object file from the link of libpython.
+    ///
+    /// `path` is the path of the object file relative to the Python distribution.
+    /// This is an escape hatch for working around duplicate symbols and excluding
+    /// object files may break functionality.
+    fn exclude_object_file(&mut self, logger: &slog::Logger, path: &Path) -> Result<()>;
+
+    /// Set a prefix to apply to the names of generated build artifacts.
+    ///
+    /// This allows artifacts from multiple builders to be linked into the same
+    /// binary without their names colliding. The prefix applies to generated
+    /// library and file names as well as to the functions and packed resources
+    /// static defined by the generated config source. The default prefix is
+    /// empty, which preserves the historical names.
+    ///
+    /// Errors if the prefix isn't valid. See `validate_artifact_name_prefix()`.
+    fn set_artifact_name_prefix(&mut self, prefix: &str) -> Result<()>;
+
+    /// Register extra arguments to pass to the linker when building for a target.
+    ///
+    /// Arguments are only used when the builder's target triple is
+    /// `target_triple`. They are recorded in `PythonLinkingInfo::linker_args`.
+    /// Arguments are passed via `RUSTFLAGS`, which is split on whitespace, so
+    /// they can't contain whitespace.
+    fn add_extra_linker_args(&mut self, target_triple: &str, args: &[String]) -> Result<()>;
+
+    /// Set a file to write a description of how libpython is linked to.
+    ///
+    /// The file lists the command compiling the generated `config.c`, the
+    /// object files archived into the static libpython, and the libraries,
+    /// search paths, and linker arguments emitted for the final link. This
+    /// aids reproducing and debugging link failures. Nothing is written if
+    /// libpython is linked dynamically.
+    fn set_link_description_path(&mut self, path: Option<&Path>);
+
+    /// Set the directory under which temporary build directories are created.
+    ///
+    /// Linking libpython copies and produces many object files. By default,
+    /// they are written to the system temporary directory, which may be too
+    /// small to hold them. The directory must have sufficient free space and
+    /// must exist. `None` restores the default.
+    fn set_build_temp_dir(&mut self, path: Option<&Path>);
+
+    /// Set whether to produce debug info that can be split from the binary.
+    ///
+    /// When enabled, generated code is compiled with debug info and linker
+    /// arguments appropriate for the target are used. Once the binary is
+    /// built, its debug info is moved to a separate file (a PDB, `.dSYM`
+    /// bundle, or ELF debug file) that can be archived for symbolicating
+    /// crashes of the stripped binary.
+    fn set_split_debug_info(&mut self, enabled: bool);
+
+    /// Set whether and how to compress the in-memory data of individual resources.
+    ///
+    /// Compression is disabled by default. See
+    /// `EmbeddedPythonResources::compress_resources()` for which resources
+    /// are compressed.
+    fn set_resource_compression(&mut self, options: Option<ResourceCompressionOptions>);
+
+    /// Set how to handle resources whose data no longer exists when packaging.
+    ///
+    /// Packaging fails by default.
+    fn set_unresolvable_resource_policy(&mut self, policy: UnresolvableResourcePolicy);
+
+    /// Set whether warnings fail the build.
+    ///
+    /// When enabled, producing binary data fails if any warnings were recorded
+    /// while collecting or packaging resources. The error lists every warning.
+    fn set_warnings_as_errors(&mut self, enabled: bool);
+
+    /// Log a warning and record it for `set_warnings_as_errors()`.
+    fn add_warning(&mut self, logger: &slog::Logger, message: &str);
+
+    /// Set the filename to write packed resources data to.
+    ///
+    /// When set, packed resources are written to a file next to the
+    /// produced binary and loaded at run-time rather than embedded in the
+    /// binary. This allows resources to be updated without relinking.
+    /// The artifact name prefix is prepended to `filename`. `None` restores
+    /// embedding.
+    fn set_packed_resources_sidecar(&mut self, filename: Option<&str>);
+
+    /// Set the Python module to run when the interpreter starts.
+    ///
+    /// The run configuration is independent of resources. So this can be
+    /// called repeatedly without having to re-add any resources.
+    fn set_run_module(&mut self, module: &str);
+
+    /// Set Python code to evaluate when the interpreter starts.
+    ///
+    /// Like `set_run_module()`, this only changes the run configuration.
+    fn set_run_code(&mut self, code: &str);
+
+    /// Set the fixed hash seed of the embedded interpreter.
+    ///
+    /// Errors if the seed is outside the range CPython accepts.
+    fn set_hash_seed(&mut self, seed: u64) -> Result<()>;
+
+    /// Set the recursion limit the embedded interpreter starts with.
+    ///
+    /// Errors if the limit is too small for the interpreter to function.
+    fn set_recursion_limit(&mut self, limit: u32) -> Result<()>;
+
+    /// Set the stack size, in bytes, of threads created by the embedded interpreter.
+    ///
+    /// Errors if the size is non-zero and below the minimum CPython accepts.
+    fn set_thread_stack_size(&mut self, size: u64) -> Result<()>;
+
+    /// Set whether the embedded interpreter enables `faulthandler` at startup.
+    ///
+    /// With it enabled, fatal signals such as segfaults in extension modules
+    /// print a Python traceback. Equivalent to setting `PYTHONFAULTHANDLER`.
+    fn set_faulthandler(&mut self, enabled: bool);
+
+    /// Set whether the embedded interpreter runs in Python Development Mode.
+    ///
+    /// This enables extra run-time checks. Equivalent to `-X dev`.
+    fn set_dev_mode(&mut self, enabled: bool);
+
+    /// Embed source code as a module and run it as `__main__` when the interpreter starts.
+    ///
+    /// The source is embedded as the `MAIN_MODULE_NAME` module. Registering
+    /// it as `__main__` would shadow the interpreter's own `__main__` module.
+    /// Running it mirrors `python -m` semantics, including
+    /// `__name__ == "__main__"`. The source is compiled with the builder's
+    /// Python interpreter to ensure it is valid before it is embedded.
+    fn set_main_module_source(&mut self, source: &str) -> Result<()> {
+        check_source_compiles(self.python_exe_path(), source, MAIN_MODULE_NAME)
+            .map_err(|e| anyhow!("__main__ module source failed to compile: {}", e))?;
+
+        let cache_tag = self.cache_tag().to_string();
+
+        self.add_module_source(&PythonModuleSource {
+            name: MAIN_MODULE_NAME.to_string(),
+            source: DataLocation::Memory(source.as_bytes().to_vec()),
+            is_package: false,
+            cache_tag: cache_tag.clone(),
+            is_stdlib: false,
+            is_test: false,
+        })?;
+        self.add_module_bytecode(&PythonModuleBytecodeFromSource {
+            name: MAIN_MODULE_NAME.to_string(),
+            source: DataLocation::Memory(source.as_bytes().to_vec()),
+            optimize_level: BytecodeOptimizationLevel::Zero,
+            is_package: false,
+            cache_tag,
+            is_stdlib: false,
+            is_test: false,
+        })?;
+
+        self.set_run_module(MAIN_MODULE_NAME);
+
+        Ok(())
+    }
+
+    /// Set Python code to run before any module is imported.
+    ///
+    /// This allows registering custom meta path finders ahead of or after
+    /// PyOxidizer's importer. See `pyembed::OxidizedPythonInterpreterConfig`
+    /// for when the code runs and what it can safely do. Errors if the code
+    /// does not compile.
+    fn set_pre_import_code(&mut self, code: &str) -> Result<()>;
+
+    /// Obtain the `sys.path` entries the embedded interpreter will be configured with.
+    ///
+    /// `$ORIGIN` resolves to the directory of the binary at run-time.
+    fn sys_paths(&self) -> Vec<String>;
+
+    /// Add `sys.path` entries for the prefixes of relative-path modules.
+    ///
+    /// Every prefix that modules were installed under relative to the binary
+    /// gets a `$ORIGIN/<prefix>` entry and the filesystem importer is enabled,
+    /// so those modules can be found at run-time. Returns the entries for all
+    /// such prefixes.
+    fn add_relative_path_sys_paths(&mut self) -> Vec<String>;
+
+    /// Add an interpreter configuration that can be selected at run-time.
+    ///
+    /// The produced binary uses the builder's configuration unless the
+    /// environment variable set by `set_config_selector_env()` names another
+    /// configuration. All configurations share the same resources. Errors if
+    /// the name isn't made of ASCII alphanumerics, `-`, and `_`, or if the
+    /// configuration is invalid.
+    fn add_named_config(&mut self, name: &str, config: EmbeddedPythonConfig) -> Result<()>;
+
+    /// Set the environment variable selecting a named configuration at run-time.
+    ///
+    /// Defaults to `PYOXIDIZER_PYTHON_CONFIG`.
+    fn set_config_selector_env(&mut self, name: &str) -> Result<()>;
+
     /// Whether the binary requires the jemalloc library.
     fn requires_jemalloc(&self) -> bool;
 
@@ -336,6 +1013,15 @@ pub struct PythonLinkingInfo {
 
     /// Lines that need to be emitted from a Cargo build script.
     pub cargo_metadata: Vec<String>,
+
+    /// Arguments to pass to the linker when linking the final binary.
+    ///
+    /// These can't be emitted as Cargo build script lines: build scripts of
+    /// library crates like `pyembed` can't pass linker arguments to the
+    /// binaries depending on them. PyOxidizer passes them to rustc via
+    /// `RUSTFLAGS` when it invokes Cargo. Binaries built by running
+    /// `cargo build` directly don't receive them.
+    pub linker_args: Vec<String>,
 }
 
 /// Represents serialized data embedded in binaries for loading Python resources.
@@ -400,16 +1086,91 @@ pub struct EmbeddedPythonBinaryData {
 
     /// Rust target triple for the target we are building for.
     pub target: String,
+
+    /// Prefix to apply to the names of written artifacts.
+    pub artifact_name_prefix: String,
+
+    /// Filename of a file next to the binary holding packed resources.
+    ///
+    /// If set, packed resources are installed as this file and loaded from
+    /// it at run-time instead of being embedded in the binary. The artifact
+    /// name prefix is already applied.
+    pub packed_resources_sidecar: Option<String>,
+
+    /// Additional interpreter configurations selectable at run-time, keyed by name.
+    pub named_configs: BTreeMap<String, EmbeddedPythonConfig>,
+
+    /// Environment variable selecting a named configuration at run-time.
+    pub config_selector_env: String,
+
+    /// Whether debug info of the built binary should be split into separate files.
+    pub split_debug_info: bool,
 }
 
 impl EmbeddedPythonBinaryData {
+    /// Change what the embedded interpreter runs when it starts.
+    ///
+    /// Call `write_config_rs()` afterwards to update an existing build
+    /// directory without rewriting resources or libraries.
+    pub fn set_run_mode(&mut self, run_mode: RunMode) {
+        self.config.run_mode = run_mode;
+    }
+
+    /// Write the `{prefix}default_python_config.rs` file derived from the config.
+    pub fn write_config_rs(&self, dest_dir: &Path) -> Result<PathBuf> {
+        let embedded_resources =
+            dest_dir.join(format!("{}packed-resources", self.artifact_name_prefix));
+
+        let packed_resources = packed_resources_symbol(&self.artifact_name_prefix);
+
+        let config_rs_data = derive_python_config(
+            &self.config,
+            &packed_resources,
+            self.packed_resources_sidecar.as_deref(),
+        );
+        let named_configs_rs = self
+            .named_configs
+            .iter()
+            .map(|(name, config)| {
+                (
+                    name.clone(),
+                    derive_python_config(
+                        config,
+                        &packed_resources,
+                        self.packed_resources_sidecar.as_deref(),
+                    ),
+                )
+            })
+            .collect();
+
+        let config_rs = dest_dir.join(format!(
+            "{}default_python_config.rs",
+            self.artifact_name_prefix
+        ));
+        write_default_python_config_rs(
+            &config_rs,
+            &self.artifact_name_prefix,
+            if self.packed_resources_sidecar.is_some() {
+                None
+            } else {
+                Some(&embedded_resources)
+            },
+            &config_rs_data,
+            &named_configs_rs,
+            &self.config_selector_env,
+        )?;
+
+        Ok(config_rs)
+    }
+
     /// Write out files needed to link a binary.
     pub fn write_files(&self, dest_dir: &Path) -> Result<EmbeddedPythonBinaryPaths> {
-        let module_names = dest_dir.join("py-module-names");
+        let module_names = dest_dir.join(format!("{}py-module-names", self.artifact_name_prefix));
         let mut fh = File::create(&module_names)?;
         fh.write_all(&self.resources.module_names)?;
 
-        let embedded_resources = dest_dir.join("packed-resources");
+        let embedded_resources =
+            dest_dir.join(format!("{}packed-resources", self.artifact_name_prefix));
         let mut fh = File::create(&embedded_resources)?;
         fh.write_all(&self.resources.resources)?;
 
@@ -431,9 +1192,7 @@ impl EmbeddedPythonBinaryData {
             None
         };
 
-        let config_rs_data = derive_python_config(&self.config, &embedded_resources);
-        let config_rs = dest_dir.join("default_python_config.rs");
-        write_default_python_config_rs(&config_rs, &config_rs_data)?;
+        let config_rs = self.write_config_rs(dest_dir)?;
 
         let mut cargo_metadata_lines = Vec::new();
         cargo_metadata_lines.extend(self.linking_info.cargo_metadata.clone());
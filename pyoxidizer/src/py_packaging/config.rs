@@ -6,6 +6,19 @@
 Configuring a Python interpreter.
 */
 
+use anyhow::{anyhow, Result};
+
+/// Minimum recursion limit that can be configured.
+///
+/// Lower values can prevent the interpreter from initializing.
+pub const MINIMUM_RECURSION_LIMIT: u32 = 100;
+
+/// Minimum non-zero thread stack size, in bytes, accepted by CPython.
+pub const MINIMUM_THREAD_STACK_SIZE: u64 = 32768;
+
+/// Default environment variable selecting a named configuration at run-time.
+pub const DEFAULT_CONFIG_SELECTOR_ENV: &str = "PYOXIDIZER_PYTHON_CONFIG";
+
 /// Determine the default raw allocator for a target triple.
 pub fn default_raw_allocator(target_triple: &str) -> RawAllocator {
     // Jemalloc doesn't work on Windows.
@@ -67,10 +80,16 @@ pub struct EmbeddedPythonConfig {
     pub sys_paths: Vec<String>,
     pub terminfo_resolution: TerminfoResolution,
     pub use_hash_seed: bool,
+    pub hash_seed: Option<u64>,
+    pub recursion_limit: Option<u32>,
+    pub thread_stack_size: Option<u64>,
+    pub fault_handler: bool,
+    pub development_mode: bool,
     pub user_site_directory: bool,
     pub verbose: i32,
     pub write_bytecode: bool,
     pub write_modules_directory_env: Option<String>,
+    pub pre_import_code: Option<String>,
 }
 
 impl Default for EmbeddedPythonConfig {
@@ -90,6 +109,11 @@ impl Default for EmbeddedPythonConfig {
             stdio_encoding_errors: None,
             unbuffered_stdio: false,
             use_hash_seed: false,
+            hash_seed: None,
+            recursion_limit: None,
+            thread_stack_size: None,
+            fault_handler: false,
+            development_mode: false,
             verbose: 0,
             filesystem_importer: false,
             site_import: false,
@@ -102,6 +126,147 @@ impl Default for EmbeddedPythonConfig {
             user_site_directory: false,
             write_bytecode: false,
             write_modules_directory_env: None,
+            pre_import_code: None,
+        }
+    }
+}
+
+impl EmbeddedPythonConfig {
+    /// Set the fixed seed used to randomize hashes of str and bytes objects.
+    ///
+    /// CPython accepts seeds in the range `[0, 4294967295]`.
+    pub fn set_hash_seed(&mut self, seed: u64) -> Result<()> {
+        if seed > u64::from(u32::MAX) {
+            return Err(anyhow!(
+                "hash seed {} must be in the range [0, {}]",
+                seed,
+                u32::MAX
+            ));
+        }
+
+        self.hash_seed = Some(seed);
+
+        Ok(())
+    }
+
+    /// Set the maximum depth of the Python interpreter stack.
+    ///
+    /// This is equivalent to calling `sys.setrecursionlimit()`.
+    pub fn set_recursion_limit(&mut self, limit: u32) -> Result<()> {
+        if limit < MINIMUM_RECURSION_LIMIT || limit > i32::MAX as u32 {
+            return Err(anyhow!(
+                "recursion limit {} must be in the range [{}, {}]",
+                limit,
+                MINIMUM_RECURSION_LIMIT,
+                i32::MAX
+            ));
+        }
+
+        self.recursion_limit = Some(limit);
+
+        Ok(())
+    }
+
+    /// Set the stack size, in bytes, of threads created by Python.
+    ///
+    /// This is equivalent to calling `threading.stack_size()`. 0 uses the
+    /// platform default.
+    pub fn set_thread_stack_size(&mut self, size: u64) -> Result<()> {
+        if size != 0 && size < MINIMUM_THREAD_STACK_SIZE {
+            return Err(anyhow!(
+                "thread stack size {} must be 0 or at least {}",
+                size,
+                MINIMUM_THREAD_STACK_SIZE
+            ));
+        }
+
+        self.thread_stack_size = Some(size);
+
+        Ok(())
+    }
+
+    /// Verify settings are within the ranges accepted by their setters.
+    ///
+    /// This is useful for instances whose fields were assigned directly.
+    pub fn validate(&self) -> Result<()> {
+        let mut config = self.clone();
+
+        if let Some(seed) = self.hash_seed {
+            config.set_hash_seed(seed)?;
+        }
+        if let Some(limit) = self.recursion_limit {
+            config.set_recursion_limit(limit)?;
+        }
+        if let Some(size) = self.thread_stack_size {
+            config.set_thread_stack_size(size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensure resources installed under a path relative to the binary are on `sys.path`.
+    ///
+    /// `$ORIGIN/<prefix>` is added to `sys_paths` if it isn't already present
+    /// and the filesystem importer, which consults `sys.path`, is enabled.
+    /// Returns the `sys.path` entry for `prefix`.
+    pub fn add_relative_path_sys_path(&mut self, prefix: &str) -> String {
+        let prefix = prefix.trim_end_matches('/');
+        let entry = if prefix.is_empty() {
+            "$ORIGIN".to_string()
+        } else {
+            format!("$ORIGIN/{}", prefix)
+        };
+
+        if !self.sys_paths.contains(&entry) {
+            self.sys_paths.push(entry.clone());
         }
+        self.filesystem_importer = true;
+
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tunable_ranges() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        config.set_hash_seed(42)?;
+        assert_eq!(config.hash_seed, Some(42));
+        assert!(config.set_hash_seed(u64::from(u32::MAX) + 1).is_err());
+
+        config.set_recursion_limit(10000)?;
+        assert_eq!(config.recursion_limit, Some(10000));
+        assert!(config.set_recursion_limit(10).is_err());
+
+        config.set_thread_stack_size(0)?;
+        config.set_thread_stack_size(1048576)?;
+        assert_eq!(config.thread_stack_size, Some(1048576));
+        assert!(config.set_thread_stack_size(1024).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = EmbeddedPythonConfig::default();
+        assert!(config.validate().is_ok());
+
+        config.recursion_limit = Some(10);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_add_relative_path_sys_path() {
+        let mut config = EmbeddedPythonConfig::default();
+
+        assert_eq!(config.add_relative_path_sys_path("lib"), "$ORIGIN/lib");
+        assert_eq!(config.add_relative_path_sys_path("lib/"), "$ORIGIN/lib");
+        assert_eq!(config.add_relative_path_sys_path(""), "$ORIGIN");
+        assert_eq!(config.sys_paths, vec!["$ORIGIN/lib", "$ORIGIN"]);
+        assert!(config.filesystem_importer);
     }
 }
@@ -10,6 +10,7 @@ use {
     super::binary::{LibpythonLinkMode, PythonBinaryBuilder},
     super::config::EmbeddedPythonConfig,
     super::standalone_distribution::StandaloneDistribution,
+    super::warnings::BuildWarnings,
     crate::python_distributions::PYTHON_DISTRIBUTIONS,
     anyhow::{anyhow, Context, Result},
     fs2::FileExt,
@@ -136,6 +137,16 @@ pub trait PythonDistribution {
         &'a self,
     ) -> Box<dyn Iterator<Item = &'a PythonExtensionModule> + 'a>;
 
+    /// Obtain the number of distinct extension modules in this distribution.
+    ///
+    /// Variants of the same extension module are counted once.
+    fn extension_module_count(&self) -> usize;
+
+    /// Obtain the number of extension modules that are builtins by default.
+    ///
+    /// An extension module is counted if any of its variants is a default builtin.
+    fn default_builtin_extension_count(&self) -> usize;
+
     /// Obtain `SourceModule` instances present in this distribution.
     fn source_modules(&self) -> Result<Vec<PythonModuleSource>>;
 
@@ -169,10 +180,12 @@ pub trait PythonDistribution {
     ///
     /// We will throw away resources that aren't compatible with us. For
     /// example, on statically linked Windows distributions, dynamically linked
-    /// extension module files are ignored.
+    /// extension module files are ignored. Ignored resources the caller likely
+    /// expected to be usable are reported to `warnings`.
     fn filter_compatible_python_resources(
         &self,
         logger: &slog::Logger,
+        warnings: &mut BuildWarnings,
         resources: &[PythonResource],
     ) -> Result<Vec<PythonResource>>;
 }
@@ -523,7 +536,7 @@ mod tests {
         let logger = get_logger()?;
         let target = env!("HOST");
 
-        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let temp_dir = crate::environment::new_temp_dir("test")?;
 
         default_distribution(
             &logger,
@@ -7,27 +7,33 @@ Embedded Python resources in a binary.
 */
 
 use {
-    super::filtering::{filter_btreemap, resolve_resource_names_from_files},
+    super::filtering::{
+        compile_resource_name_patterns, filter_btreemap, resolve_resource_names_from_files,
+        resource_name_included,
+    },
+    super::warnings::BuildWarnings,
     crate::app_packaging::resource::{FileContent, FileManifest},
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     python_packaging::policy::PythonResourcesPolicy,
     python_packaging::resource::{
-        DataLocation, PythonExtensionModule, PythonModuleBytecodeFromSource, PythonModuleSource,
-        PythonPackageDistributionResource, PythonPackageResource,
+        DataLocation, PythonExtensionModule, PythonModuleBytecode, PythonModuleBytecodeFromSource,
+        PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
     },
     python_packaging::resource_collection::{
         ConcreteResourceLocation, PrePackagedResource, PreparedPythonResources,
         PythonResourceCollector,
     },
+    serde::{Deserialize, Serialize},
     slog::{info, warn},
+    std::borrow::Cow,
     std::collections::{BTreeMap, BTreeSet},
     std::io::Write,
     std::iter::FromIterator,
-    std::path::Path,
+    std::path::{Path, PathBuf},
 };
 
 /// Holds state necessary to link an extension module into libpython.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub struct ExtensionModuleBuildState {
     /// Extension C initialization function.
     pub init_fn: Option<String>,
@@ -55,10 +61,81 @@ pub struct ExtensionModuleBuildState {
 ///
 /// This collection holds resources before packaging. This type is
 /// transformed to `EmbeddedPythonResources` as part of packaging.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PrePackagedResources {
     collector: PythonResourceCollector,
     extension_module_states: BTreeMap<String, ExtensionModuleBuildState>,
+    priority: Vec<String>,
+    /// Names of shared libraries expected to be provided by the host.
+    #[serde(default)]
+    host_provided_libraries: BTreeSet<String>,
+    /// Locations each module name was registered under.
+    #[serde(default)]
+    module_locations: BTreeMap<String, BTreeSet<String>>,
+    /// Whether modules registered under conflicting locations are an error.
+    #[serde(default)]
+    strict_conflicts: bool,
+}
+
+/// Describe a resource location for conflict reporting.
+fn location_label(location: &ConcreteResourceLocation) -> String {
+    match location {
+        ConcreteResourceLocation::InMemory => "in-memory".to_string(),
+        ConcreteResourceLocation::RelativePath(prefix) => format!("relative-path:{}", prefix),
+    }
+}
+
+/// Whether a resource has any data to be installed at the given location.
+fn resource_has_location(
+    resource: &PrePackagedResource,
+    location: &ConcreteResourceLocation,
+) -> bool {
+    match location {
+        ConcreteResourceLocation::InMemory => {
+            resource.in_memory_source.is_some()
+                || resource.in_memory_bytecode.is_some()
+                || resource.in_memory_bytecode_opt1.is_some()
+                || resource.in_memory_bytecode_opt2.is_some()
+                || resource.in_memory_extension_module_shared_library.is_some()
+                || resource.in_memory_resources.is_some()
+                || resource.in_memory_distribution_resources.is_some()
+                || resource.in_memory_shared_library.is_some()
+        }
+        ConcreteResourceLocation::RelativePath(prefix) => {
+            let mut prefixes = Vec::new();
+
+            if let Some((prefix, _)) = &resource.relative_path_module_source {
+                prefixes.push(prefix);
+            }
+            for (prefix, _, _) in [
+                &resource.relative_path_bytecode,
+                &resource.relative_path_bytecode_opt1,
+                &resource.relative_path_bytecode_opt2,
+            ]
+            .iter()
+            .filter_map(|x| x.as_ref())
+            {
+                prefixes.push(prefix);
+            }
+            if let Some((prefix, _, _)) = &resource.relative_path_extension_module_shared_library {
+                prefixes.push(prefix);
+            }
+            for resources in [
+                &resource.relative_path_package_resources,
+                &resource.relative_path_distribution_resources,
+            ]
+            .iter()
+            .filter_map(|x| x.as_ref())
+            {
+                prefixes.extend(resources.values().map(|(prefix, _, _)| prefix));
+            }
+            if let Some((prefix, _)) = &resource.relative_path_shared_library {
+                prefixes.push(prefix);
+            }
+
+            prefixes.into_iter().any(|x| x == prefix)
+        }
+    }
 }
 
 impl PrePackagedResources {
@@ -66,13 +143,117 @@ impl PrePackagedResources {
         Self {
             collector: PythonResourceCollector::new(policy, cache_tag),
             extension_module_states: BTreeMap::new(),
+            priority: Vec::new(),
+            host_provided_libraries: BTreeSet::new(),
+            module_locations: BTreeMap::new(),
+            strict_conflicts: false,
+        }
+    }
+
+    /// Set whether modules registered under conflicting locations are an error.
+    ///
+    /// A module registered both in-memory and relative to the filesystem (or
+    /// under different relative path prefixes) is ambiguous. By default,
+    /// `package()` emits a warning for each such module. When strict, it
+    /// fails instead.
+    pub fn set_strict_conflicts(&mut self, strict: bool) {
+        self.strict_conflicts = strict;
+    }
+
+    fn record_module_location(&mut self, name: &str, location: &ConcreteResourceLocation) {
+        self.module_locations
+            .entry(name.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(location_label(location));
+    }
+
+    /// Obtain modules registered under more than one location.
+    ///
+    /// Keys are module names and values are descriptions of the locations
+    /// the module was registered under. Modules that have since been
+    /// filtered out of the collection are not reported.
+    pub fn location_conflicts(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let names = self
+            .collector
+            .iter_resources()
+            .map(|(name, _)| name.as_str())
+            .collect::<BTreeSet<_>>();
+
+        self.module_locations
+            .iter()
+            .filter(|(name, locations)| locations.len() > 1 && names.contains(name.as_str()))
+            .map(|(name, locations)| (name.clone(), locations.clone()))
+            .collect()
+    }
+
+    /// Set whether a shared library extension modules depend on is provided by the host.
+    ///
+    /// Shared library dependencies of extension modules loaded from memory or
+    /// from relative paths are bundled by default. Host provided libraries
+    /// aren't bundled and must be found by the host's dynamic loader at
+    /// run-time. This only affects extension modules added afterwards.
+    pub fn set_library_host_provided(&mut self, name: &str, host_provided: bool) {
+        if host_provided {
+            self.host_provided_libraries.insert(name.to_string());
+        } else {
+            self.host_provided_libraries.remove(name);
         }
     }
 
+    /// Remove all resources from this collection.
+    ///
+    /// Settings, such as libraries marked as host provided, the priority of
+    /// resources, and whether location conflicts are errors, are retained.
+    pub fn clear(&mut self) -> Result<()> {
+        self.collector.filter_resources_mut(|_| false)?;
+        self.extension_module_states.clear();
+        self.module_locations.clear();
+
+        Ok(())
+    }
+
+    /// Write the state of this instance to a file.
+    ///
+    /// Filesystem-backed data is recorded by path and is not copied into the
+    /// file. So referenced files must still exist when the file is read back
+    /// via `deserialize_from()`.
+    pub fn serialize_to(&self, path: &Path) -> Result<()> {
+        let fh =
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        serde_json::to_writer(std::io::BufWriter::new(fh), self)
+            .with_context(|| format!("serializing resources to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Construct an instance from a file written by `serialize_to()`.
+    pub fn deserialize_from(path: &Path) -> Result<Self> {
+        let fh =
+            std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+
+        serde_json::from_reader(std::io::BufReader::new(fh))
+            .with_context(|| format!("deserializing resources from {}", path.display()))
+    }
+
     pub fn iter_resources(&self) -> impl Iterator<Item = (&String, &PrePackagedResource)> {
         self.collector.iter_resources()
     }
 
+    /// Iterate over resources having data at the given location.
+    ///
+    /// A resource with data in multiple locations (e.g. source in memory and
+    /// bytecode relative to the filesystem) is emitted for each of them.
+    /// For relative path locations, only resources under the same prefix
+    /// are emitted.
+    pub fn iter_resources_by_location<'a>(
+        &'a self,
+        location: &'a ConcreteResourceLocation,
+    ) -> impl Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a {
+        self.collector
+            .iter_resources()
+            .filter(move |(_, resource)| resource_has_location(resource, location))
+    }
+
     /// Obtain the names of extension modules that will be compiled into libpython.
     ///
     /// These extension modules are statically linked into the binary. They
@@ -88,7 +269,23 @@ impl PrePackagedResources {
         module: &PythonModuleSource,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.collector.add_python_module_source(module, location)
+        self.collector.add_python_module_source(module, location)?;
+        self.record_module_location(&module.name, location);
+
+        Ok(())
+    }
+
+    /// Add precompiled Python module bytecode to the collection.
+    pub fn add_python_module_bytecode(
+        &mut self,
+        module: &PythonModuleBytecode,
+        location: &ConcreteResourceLocation,
+    ) -> Result<()> {
+        self.collector
+            .add_python_module_bytecode(module, location)?;
+        self.record_module_location(&module.name, location);
+
+        Ok(())
     }
 
     /// Add Python module bytecode to be derived from source code to the collection.
@@ -98,7 +295,10 @@ impl PrePackagedResources {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.collector
-            .add_python_module_bytecode_from_source(module, location)
+            .add_python_module_bytecode_from_source(module, location)?;
+        self.record_module_location(&module.name, location);
+
+        Ok(())
     }
 
     /// Add Python package resource data to the collection.
@@ -199,6 +399,10 @@ impl PrePackagedResources {
         let mut depends = Vec::new();
 
         for link in &module.link_libraries {
+            if self.host_provided_libraries.contains(&link.name) {
+                continue;
+            }
+
             if let Some(shared_library) = &link.dynamic_library {
                 // Add a resource holding the shared library data.
                 self.collector.add_shared_library(
@@ -220,6 +424,7 @@ impl PrePackagedResources {
                 &data,
                 &depends_refs,
             )?;
+        self.record_module_location(&module.name, &ConcreteResourceLocation::InMemory);
 
         Ok(())
     }
@@ -239,13 +444,21 @@ impl PrePackagedResources {
 
         self.collector
             .add_relative_path_python_extension_module(&module, prefix)?;
+        self.record_module_location(
+            &module.name,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        );
 
         for link in &module.link_libraries {
+            if self.host_provided_libraries.contains(&link.name) {
+                continue;
+            }
+
             // Install dynamic library dependencies next to extension module.
             //
             // On Windows, this should "just work" since the opening DLL's directory
-            // is searched for dependencies.
-            // TODO this logic likely needs to be expanded.
+            // is searched for dependencies. On ELF targets, the binary needs an
+            // rpath entry for the prefix. See `LibpythonLinkingInfo::rpath_entries()`.
             if let Some(shared_library) = &link.dynamic_library {
                 self.collector.add_shared_library(
                     &link.name,
@@ -292,16 +505,22 @@ impl PrePackagedResources {
     }
 
     /// Add an extension module shared library that should be imported from memory.
+    ///
+    /// `depends` holds names of shared libraries the extension module depends on.
+    /// These are recorded on the resource but not added: the shared libraries
+    /// themselves need to be added separately so they can be loaded from memory.
     pub fn add_in_memory_extension_module_shared_library(
         &mut self,
         module: &str,
         is_package: bool,
         data: &[u8],
+        depends: &[&str],
     ) -> Result<()> {
         self.collector
-            .add_in_memory_python_extension_module_shared_library(module, is_package, data, &[])?;
-
-        // TODO add shared library dependencies to be packaged as well.
+            .add_in_memory_python_extension_module_shared_library(
+                module, is_package, data, depends,
+            )?;
+        self.record_module_location(module, &ConcreteResourceLocation::InMemory);
 
         Ok(())
     }
@@ -313,22 +532,33 @@ impl PrePackagedResources {
         prefix: &str,
     ) -> Result<()> {
         self.collector
-            .add_relative_path_python_extension_module(em, prefix)
+            .add_relative_path_python_extension_module(em, prefix)?;
+        self.record_module_location(
+            &em.name,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        );
+
+        Ok(())
     }
 
     /// Filter the entities in this instance against names in files.
+    ///
+    /// Entities are kept if their name is read from the files and doesn't
+    /// match any of the regular expressions in `exclude_regex_patterns`.
     pub fn filter_from_files(
         &mut self,
         logger: &slog::Logger,
         files: &[&Path],
         glob_patterns: &[&str],
+        exclude_regex_patterns: &[&str],
     ) -> Result<()> {
         let resource_names = resolve_resource_names_from_files(files, glob_patterns)?;
+        let exclude_patterns = compile_resource_name_patterns(exclude_regex_patterns)?;
 
         warn!(logger, "filtering module entries");
 
         self.collector.filter_resources_mut(|resource| {
-            if !resource_names.contains(&resource.name) {
+            if !resource_name_included(&resource.name, &resource_names, &exclude_patterns) {
                 warn!(logger, "removing {}", resource.name);
                 false
             } else {
@@ -337,24 +567,133 @@ impl PrePackagedResources {
         })?;
 
         warn!(logger, "filtering embedded extension modules");
-        filter_btreemap(logger, &mut self.extension_module_states, &resource_names);
+        filter_btreemap(
+            logger,
+            &mut self.extension_module_states,
+            &resource_names,
+            &exclude_patterns,
+        );
 
         Ok(())
     }
 
+    /// Retain only resources for which a predicate returns true.
+    ///
+    /// The predicate receives the resource name and the resource. Removed
+    /// extension modules are also removed from the extension module build state.
+    ///
+    /// Returns the number of resources removed.
+    pub fn retain_resources<F>(&mut self, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&str, &PrePackagedResource) -> bool,
+    {
+        let removed = self
+            .collector
+            .iter_resources()
+            .filter_map(|(name, resource)| {
+                if f(name, resource) {
+                    None
+                } else {
+                    Some(name.clone())
+                }
+            })
+            .collect::<BTreeSet<_>>();
+
+        self.collector
+            .filter_resources_mut(|resource| !removed.contains(&resource.name))?;
+
+        for name in &removed {
+            self.extension_module_states.remove(name);
+        }
+
+        Ok(removed.len())
+    }
+
+    /// Obtain (name, init function) pairs of extension modules built into libpython.
+    pub fn builtin_extensions(&self) -> Vec<(String, String)> {
+        self.extension_module_states
+            .iter()
+            .filter_map(|(name, state)| {
+                state
+                    .init_fn
+                    .as_ref()
+                    .map(|init_fn| (name.clone(), init_fn.clone()))
+            })
+            .collect()
+    }
+
+    /// Remove filesystem-backed data of resources that no longer exists.
+    ///
+    /// Only the missing data is removed. Resources left without any data are
+    /// removed entirely.
+    ///
+    /// Returns the names of changed resources.
+    pub fn remove_unresolvable_resources(&mut self) -> Result<Vec<String>> {
+        let changed = self.collector.remove_unresolvable_data();
+
+        for name in &changed {
+            if !self
+                .collector
+                .iter_resources()
+                .any(|(resource_name, _)| resource_name == name)
+            {
+                self.extension_module_states.remove(name);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Change the prefix of resources installed relative to the produced binary.
+    ///
+    /// Returns the number of resources changed.
+    pub fn rewrite_relative_prefix(&mut self, old: &str, new: &str) -> Result<usize> {
+        self.collector.rewrite_relative_prefix(old, new)
+    }
+
+    /// Set whether a resource should be loaded eagerly at run-time.
+    pub fn set_eager_load(&mut self, name: &str, eager: bool) -> Result<()> {
+        self.collector.set_eager_load(name, eager)
+    }
+
+    /// Set the order in which resources are written to packed resources data.
+    ///
+    /// Named resources are written first, in the order given. Remaining
+    /// resources follow in the default order.
+    pub fn set_priority(&mut self, names: &[String]) {
+        self.priority = names.to_vec();
+    }
+
+    /// Replace bytecode that would be compiled from source with that source.
+    ///
+    /// Returns the number of resources changed.
+    pub fn replace_bytecode_with_source(&mut self) -> usize {
+        self.collector.replace_bytecode_with_source()
+    }
+
+    /// Find modules whose precompiled bytecode doesn't match their source.
+    pub fn find_source_bytecode_mismatches(&self, python_exe: &Path) -> Result<Vec<String>> {
+        self.collector.find_source_bytecode_mismatches(python_exe)
+    }
+
     /// Transform this instance into embedded resources data.
     ///
     /// This method performs actions necessary to produce entities which will allow the
     /// resources to be embedded in a binary.
+    ///
+    /// Warnings encountered are recorded in `warnings`. Modules registered
+    /// under conflicting locations are reported as warnings, or cause an
+    /// error if strict conflict checking is enabled.
     pub fn package(
         &self,
         logger: &slog::Logger,
         python_exe: &Path,
+        warnings: &mut BuildWarnings,
     ) -> Result<EmbeddedPythonResources> {
         let mut file_seen = false;
         for module in self.collector.find_dunder_file()? {
             file_seen = true;
-            warn!(logger, "warning: {} contains __file__", module);
+            warnings.warn(logger, &format!("{} contains __file__", module));
         }
 
         if file_seen {
@@ -369,13 +708,227 @@ impl PrePackagedResources {
             );
         }
 
+        let conflicts = self
+            .location_conflicts()
+            .into_iter()
+            .map(|(name, locations)| {
+                format!(
+                    "{} registered under conflicting locations: {}",
+                    name,
+                    locations.into_iter().collect::<Vec<_>>().join(", ")
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if self.strict_conflicts && !conflicts.is_empty() {
+            return Err(anyhow!(
+                "modules registered under conflicting locations:\n{}",
+                conflicts.join("\n")
+            ));
+        }
+
+        for conflict in &conflicts {
+            warnings.warn(logger, conflict);
+        }
+
+        let shared_library_prefixes = self
+            .iter_resources()
+            .filter_map(|(_, resource)| {
+                resource
+                    .relative_path_shared_library
+                    .as_ref()
+                    .map(|(prefix, _)| prefix.clone())
+            })
+            .collect::<BTreeSet<_>>();
+
         let resources = self.collector.to_prepared_python_resources(python_exe)?;
 
+        let file_installs = self
+            .collector
+            .derive_file_installs()?
+            .into_iter()
+            .map(|(path, location, executable)| (path, location.clone(), executable))
+            .collect();
+
         Ok(EmbeddedPythonResources {
             resources,
+            file_installs,
             extension_modules: self.extension_module_states.clone(),
+            priority: self.priority.clone(),
+            shared_library_prefixes,
         })
     }
+
+    /// Determine the sizes of resources packaging would produce.
+    ///
+    /// Resources are prepared as `package()` would, which includes compiling
+    /// bytecode, but nothing is produced for embedding.
+    pub fn package_dry_run(
+        &self,
+        logger: &slog::Logger,
+        python_exe: &Path,
+    ) -> Result<ResourceSizeReport> {
+        let prepared = self.collector.to_prepared_python_resources(python_exe)?;
+        let file_installs = self.collector.derive_file_installs()?;
+
+        let file_sizes = relative_file_sizes(
+            file_installs
+                .iter()
+                .map(|(path, location, _)| (path.as_path(), *location))
+                .chain(
+                    prepared
+                        .extra_files
+                        .iter()
+                        .map(|(path, location, _)| (path.as_path(), location)),
+                ),
+        )?;
+        let relative_size = |path: &Path| -> Result<u64> {
+            file_sizes
+                .get(path)
+                .copied()
+                .ok_or_else(|| anyhow!("no file installed at {}", path.display()))
+        };
+
+        let mut report = ResourceSizeReport {
+            module_count: prepared.resources.len(),
+            ..ResourceSizeReport::default()
+        };
+
+        for resource in prepared.resources.values() {
+            report.source_bytes += resource
+                .in_memory_source
+                .iter()
+                .map(|data| Ok(data.len() as u64))
+                .chain(
+                    resource
+                        .relative_path_module_source
+                        .iter()
+                        .map(|path| relative_size(path)),
+                )
+                .sum::<Result<u64>>()?;
+
+            report.bytecode_bytes += vec![
+                &resource.in_memory_bytecode,
+                &resource.in_memory_bytecode_opt1,
+                &resource.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|data| Ok(data.len() as u64))
+            .chain(
+                vec![
+                    &resource.relative_path_module_bytecode,
+                    &resource.relative_path_module_bytecode_opt1,
+                    &resource.relative_path_module_bytecode_opt2,
+                ]
+                .into_iter()
+                .flatten()
+                .map(|path| relative_size(path)),
+            )
+            .sum::<Result<u64>>()?;
+
+            report.resource_bytes += vec![
+                &resource.in_memory_package_resources,
+                &resource.in_memory_distribution_resources,
+            ]
+            .into_iter()
+            .flatten()
+            .flat_map(|resources| resources.values())
+            .map(|data| Ok(data.len() as u64))
+            .chain(
+                vec![
+                    &resource.relative_path_package_resources,
+                    &resource.relative_path_distribution_resources,
+                ]
+                .into_iter()
+                .flatten()
+                .flat_map(|paths| paths.values())
+                .map(|path| relative_size(path)),
+            )
+            .sum::<Result<u64>>()?;
+        }
+
+        report.extension_object_file_bytes = extension_object_sizes(&self.extension_module_states)?;
+
+        info!(
+            logger,
+            "{} resources would be packaged ({} bytes)",
+            report.module_count,
+            report.total_bytes()
+        );
+
+        Ok(report)
+    }
+}
+
+/// Sizes of resources that packaging would produce.
+///
+/// Sizes are in bytes and include data regardless of whether it would be
+/// loaded from memory or from the filesystem.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceSizeReport {
+    /// Number of resources.
+    pub module_count: usize,
+
+    /// Size of Python module source code.
+    pub source_bytes: u64,
+
+    /// Size of Python module bytecode, across all optimization levels.
+    pub bytecode_bytes: u64,
+
+    /// Size of package resources and package distribution resources.
+    pub resource_bytes: u64,
+
+    /// Size of object files of each builtin extension module.
+    pub extension_object_file_bytes: BTreeMap<String, u64>,
+}
+
+impl ResourceSizeReport {
+    /// Total size of all resources and extension module object files.
+    pub fn total_bytes(&self) -> u64 {
+        self.source_bytes
+            + self.bytecode_bytes
+            + self.resource_bytes
+            + self.extension_object_file_bytes.values().sum::<u64>()
+    }
+}
+
+/// Compute the total size of object files linked for each extension module.
+fn extension_object_sizes(
+    states: &BTreeMap<String, ExtensionModuleBuildState>,
+) -> Result<BTreeMap<String, u64>> {
+    states
+        .iter()
+        .map(|(name, state)| {
+            let size = state
+                .link_object_files
+                .iter()
+                .map(data_location_size)
+                .sum::<Result<u64>>()
+                .with_context(|| format!("resolving object files of {}", name))?;
+
+            Ok((name.clone(), size))
+        })
+        .collect()
+}
+
+/// Obtain the size in bytes of data at a location.
+fn data_location_size(location: &DataLocation) -> Result<u64> {
+    Ok(match location {
+        DataLocation::Memory(data) => data.len() as u64,
+        DataLocation::Path(path) => std::fs::metadata(path)
+            .with_context(|| format!("resolving size of {}", path.display()))?
+            .len(),
+    })
+}
+
+/// Resolve the sizes of files installed relative to the produced binary.
+fn relative_file_sizes<'b>(
+    files: impl Iterator<Item = (&'b Path, &'b DataLocation)>,
+) -> Result<BTreeMap<&'b Path, u64>> {
+    files
+        .map(|(path, location)| Ok((path, data_location_size(location)?)))
+        .collect()
 }
 
 /// Holds state necessary to link libpython.
@@ -387,6 +940,130 @@ pub struct LibpythonLinkingInfo {
     pub link_frameworks: BTreeSet<String>,
     pub link_system_libraries: BTreeSet<String>,
     pub link_libraries_external: BTreeSet<String>,
+
+    /// Relative path prefixes that shared libraries are installed to.
+    ///
+    /// Dynamic library dependencies of relative path extension modules are
+    /// installed under these prefixes.
+    pub shared_library_prefixes: BTreeSet<String>,
+}
+
+impl LibpythonLinkingInfo {
+    /// Obtain run-time library search path entries the binary needs.
+    ///
+    /// On Windows, the directory of a loaded DLL is searched for its
+    /// dependencies, so nothing is needed. On ELF targets, the loader won't
+    /// find shared libraries next to extension modules unless the binary
+    /// has an `$ORIGIN` relative rpath entry for each prefix.
+    pub fn rpath_entries(&self, target_triple: &str) -> Vec<String> {
+        if target_triple.contains("-linux-") {
+            self.shared_library_prefixes
+                .iter()
+                .map(|prefix| format!("$ORIGIN/{}", prefix))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Obtain linker arguments adding `rpath_entries()` to the binary.
+    ///
+    /// `DT_RPATH` is emitted instead of `DT_RUNPATH`, as only the former is
+    /// consulted when resolving dependencies of extension modules loaded at
+    /// run-time.
+    pub fn rpath_linker_args(&self, target_triple: &str) -> Vec<String> {
+        let entries = self.rpath_entries(target_triple);
+
+        if entries.is_empty() {
+            return vec![];
+        }
+
+        let mut args = vec!["-Wl,--disable-new-dtags".to_string()];
+        args.extend(entries.iter().map(|entry| format!("-Wl,-rpath,{}", entry)));
+
+        args
+    }
+}
+
+/// How to handle resources whose data no longer exists at packaging time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnresolvableResourcePolicy {
+    /// Fail packaging.
+    Fail,
+    /// Omit the missing data and log a warning.
+    SkipWithWarning,
+    /// Omit the missing data, log a warning, and install a file listing affected resources.
+    SkipAndReport,
+}
+
+impl Default for UnresolvableResourcePolicy {
+    fn default() -> Self {
+        UnresolvableResourcePolicy::Fail
+    }
+}
+
+/// Options controlling compression of individual resources.
+#[derive(Clone, Debug)]
+pub struct ResourceCompressionOptions {
+    /// Resources with less in-memory data than this many bytes aren't compressed.
+    pub min_size: usize,
+
+    /// zstd compression level.
+    pub level: i32,
+}
+
+impl Default for ResourceCompressionOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 4096,
+            level: 3,
+        }
+    }
+}
+
+/// Leading bytes of file formats which are already compressed.
+const COMPRESSED_MAGICS: &[&[u8]] = &[
+    b"PK\x03\x04",
+    b"\x1f\x8b",
+    b"\x28\xb5\x2f\xfd",
+    b"\xfd7zXZ\x00",
+    b"BZh",
+    b"\x89PNG",
+    b"\xff\xd8\xff",
+];
+
+/// Whether data appears to be in an already compressed format.
+fn is_compressed_format(data: &[u8]) -> bool {
+    COMPRESSED_MAGICS
+        .iter()
+        .any(|magic| data.starts_with(magic))
+}
+
+/// An entry in the manifest of embedded resources.
+#[derive(Serialize)]
+struct ResourceManifestEntry<'a> {
+    name: &'a str,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource: Option<String>,
+    location: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    size: u64,
+}
+
+/// A builtin extension module in the manifest of embedded resources.
+#[derive(Serialize)]
+struct BuiltinExtensionManifestEntry {
+    name: String,
+    init_fn: String,
+}
+
+/// Manifest of embedded resources.
+#[derive(Serialize)]
+struct ResourceManifest<'a> {
+    resources: Vec<ResourceManifestEntry<'a>>,
+    builtin_extensions: Vec<BuiltinExtensionManifestEntry>,
 }
 
 /// Represents Python resources to embed in a binary.
@@ -395,11 +1072,82 @@ pub struct EmbeddedPythonResources<'a> {
     /// Resources to write to a packed resources data structure.
     resources: PreparedPythonResources<'a>,
 
+    /// Files referenced by resources to install relative to the produced binary.
+    ///
+    /// Each entry is a relative path, its data, and whether it is executable.
+    file_installs: Vec<(PathBuf, DataLocation, bool)>,
+
     /// Holds state needed for adding extension modules to libpython.
     extension_modules: BTreeMap<String, ExtensionModuleBuildState>,
+
+    /// Names of resources to write first in packed resources data.
+    priority: Vec<String>,
+
+    /// Relative path prefixes that shared libraries are installed to.
+    shared_library_prefixes: BTreeSet<String>,
 }
 
 impl<'a> EmbeddedPythonResources<'a> {
+    /// Compress the in-memory data of individual resources with zstd.
+    ///
+    /// A resource is compressed if the combined size of its in-memory data
+    /// is at least `options.min_size` and none of that data appears to
+    /// already be compressed. Compressed resources are flagged in the packed
+    /// resources data so consumers know to decompress them.
+    ///
+    /// Returns the number of compressed resources.
+    pub fn compress_resources(&mut self, options: &ResourceCompressionOptions) -> Result<usize> {
+        let mut count = 0;
+
+        for resource in self.resources.resources.values_mut() {
+            if resource.zstd_compressed {
+                continue;
+            }
+
+            let mut datas: Vec<&mut Cow<'a, [u8]>> = vec![];
+
+            for field in vec![
+                &mut resource.in_memory_source,
+                &mut resource.in_memory_bytecode,
+                &mut resource.in_memory_bytecode_opt1,
+                &mut resource.in_memory_bytecode_opt2,
+                &mut resource.in_memory_extension_module_shared_library,
+                &mut resource.in_memory_shared_library,
+            ] {
+                if let Some(data) = field {
+                    datas.push(data);
+                }
+            }
+
+            for field in vec![
+                &mut resource.in_memory_package_resources,
+                &mut resource.in_memory_distribution_resources,
+            ] {
+                if let Some(resources) = field {
+                    datas.extend(resources.values_mut());
+                }
+            }
+
+            let size = datas.iter().map(|data| data.len()).sum::<usize>();
+
+            if size == 0
+                || size < options.min_size
+                || datas.iter().any(|data| is_compressed_format(data))
+            {
+                continue;
+            }
+
+            for data in datas {
+                *data = Cow::Owned(zstd::stream::encode_all(data.as_ref(), options.level)?);
+            }
+
+            resource.zstd_compressed = true;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
     /// Write entities defining resources.
     pub fn write_blobs<W: Write>(&self, module_names: &mut W, resources: &mut W) -> Result<()> {
         for name in self.resources.resources.keys() {
@@ -409,7 +1157,146 @@ impl<'a> EmbeddedPythonResources<'a> {
             module_names.write_all(b"\n").expect("failed to write");
         }
 
-        self.resources.write_packed_resources_v1(resources)
+        self.resources
+            .write_packed_resources_v1_ordered(resources, &self.priority)
+    }
+
+    /// Write a JSON manifest describing the embedded resources.
+    ///
+    /// Each piece of data of each resource is listed with its kind, whether it
+    /// is loaded from memory or from a path relative to the binary, and its
+    /// size in bytes as stored. Builtin extension modules are listed with their
+    /// initialization functions. Output is deterministic so manifests from
+    /// different builds can be diffed.
+    pub fn write_manifest_json<W: Write>(&self, w: &mut W) -> Result<()> {
+        let file_sizes = relative_file_sizes(
+            self.file_installs
+                .iter()
+                .chain(self.resources.extra_files.iter())
+                .map(|(path, location, _)| (path.as_path(), location)),
+        )?;
+
+        let relative_size = |path: &Path| -> Result<u64> {
+            file_sizes
+                .get(path)
+                .copied()
+                .ok_or_else(|| anyhow!("no file installed at {}", path.display()))
+        };
+
+        let mut entries = vec![];
+
+        for (name, resource) in &self.resources.resources {
+            for (kind, data) in &[
+                ("module-source", &resource.in_memory_source),
+                ("module-bytecode", &resource.in_memory_bytecode),
+                ("module-bytecode-opt1", &resource.in_memory_bytecode_opt1),
+                ("module-bytecode-opt2", &resource.in_memory_bytecode_opt2),
+                (
+                    "extension-module",
+                    &resource.in_memory_extension_module_shared_library,
+                ),
+                ("shared-library", &resource.in_memory_shared_library),
+            ] {
+                if let Some(data) = data {
+                    entries.push(ResourceManifestEntry {
+                        name,
+                        kind: *kind,
+                        resource: None,
+                        location: "in-memory",
+                        path: None,
+                        size: data.len() as u64,
+                    });
+                }
+            }
+
+            for (kind, resources) in &[
+                ("package-resource", &resource.in_memory_package_resources),
+                (
+                    "distribution-resource",
+                    &resource.in_memory_distribution_resources,
+                ),
+            ] {
+                if let Some(resources) = resources {
+                    let resources = resources.iter().collect::<BTreeMap<_, _>>();
+                    for (resource_name, data) in resources {
+                        entries.push(ResourceManifestEntry {
+                            name,
+                            kind: *kind,
+                            resource: Some(resource_name.to_string()),
+                            location: "in-memory",
+                            path: None,
+                            size: data.len() as u64,
+                        });
+                    }
+                }
+            }
+
+            for (kind, path) in &[
+                ("module-source", &resource.relative_path_module_source),
+                ("module-bytecode", &resource.relative_path_module_bytecode),
+                (
+                    "module-bytecode-opt1",
+                    &resource.relative_path_module_bytecode_opt1,
+                ),
+                (
+                    "module-bytecode-opt2",
+                    &resource.relative_path_module_bytecode_opt2,
+                ),
+                (
+                    "extension-module",
+                    &resource.relative_path_extension_module_shared_library,
+                ),
+            ] {
+                if let Some(path) = path {
+                    entries.push(ResourceManifestEntry {
+                        name,
+                        kind: *kind,
+                        resource: None,
+                        location: "relative-path",
+                        path: Some(path.display().to_string()),
+                        size: relative_size(path)?,
+                    });
+                }
+            }
+
+            for (kind, paths) in &[
+                (
+                    "package-resource",
+                    &resource.relative_path_package_resources,
+                ),
+                (
+                    "distribution-resource",
+                    &resource.relative_path_distribution_resources,
+                ),
+            ] {
+                if let Some(paths) = paths {
+                    let paths = paths.iter().collect::<BTreeMap<_, _>>();
+                    for (resource_name, path) in paths {
+                        entries.push(ResourceManifestEntry {
+                            name,
+                            kind: *kind,
+                            resource: Some(resource_name.to_string()),
+                            location: "relative-path",
+                            path: Some(path.display().to_string()),
+                            size: relative_size(path)?,
+                        });
+                    }
+                }
+            }
+        }
+
+        let manifest = ResourceManifest {
+            resources: entries,
+            builtin_extensions: self
+                .builtin_extensions()
+                .into_iter()
+                .map(|(name, init_fn)| BuiltinExtensionManifestEntry { name, init_fn })
+                .collect(),
+        };
+
+        serde_json::to_writer_pretty(w, &manifest)?;
+
+        Ok(())
     }
 
     /// Obtain a list of built-in extensions.
@@ -445,17 +1332,60 @@ impl<'a> EmbeddedPythonResources<'a> {
         Ok(res)
     }
 
-    /// Resolve state needed to link a libpython.
-    pub fn resolve_libpython_linking_info(
-        &self,
-        logger: &slog::Logger,
-    ) -> Result<LibpythonLinkingInfo> {
-        let mut object_files = Vec::new();
-        let mut link_libraries = BTreeSet::new();
+    /// Obtain the paths of extra files to install relative to the produced binary.
+    ///
+    /// Unlike `extra_install_files()`, file data isn't resolved.
+    pub fn extra_file_paths(&self) -> Vec<PathBuf> {
+        self.resources
+            .extra_files
+            .iter()
+            .map(|(path, _, _)| path.clone())
+            .collect()
+    }
+
+    /// Obtain the total size of object files linked for each builtin extension module.
+    ///
+    /// Keys are extension module names. This is useful to identify which
+    /// extension modules contribute the most to libpython link times.
+    pub fn extension_object_sizes(&self) -> Result<BTreeMap<String, u64>> {
+        extension_object_sizes(&self.extension_modules)
+    }
+
+    /// Compute the object files and libraries required by builtin extension modules.
+    ///
+    /// Libraries and frameworks required by multiple extension modules are
+    /// only reported once.
+    pub fn compute_link_requirements(&self) -> LibpythonLinkingInfo {
+        let mut object_files = Vec::new();
+        let mut link_libraries = BTreeSet::new();
         let mut link_frameworks = BTreeSet::new();
         let mut link_system_libraries = BTreeSet::new();
         let mut link_libraries_external = BTreeSet::new();
 
+        for state in self.extension_modules.values() {
+            object_files.extend(state.link_object_files.iter().cloned());
+            link_frameworks.extend(state.link_frameworks.iter().cloned());
+            link_system_libraries.extend(state.link_system_libraries.iter().cloned());
+            link_libraries.extend(state.link_static_libraries.iter().cloned());
+            link_libraries.extend(state.link_dynamic_libraries.iter().cloned());
+            link_libraries_external.extend(state.link_external_libraries.iter().cloned());
+        }
+
+        LibpythonLinkingInfo {
+            object_files,
+            link_libraries,
+            link_frameworks,
+            link_system_libraries,
+            link_libraries_external,
+            shared_library_prefixes: self.shared_library_prefixes.clone(),
+        }
+    }
+
+    /// Resolve state needed to link a libpython.
+    pub fn resolve_libpython_linking_info(
+        &self,
+        logger: &slog::Logger,
+    ) -> Result<LibpythonLinkingInfo> {
         warn!(
             logger,
             "resolving inputs for {} extension modules...",
@@ -470,48 +1400,102 @@ impl<'a> EmbeddedPythonResources<'a> {
                     state.link_object_files.len(),
                     name
                 );
-                object_files.extend(state.link_object_files.iter().cloned());
             }
 
             for framework in &state.link_frameworks {
                 warn!(logger, "framework {} required by {}", framework, name);
-                link_frameworks.insert(framework.clone());
             }
 
             for library in &state.link_system_libraries {
                 warn!(logger, "system library {} required by {}", library, name);
-                link_system_libraries.insert(library.clone());
             }
 
             for library in &state.link_static_libraries {
                 warn!(logger, "static library {} required by {}", library, name);
-                link_libraries.insert(library.clone());
             }
 
             for library in &state.link_dynamic_libraries {
                 warn!(logger, "dynamic library {} required by {}", library, name);
-                link_libraries.insert(library.clone());
             }
 
             for library in &state.link_external_libraries {
                 warn!(logger, "dynamic library {} required by {}", library, name);
-                link_libraries_external.insert(library.clone());
             }
         }
 
-        Ok(LibpythonLinkingInfo {
-            object_files,
-            link_libraries,
-            link_frameworks,
-            link_system_libraries,
-            link_libraries_external,
-        })
+        Ok(self.compute_link_requirements())
     }
+
+    /// Describe link dependencies of builtin extension modules as a Graphviz DOT graph.
+    ///
+    /// Nodes are extension modules and the libraries and frameworks they
+    /// require. Each edge points from an extension module to something it
+    /// requires. Library nodes are labeled with the kind of library.
+    pub fn link_dependency_graph_dot(&self) -> String {
+        let mut nodes = BTreeSet::new();
+        let mut edges = Vec::new();
+
+        for (name, state) in &self.extension_modules {
+            let module_id = format!("extension:{}", name);
+            nodes.insert((module_id.clone(), name.clone(), "box"));
+
+            for (kind, names) in &[
+                ("framework", &state.link_frameworks),
+                ("system", &state.link_system_libraries),
+                ("static", &state.link_static_libraries),
+                ("dynamic", &state.link_dynamic_libraries),
+                ("external", &state.link_external_libraries),
+            ] {
+                for library in names.iter() {
+                    let library_id = format!("{}:{}", kind, library);
+                    nodes.insert((
+                        library_id.clone(),
+                        format!("{} ({})", library, kind),
+                        "ellipse",
+                    ));
+                    edges.push((module_id.clone(), library_id));
+                }
+            }
+        }
+
+        let mut lines = vec!["digraph link_dependencies {".to_string()];
+
+        for (id, label, shape) in nodes {
+            lines.push(format!(
+                "    \"{}\" [label=\"{}\", shape={}];",
+                dot_escape(&id),
+                dot_escape(&label),
+                shape
+            ));
+        }
+
+        for (from, to) in edges {
+            lines.push(format!(
+                "    \"{}\" -> \"{}\";",
+                dot_escape(&from),
+                dot_escape(&to)
+            ));
+        }
+
+        lines.push("}".to_string());
+        lines.push(String::new());
+
+        lines.join("\n")
+    }
+}
+
+/// Escape a string for use in a quoted Graphviz DOT identifier.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {
+        super::*,
+        python_packaging::resource::{BytecodeOptimizationLevel, LibraryDependency},
+        std::path::PathBuf,
+    };
 
     const DEFAULT_CACHE_TAG: &str = "cpython-37";
 
@@ -554,6 +1538,210 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_manifest_json() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+        let relative = ConcreteResourceLocation::RelativePath("lib".to_string());
+
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import io".to_vec()),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "bar".to_string(),
+                source: DataLocation::Memory(b"x = 1".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &relative,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "data.txt".to_string(),
+                data: DataLocation::Memory(b"data".to_vec()),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &relative,
+        )?;
+        r.add_builtin_extension_module(&PythonExtensionModule {
+            name: "_io".to_string(),
+            init_fn: Some("PyInit__io".to_string()),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![DataLocation::Memory(vec![42])],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: true,
+            builtin_default: true,
+            required: true,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+        })?;
+
+        let resources = r.package(
+            &logger,
+            &distribution.python_exe,
+            &mut BuildWarnings::default(),
+        )?;
+
+        let mut data = Vec::new();
+        resources.write_manifest_json(&mut data)?;
+        let manifest: serde_json::Value = serde_json::from_slice(&data)?;
+
+        assert_eq!(
+            manifest["builtin_extensions"],
+            serde_json::json!([{"name": "_io", "init_fn": "PyInit__io"}])
+        );
+        assert_eq!(
+            manifest["resources"],
+            serde_json::json!([
+                {
+                    "name": "bar",
+                    "kind": "module-source",
+                    "location": "relative-path",
+                    "path": "lib/bar.py",
+                    "size": 5,
+                },
+                {
+                    "name": "foo",
+                    "kind": "module-source",
+                    "location": "in-memory",
+                    "size": 9,
+                },
+                {
+                    "name": "foo",
+                    "kind": "package-resource",
+                    "resource": "data.txt",
+                    "location": "relative-path",
+                    "path": "lib/foo/data.txt",
+                    "size": 4,
+                },
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_link_requirements() {
+        let mut extension_modules = BTreeMap::new();
+        extension_modules.insert(
+            "_ssl".to_string(),
+            ExtensionModuleBuildState {
+                init_fn: None,
+                link_object_files: vec![DataLocation::Memory(vec![42])],
+                link_frameworks: BTreeSet::new(),
+                link_system_libraries: BTreeSet::from_iter(vec!["dl".to_string()]),
+                link_static_libraries: BTreeSet::from_iter(vec!["ssl".to_string()]),
+                link_dynamic_libraries: BTreeSet::new(),
+                link_external_libraries: BTreeSet::new(),
+            },
+        );
+        extension_modules.insert(
+            "_hashlib".to_string(),
+            ExtensionModuleBuildState {
+                init_fn: None,
+                link_object_files: vec![DataLocation::Memory(vec![43])],
+                link_frameworks: BTreeSet::from_iter(vec!["Security".to_string()]),
+                link_system_libraries: BTreeSet::from_iter(vec!["dl".to_string()]),
+                link_static_libraries: BTreeSet::from_iter(vec!["ssl".to_string()]),
+                link_dynamic_libraries: BTreeSet::from_iter(vec!["crypto".to_string()]),
+                link_external_libraries: BTreeSet::from_iter(vec!["z".to_string()]),
+            },
+        );
+
+        let resources = EmbeddedPythonResources {
+            extension_modules,
+            ..EmbeddedPythonResources::default()
+        };
+
+        let info = resources.compute_link_requirements();
+
+        assert_eq!(info.object_files.len(), 2);
+        assert_eq!(
+            info.link_libraries,
+            BTreeSet::from_iter(vec!["crypto".to_string(), "ssl".to_string()])
+        );
+        assert_eq!(
+            info.link_frameworks,
+            BTreeSet::from_iter(vec!["Security".to_string()])
+        );
+        assert_eq!(
+            info.link_system_libraries,
+            BTreeSet::from_iter(vec!["dl".to_string()])
+        );
+        assert_eq!(
+            info.link_libraries_external,
+            BTreeSet::from_iter(vec!["z".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_link_dependency_graph_dot() {
+        let mut extension_modules = BTreeMap::new();
+        extension_modules.insert(
+            "_ssl".to_string(),
+            ExtensionModuleBuildState {
+                init_fn: None,
+                link_object_files: vec![],
+                link_frameworks: BTreeSet::new(),
+                link_system_libraries: BTreeSet::from_iter(vec!["dl".to_string()]),
+                link_static_libraries: BTreeSet::from_iter(vec!["ssl".to_string()]),
+                link_dynamic_libraries: BTreeSet::new(),
+                link_external_libraries: BTreeSet::new(),
+            },
+        );
+        extension_modules.insert(
+            "_hashlib".to_string(),
+            ExtensionModuleBuildState {
+                init_fn: None,
+                link_object_files: vec![],
+                link_frameworks: BTreeSet::new(),
+                link_system_libraries: BTreeSet::new(),
+                link_static_libraries: BTreeSet::from_iter(vec!["ssl".to_string()]),
+                link_dynamic_libraries: BTreeSet::new(),
+                link_external_libraries: BTreeSet::new(),
+            },
+        );
+
+        let resources = EmbeddedPythonResources {
+            extension_modules,
+            ..EmbeddedPythonResources::default()
+        };
+
+        assert_eq!(
+            resources.link_dependency_graph_dot(),
+            "digraph link_dependencies {\n    \
+             \"extension:_hashlib\" [label=\"_hashlib\", shape=box];\n    \
+             \"extension:_ssl\" [label=\"_ssl\", shape=box];\n    \
+             \"static:ssl\" [label=\"ssl (static)\", shape=ellipse];\n    \
+             \"system:dl\" [label=\"dl (system)\", shape=ellipse];\n    \
+             \"extension:_hashlib\" -> \"static:ssl\";\n    \
+             \"extension:_ssl\" -> \"system:dl\";\n    \
+             \"extension:_ssl\" -> \"static:ssl\";\n\
+             }\n"
+        );
+    }
+
     #[test]
     fn test_add_extension_module_data() -> Result<()> {
         let mut r =
@@ -592,4 +1780,667 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_serialize_roundtrip() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+        let temp_dir = crate::environment::new_temp_dir("test")?;
+
+        let mut r =
+            PrePackagedResources::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import os".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import os".to_vec()),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_builtin_extension_module(&PythonExtensionModule {
+            name: "foo.bar".to_string(),
+            init_fn: Some("PyInit_bar".to_string()),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![DataLocation::Memory(vec![42])],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: true,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+        })?;
+
+        let path = temp_dir.path().join("resources.json");
+        r.serialize_to(&path)?;
+        let r2 = PrePackagedResources::deserialize_from(&path)?;
+
+        assert_eq!(r2.extension_module_states, r.extension_module_states);
+
+        let mut names1 = Vec::new();
+        let mut data1 = Vec::new();
+        r.package(
+            &logger,
+            &distribution.python_exe,
+            &mut BuildWarnings::default(),
+        )?
+        .write_blobs(&mut names1, &mut data1)?;
+
+        let mut names2 = Vec::new();
+        let mut data2 = Vec::new();
+        r2.package(
+            &logger,
+            &distribution.python_exe,
+            &mut BuildWarnings::default(),
+        )?
+        .write_blobs(&mut names2, &mut data2)?;
+
+        assert_eq!(names1, names2);
+        assert_eq!(data1, data2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_dry_run() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import os".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import os".to_vec()),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "data.txt".to_string(),
+                data: DataLocation::Memory(b"data".to_vec()),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "baz".to_string(),
+                source: DataLocation::Memory(b"x = 1".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "relative.txt".to_string(),
+                data: DataLocation::Memory(b"relative".to_vec()),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+        r.add_builtin_extension_module(&PythonExtensionModule {
+            name: "foo.bar".to_string(),
+            init_fn: Some("PyInit_bar".to_string()),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![DataLocation::Memory(vec![42; 3])],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: true,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+        })?;
+
+        let report = r.package_dry_run(&logger, &distribution.python_exe)?;
+
+        // Builtin extension modules aren't packaged as resources.
+        assert_eq!(report.module_count, 2);
+        assert_eq!(report.source_bytes, 9 + 5);
+        assert!(report.bytecode_bytes > 0);
+        assert_eq!(report.resource_bytes, 4 + 8);
+        assert_eq!(report.extension_object_file_bytes.get("foo.bar"), Some(&3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_from_files_regex() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let temp_dir = crate::environment::new_temp_dir("test")?;
+
+        let mut r =
+            PrePackagedResources::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        for name in &["numpy.core", "numpy.tests.foo", "numpy.testsuite", "other"] {
+            r.add_builtin_extension_module(&PythonExtensionModule {
+                name: name.to_string(),
+                init_fn: Some("PyInit_foo".to_string()),
+                extension_file_suffix: "".to_string(),
+                shared_library: None,
+                object_file_data: vec![DataLocation::Memory(vec![42])],
+                is_package: false,
+                link_libraries: vec![],
+                is_stdlib: false,
+                builtin_default: true,
+                required: false,
+                variant: None,
+                licenses: None,
+                license_texts: None,
+                license_public_domain: None,
+            })?;
+        }
+
+        let names_path = temp_dir.path().join("names");
+        std::fs::write(
+            &names_path,
+            "numpy.core\nnumpy.tests.foo\nnumpy.testsuite\nother\n",
+        )?;
+
+        assert!(r
+            .filter_from_files(&logger, &[&names_path], &[], &["("])
+            .is_err());
+
+        r.filter_from_files(&logger, &[&names_path], &[], &[r"^numpy\.tests\."])?;
+
+        assert_eq!(
+            r.extension_module_states.keys().collect::<Vec<_>>(),
+            vec!["numpy.core", "numpy.testsuite", "other"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_relative_path_extensions_shared_library_dedup() -> Result<()> {
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+
+        let depends = LibraryDependency {
+            name: "libfoo".to_string(),
+            static_library: None,
+            dynamic_library: Some(DataLocation::Memory(vec![42])),
+            framework: false,
+            system: false,
+        };
+
+        let em = PythonExtensionModule {
+            name: "foo".to_string(),
+            init_fn: None,
+            extension_file_suffix: ".so".to_string(),
+            shared_library: Some(DataLocation::Memory(vec![1])),
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![depends.clone()],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+        };
+
+        r.add_relative_path_distribution_extension_module("lib", &em)?;
+        r.add_relative_path_distribution_extension_module(
+            "lib",
+            &PythonExtensionModule {
+                name: "bar".to_string(),
+                shared_library: Some(DataLocation::Memory(vec![2])),
+                ..em.clone()
+            },
+        )?;
+
+        let installs = r.collector.derive_file_installs()?;
+        assert_eq!(
+            installs
+                .iter()
+                .filter(|(path, _, _)| path == &PathBuf::from("lib/libfoo"))
+                .count(),
+            1
+        );
+
+        // Same name with different content is a conflict.
+        let res = r.add_relative_path_distribution_extension_module(
+            "lib",
+            &PythonExtensionModule {
+                name: "baz".to_string(),
+                link_libraries: vec![LibraryDependency {
+                    dynamic_library: Some(DataLocation::Memory(vec![43])),
+                    ..depends
+                }],
+                ..em
+            },
+        );
+        assert!(res.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_object_sizes() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+
+        let mut r =
+            PrePackagedResources::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+
+        for (name, object_file_data) in vec![
+            ("foo", vec![DataLocation::Memory(vec![42; 10])]),
+            (
+                "bar",
+                vec![
+                    DataLocation::Memory(vec![42; 5]),
+                    DataLocation::Memory(vec![42; 7]),
+                ],
+            ),
+        ] {
+            r.add_builtin_extension_module(&PythonExtensionModule {
+                name: name.to_string(),
+                init_fn: Some(format!("PyInit_{}", name)),
+                extension_file_suffix: "".to_string(),
+                shared_library: None,
+                object_file_data,
+                is_package: false,
+                link_libraries: vec![],
+                is_stdlib: false,
+                builtin_default: true,
+                required: false,
+                variant: None,
+                licenses: None,
+                license_texts: None,
+                license_public_domain: None,
+            })?;
+        }
+
+        let embedded = r.package(
+            &logger,
+            &distribution.python_exe,
+            &mut BuildWarnings::default(),
+        )?;
+
+        let sizes = embedded.extension_object_sizes()?;
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes.get("foo"), Some(&10));
+        assert_eq!(sizes.get("bar"), Some(&12));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_resources_by_location() -> Result<()> {
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+
+        for (name, location) in &[
+            ("foo", ConcreteResourceLocation::InMemory),
+            ("bar", ConcreteResourceLocation::InMemory),
+            (
+                "baz",
+                ConcreteResourceLocation::RelativePath("lib".to_string()),
+            ),
+            (
+                "qux",
+                ConcreteResourceLocation::RelativePath("other".to_string()),
+            ),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: DataLocation::Memory(vec![]),
+                    is_package: false,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                location,
+            )?;
+        }
+
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "data.txt".to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+
+        let in_memory = ConcreteResourceLocation::InMemory;
+        assert_eq!(
+            r.iter_resources_by_location(&in_memory)
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["bar", "foo"]
+        );
+
+        let lib = ConcreteResourceLocation::RelativePath("lib".to_string());
+        assert_eq!(
+            r.iter_resources_by_location(&lib)
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["baz", "foo"]
+        );
+
+        let other = ConcreteResourceLocation::RelativePath("other".to_string());
+        assert_eq!(r.iter_resources_by_location(&other).count(), 1);
+
+        let missing = ConcreteResourceLocation::RelativePath("missing".to_string());
+        assert_eq!(r.iter_resources_by_location(&missing).count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_location_conflicts() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+
+        let module = PythonModuleSource {
+            name: "foo".to_string(),
+            source: DataLocation::Memory(b"import os".to_vec()),
+            is_package: false,
+            cache_tag: DEFAULT_CACHE_TAG.to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        r.add_python_module_source(&module, &ConcreteResourceLocation::InMemory)?;
+        assert!(r.location_conflicts().is_empty());
+
+        r.add_python_module_source(
+            &module,
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+
+        let conflicts = r.location_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts.get("foo"),
+            Some(&BTreeSet::from_iter(vec![
+                "in-memory".to_string(),
+                "relative-path:lib".to_string()
+            ]))
+        );
+
+        let mut warnings = BuildWarnings::default();
+        r.package(&logger, &distribution.python_exe, &mut warnings)?;
+        assert_eq!(
+            warnings.warnings(),
+            &["foo registered under conflicting locations: in-memory, relative-path:lib"]
+        );
+
+        r.set_strict_conflicts(true);
+        let mut warnings = BuildWarnings::default();
+        assert!(r
+            .package(&logger, &distribution.python_exe, &mut warnings)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_provided_libraries() -> Result<()> {
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+        r.set_library_host_provided("libm", true);
+
+        let em = PythonExtensionModule {
+            name: "foo".to_string(),
+            init_fn: None,
+            extension_file_suffix: ".so".to_string(),
+            shared_library: Some(DataLocation::Memory(vec![1])),
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![
+                LibraryDependency {
+                    name: "libm".to_string(),
+                    static_library: None,
+                    dynamic_library: Some(DataLocation::Memory(vec![42])),
+                    framework: false,
+                    system: false,
+                },
+                LibraryDependency {
+                    name: "libfoo".to_string(),
+                    static_library: None,
+                    dynamic_library: Some(DataLocation::Memory(vec![43])),
+                    framework: false,
+                    system: false,
+                },
+            ],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+        };
+
+        r.add_in_memory_distribution_extension_module(&em)?;
+        r.add_relative_path_distribution_extension_module(
+            "lib",
+            &PythonExtensionModule {
+                name: "bar".to_string(),
+                ..em.clone()
+            },
+        )?;
+
+        let names = r
+            .iter_resources()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"libfoo"));
+        assert!(!names.contains(&"libm"));
+
+        let foo = r
+            .iter_resources()
+            .find(|(name, _)| *name == "foo")
+            .unwrap()
+            .1;
+        assert_eq!(
+            foo.shared_library_dependency_names,
+            Some(vec!["libfoo".to_string()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_in_memory_extension_module_shared_library_depends() -> Result<()> {
+        let mut r =
+            PrePackagedResources::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+
+        r.add_in_memory_extension_module_shared_library("foo", false, &[42], &["libbar"])?;
+        r.add_in_memory_extension_module_shared_library("baz", false, &[42], &[])?;
+
+        let foo = r
+            .iter_resources()
+            .find(|(name, _)| *name == "foo")
+            .unwrap()
+            .1;
+        assert_eq!(
+            foo.shared_library_dependency_names,
+            Some(vec!["libbar".to_string()])
+        );
+
+        let baz = r
+            .iter_resources()
+            .find(|(name, _)| *name == "baz")
+            .unwrap()
+            .1;
+        assert_eq!(baz.shared_library_dependency_names, Some(vec![]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path_shared_library_rpath() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let distribution = crate::testutil::get_default_distribution()?;
+
+        let mut r = PrePackagedResources::new(
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+
+        r.add_relative_path_distribution_extension_module(
+            "lib",
+            &PythonExtensionModule {
+                name: "foo".to_string(),
+                init_fn: None,
+                extension_file_suffix: ".so".to_string(),
+                shared_library: Some(DataLocation::Memory(vec![1])),
+                object_file_data: vec![],
+                is_package: false,
+                link_libraries: vec![LibraryDependency {
+                    name: "libfoo".to_string(),
+                    static_library: None,
+                    dynamic_library: Some(DataLocation::Memory(vec![42])),
+                    framework: false,
+                    system: false,
+                }],
+                is_stdlib: false,
+                builtin_default: false,
+                required: false,
+                variant: None,
+                licenses: None,
+                license_texts: None,
+                license_public_domain: None,
+            },
+        )?;
+
+        let embedded = r.package(
+            &logger,
+            &distribution.python_exe,
+            &mut BuildWarnings::default(),
+        )?;
+        let info = embedded.compute_link_requirements();
+
+        assert_eq!(
+            info.shared_library_prefixes,
+            BTreeSet::from_iter(vec!["lib".to_string()])
+        );
+        assert_eq!(
+            info.rpath_entries("x86_64-unknown-linux-gnu"),
+            vec!["$ORIGIN/lib".to_string()]
+        );
+        assert!(info.rpath_entries("x86_64-pc-windows-msvc").is_empty());
+        assert_eq!(
+            info.rpath_linker_args("x86_64-unknown-linux-gnu"),
+            vec![
+                "-Wl,--disable-new-dtags".to_string(),
+                "-Wl,-rpath,$ORIGIN/lib".to_string()
+            ]
+        );
+        assert!(info.rpath_linker_args("x86_64-pc-windows-msvc").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_retains_settings() -> Result<()> {
+        let mut r =
+            PrePackagedResources::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(b"import os".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.set_priority(&["foo".to_string()]);
+        r.set_library_host_provided("ssl", true);
+
+        r.clear()?;
+
+        assert_eq!(r.iter_resources().count(), 0);
+        assert_eq!(r.priority, vec!["foo".to_string()]);
+        assert!(r.host_provided_libraries.contains("ssl"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_file_paths() {
+        let resources = EmbeddedPythonResources {
+            resources: PreparedPythonResources {
+                extra_files: vec![(
+                    PathBuf::from("lib/foo.so"),
+                    DataLocation::Path(PathBuf::from("/does/not/exist")),
+                    false,
+                )],
+                ..PreparedPythonResources::default()
+            },
+            ..EmbeddedPythonResources::default()
+        };
+
+        assert_eq!(
+            resources.extra_file_paths(),
+            vec![PathBuf::from("lib/foo.so")]
+        );
+    }
 }
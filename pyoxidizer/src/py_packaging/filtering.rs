@@ -6,7 +6,8 @@
 Utility code for filtering.
 */
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
 use slog::warn;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
@@ -61,15 +62,36 @@ pub fn resolve_resource_names_from_files(
     Ok(include_names)
 }
 
+/// Compile regular expressions matching resource names.
+pub fn compile_resource_name_patterns(patterns: &[&str]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .with_context(|| format!("invalid resource name pattern: {}", pattern))
+        })
+        .collect()
+}
+
+/// Whether a resource name is in a set of names and matches no exclude pattern.
+pub fn resource_name_included(
+    name: &str,
+    names: &BTreeSet<String>,
+    exclude_patterns: &[Regex],
+) -> bool {
+    names.contains(name) && !exclude_patterns.iter().any(|re| re.is_match(name))
+}
+
 pub fn filter_btreemap<V>(
     logger: &slog::Logger,
     m: &mut BTreeMap<String, V>,
     f: &BTreeSet<String>,
+    exclude_patterns: &[Regex],
 ) {
     let keys: Vec<String> = m.keys().cloned().collect();
 
     for key in keys {
-        if !f.contains(&key) {
+        if !resource_name_included(&key, f, exclude_patterns) {
             warn!(logger, "removing {}", key);
             m.remove(&key);
         }
@@ -0,0 +1,249 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Static analysis of imports between Python modules.
+
+Imports are discovered by parsing module source with Python's `ast`
+module. Only `import` statements are detected. Modules imported
+dynamically are not, so results derived from this analysis are advisory.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::resource::DataLocation,
+    python_packaging::resource_collection::{PrePackagedResource, PythonModuleBytecodeProvider},
+    serde::Serialize,
+    std::collections::{BTreeMap, BTreeSet},
+    std::io::Write,
+    std::path::Path,
+    std::process::{Command, Stdio},
+};
+
+/// Python script emitting the names imported by each module fed to it.
+const FIND_IMPORTS_PY: &str = r#"
+import ast, json, sys
+
+modules = json.load(sys.stdin)
+result = {}
+
+for name, info in modules.items():
+    package = name if info["is_package"] else name.rpartition(".")[0]
+    imports = set()
+
+    try:
+        tree = ast.parse(info["source"])
+    except (SyntaxError, ValueError):
+        tree = None
+
+    for node in ast.walk(tree) if tree is not None else []:
+        if isinstance(node, ast.Import):
+            for alias in node.names:
+                imports.add(alias.name)
+        elif isinstance(node, ast.ImportFrom):
+            if node.level:
+                parts = package.split(".") if package else []
+                parts = parts[: len(parts) - (node.level - 1)]
+                if node.module:
+                    parts.append(node.module)
+                base = ".".join(parts)
+            else:
+                base = node.module or ""
+
+            if base:
+                imports.add(base)
+
+            for alias in node.names:
+                if alias.name != "*":
+                    imports.add(base + "." + alias.name if base else alias.name)
+
+    result[name] = sorted(imports)
+
+json.dump(result, sys.stdout)
+"#;
+
+#[derive(Serialize)]
+struct ModuleSourceEntry {
+    source: String,
+    is_package: bool,
+}
+
+/// Obtain the location of Python source code for a resource, if available.
+pub fn resource_source(resource: &PrePackagedResource) -> Option<&DataLocation> {
+    if let Some(location) = &resource.in_memory_source {
+        Some(location)
+    } else if let Some((_, location)) = &resource.relative_path_module_source {
+        Some(location)
+    } else if let Some(PythonModuleBytecodeProvider::FromSource(location)) =
+        &resource.in_memory_bytecode
+    {
+        Some(location)
+    } else if let Some((_, _, PythonModuleBytecodeProvider::FromSource(location))) =
+        &resource.relative_path_bytecode
+    {
+        Some(location)
+    } else {
+        None
+    }
+}
+
+/// Resolve the modules imported by each resource having Python source.
+///
+/// Returns a mapping of module name to names it imports. Names of the form
+/// `from a import b` are reported as both `a` and `a.b`, since `b` may be
+/// a submodule.
+pub fn resolve_imports<'a>(
+    python_exe: &Path,
+    resources: impl Iterator<Item = (&'a String, &'a PrePackagedResource)>,
+) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut modules = BTreeMap::new();
+
+    for (name, resource) in resources {
+        if let Some(location) = resource_source(resource) {
+            modules.insert(
+                name.clone(),
+                ModuleSourceEntry {
+                    source: String::from_utf8_lossy(&location.resolve()?).to_string(),
+                    is_package: resource.is_package,
+                },
+            );
+        }
+    }
+
+    let mut child = Command::new(python_exe)
+        .args(&["-c", FIND_IMPORTS_PY])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(&serde_json::to_vec(&modules)?)?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "error resolving imports: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Compute the names of modules transitively imported by `entrypoints`.
+///
+/// Importing a module also imports its parent packages. So parents of
+/// every visited module are included.
+pub fn import_closure(
+    graph: &BTreeMap<String, BTreeSet<String>>,
+    entrypoints: &[String],
+) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut queue = entrypoints.to_vec();
+
+    while let Some(name) = queue.pop() {
+        let parts = name.split('.').collect::<Vec<_>>();
+
+        for i in 1..=parts.len() {
+            let candidate = parts[0..i].join(".");
+
+            if let Some(imports) = graph.get(&candidate) {
+                if !seen.contains(&candidate) {
+                    queue.extend(imports.iter().cloned());
+                }
+            }
+
+            seen.insert(candidate);
+        }
+    }
+
+    seen
+}
+
+/// Obtain the entries of `names` not transitively imported by `entrypoints`.
+pub fn unreachable_modules<'a>(
+    graph: &BTreeMap<String, BTreeSet<String>>,
+    names: impl Iterator<Item = &'a String>,
+    entrypoints: &[String],
+) -> Vec<String> {
+    let reachable = import_closure(graph, entrypoints);
+
+    names
+        .filter(|name| !reachable.contains(*name))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_closure() {
+        let mut graph = BTreeMap::new();
+        graph.insert(
+            "app".to_string(),
+            ["foo.bar".to_string()].iter().cloned().collect(),
+        );
+        graph.insert(
+            "foo".to_string(),
+            ["baz".to_string()].iter().cloned().collect(),
+        );
+        graph.insert("foo.bar".to_string(), BTreeSet::new());
+        graph.insert("baz".to_string(), BTreeSet::new());
+        graph.insert(
+            "unused".to_string(),
+            ["app".to_string()].iter().cloned().collect(),
+        );
+
+        let closure = import_closure(&graph, &["app".to_string()]);
+
+        assert!(closure.contains("app"));
+        assert!(closure.contains("foo"));
+        assert!(closure.contains("foo.bar"));
+        assert!(closure.contains("baz"));
+        assert!(!closure.contains("unused"));
+    }
+
+    #[test]
+    fn test_unreachable_modules() {
+        let mut graph = BTreeMap::new();
+        graph.insert(
+            "app".to_string(),
+            ["pkg.a".to_string()].iter().cloned().collect(),
+        );
+        graph.insert("pkg".to_string(), BTreeSet::new());
+        graph.insert("pkg.a".to_string(), BTreeSet::new());
+        graph.insert("pkg.b".to_string(), BTreeSet::new());
+        graph.insert(
+            "cycle_a".to_string(),
+            ["cycle_b".to_string()].iter().cloned().collect(),
+        );
+        graph.insert(
+            "cycle_b".to_string(),
+            ["cycle_a".to_string()].iter().cloned().collect(),
+        );
+
+        // Resources without source don't appear in the graph.
+        let names = ["app", "pkg", "pkg.a", "pkg.b", "cycle_a", "cycle_b", "data"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            unreachable_modules(&graph, names.iter(), &["app".to_string()]),
+            vec!["pkg.b", "cycle_a", "cycle_b", "data"]
+        );
+        assert_eq!(
+            unreachable_modules(&graph, names.iter(), &["cycle_b".to_string()]),
+            vec!["app", "pkg", "pkg.a", "pkg.b", "data"]
+        );
+        assert_eq!(unreachable_modules(&graph, names.iter(), &[]), names);
+    }
+}
@@ -9,7 +9,8 @@ Building a native binary containing Python.
 use {
     super::embedded_resource::EmbeddedPythonResources,
     super::standalone_distribution::{LicenseInfo, StandaloneDistribution},
-    anyhow::Result,
+    crate::environment::new_temp_dir_in,
+    anyhow::{anyhow, Context, Result},
     itertools::Itertools,
     lazy_static::lazy_static,
     python_packaging::resource::DataLocation,
@@ -66,18 +67,254 @@ pub fn make_config_c(extensions: &[(String, String)]) -> String {
     lines.join("\n")
 }
 
+/// Compiler flags producing debug info that can be split from the final binary.
+///
+/// Debug info is kept in object files rather than in side files next to them,
+/// as objects are archived into a static library whose build directory is
+/// temporary.
+pub fn split_debug_info_cflags(target_triple: &str) -> Vec<&'static str> {
+    if target_triple.contains("-windows-msvc") {
+        vec!["/Z7"]
+    } else {
+        vec!["-g"]
+    }
+}
+
+/// Linker arguments producing debug info that can be split from the final binary.
+///
+/// On Windows, the linker writes debug info to a PDB file next to the binary.
+/// On Linux, a build ID is embedded so debug info extracted with
+/// `objcopy --only-keep-debug` can be matched to the stripped binary. On macOS,
+/// the linker leaves debug info in object files and `dsymutil` must be run on
+/// the binary to produce a `.dSYM` bundle, so no arguments are needed.
+pub fn split_debug_info_linker_args(target_triple: &str) -> Vec<&'static str> {
+    if target_triple.contains("-windows-msvc") {
+        vec!["/DEBUG"]
+    } else if target_triple.contains("-apple-") {
+        vec![]
+    } else {
+        vec!["-Wl,--build-id"]
+    }
+}
+
+/// Run a command, failing if it doesn't exit successfully.
+///
+/// Returns the command line that was run.
+fn run_build_command(logger: &slog::Logger, mut cmd: std::process::Command) -> Result<String> {
+    let command_line = format!("{:?}", cmd);
+    warn!(logger, "running {}", command_line);
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("running {}", command_line))?;
+    if !status.success() {
+        return Err(anyhow!("error running {}", command_line));
+    }
+
+    Ok(command_line)
+}
+
+/// Resolve the program creating static libraries for a target.
+///
+/// This follows the `cc` crate: an `AR_<target>`, `TARGET_AR`, or `AR`
+/// environment variable takes precedence over the target's default archiver.
+fn archiver_command(target_triple: &str) -> std::process::Command {
+    let env_ar = vec![
+        format!("AR_{}", target_triple),
+        format!("AR_{}", target_triple.replace("-", "_")),
+        "TARGET_AR".to_string(),
+        "AR".to_string(),
+    ]
+    .into_iter()
+    .find_map(|key| std::env::var_os(key));
+
+    if let Some(ar) = env_ar {
+        std::process::Command::new(ar)
+    } else if target_triple.contains("-windows-msvc") {
+        cc::windows_registry::find(target_triple, "lib.exe")
+            .unwrap_or_else(|| std::process::Command::new("lib.exe"))
+    } else {
+        std::process::Command::new("ar")
+    }
+}
+
+/// Compile a C source file to an object file.
+///
+/// Returns the command that was run.
+fn compile_object(
+    logger: &slog::Logger,
+    build: &cc::Build,
+    target_triple: &str,
+    source: &Path,
+    object: &Path,
+) -> Result<String> {
+    let mut cmd = build.try_get_compiler()?.to_command();
+
+    cmd.arg("-c");
+    if target_triple.contains("-windows-msvc") {
+        cmd.arg(format!("-Fo{}", object.display()));
+    } else {
+        cmd.arg("-o").arg(object);
+    }
+    cmd.arg(source);
+
+    run_build_command(logger, cmd)
+}
+
+/// Create a static library from object files.
+///
+/// On MSVC, objects are passed to `lib.exe` via a response file written next
+/// to the library, as the many objects of libpython can exceed the maximum
+/// length of a Windows command line.
+///
+/// Returns the command that was run.
+fn archive_objects(
+    logger: &slog::Logger,
+    target_triple: &str,
+    library: &Path,
+    objects: &[PathBuf],
+) -> Result<String> {
+    if library.exists() {
+        fs::remove_file(library)?;
+    }
+
+    let mut cmd = archiver_command(target_triple);
+
+    if target_triple.contains("-windows-msvc") {
+        let response_path = library.with_extension("rsp");
+        let response = objects
+            .iter()
+            .map(|object| format!("\"{}\"\n", object.display()))
+            .collect::<String>();
+        fs::write(&response_path, response)
+            .with_context(|| format!("writing {}", response_path.display()))?;
+
+        cmd.arg("-nologo")
+            .arg(format!("-out:{}", library.display()))
+            .arg(format!("@{}", response_path.display()));
+    } else {
+        cmd.arg("crs").arg(library);
+        cmd.args(objects);
+    }
+
+    run_build_command(logger, cmd)
+}
+
+/// Resolve the command line of the final link from build script directives.
+///
+/// The link is performed by rustc, which invokes the target's linker with
+/// the Rust objects and libraries of the binary in addition to the arguments
+/// returned here. The linker can be overridden via the
+/// `CARGO_TARGET_<TRIPLE>_LINKER` environment variable, as it can for Cargo.
+pub fn link_command(
+    target_triple: &str,
+    cargo_metadata: &[String],
+    linker_args: &[String],
+) -> String {
+    let msvc = target_triple.contains("-windows-msvc");
+
+    let linker = std::env::var(format!(
+        "CARGO_TARGET_{}_LINKER",
+        target_triple.to_uppercase().replace("-", "_")
+    ))
+    .unwrap_or_else(|_| if msvc { "link.exe" } else { "cc" }.to_string());
+
+    let mut cmd = std::process::Command::new(linker);
+    cmd.args(linker_args);
+
+    for line in cargo_metadata {
+        if let Some(path) = line.splitn(2, "cargo:rustc-link-search=native=").nth(1) {
+            if msvc {
+                cmd.arg(format!("/LIBPATH:{}", path));
+            } else {
+                cmd.arg(format!("-L{}", path));
+            }
+        } else if let Some(lib) = line.splitn(2, "cargo:rustc-link-lib=").nth(1) {
+            let mut parts = lib.splitn(2, '=');
+            let (kind, name) = match (parts.next(), parts.next()) {
+                (Some(kind), Some(name)) => (Some(kind), name),
+                (Some(name), None) => (None, name),
+                _ => continue,
+            };
+
+            if kind == Some("framework") {
+                cmd.arg("-framework").arg(name);
+            } else if msvc {
+                cmd.arg(format!("{}.lib", name));
+            } else {
+                cmd.arg(format!("-l{}", name));
+            }
+        }
+    }
+
+    format!("{:?}", cmd)
+}
+
 #[derive(Debug)]
 pub struct LibpythonInfo {
     pub libpython_path: PathBuf,
     pub libpyembeddedconfig_path: PathBuf,
     pub cargo_metadata: Vec<String>,
+    /// Arguments to pass to the linker when linking the final binary.
+    ///
+    /// See `PythonLinkingInfo::linker_args`.
+    pub linker_args: Vec<String>,
     pub license_infos: BTreeMap<String, Vec<LicenseInfo>>,
+    /// Target triple libpython was built for.
+    pub target_triple: String,
+    /// Commands run to build the libraries, with a description of each.
+    pub build_commands: Vec<(String, String)>,
+    /// Object files archived into libpython, in archive order.
+    pub objects: Vec<PathBuf>,
+}
+
+impl LibpythonInfo {
+    /// Describe how the binary containing this libpython is linked.
+    ///
+    /// `cargo_metadata` holds the build script lines that drive the final link
+    /// performed by rustc and `linker_args` the arguments passed to the linker
+    /// through rustc. The description lists the commands that compiled
+    /// `config.c` and archived the static libraries, followed by the linker
+    /// invocation of the final link (see `link_command()`). Paths under
+    /// temporary build directories do not outlive the build.
+    pub fn describe_link(&self, cargo_metadata: &[String], linker_args: &[String]) -> String {
+        let mut lines = Vec::new();
+
+        for (description, command) in &self.build_commands {
+            lines.push(format!("# {}", description));
+            lines.push(command.clone());
+            lines.push(String::new());
+        }
+
+        lines.push("# link (run by rustc, which adds Rust objects and libraries)".to_string());
+        lines.push(link_command(
+            &self.target_triple,
+            cargo_metadata,
+            linker_args,
+        ));
+        lines.push(String::new());
+
+        lines.join("\n")
+    }
 }
 
 /// Create a static libpython from a Python distribution.
 ///
 /// Returns a vector of cargo: lines that can be printed in build scripts.
-#[allow(clippy::cognitive_complexity)]
+///
+/// Object files whose distribution relative paths are in `excluded_object_files`
+/// are not linked.
+///
+/// `name_prefix` is prepended to the names of generated libraries so multiple
+/// embedded Pythons can coexist in the same binary.
+///
+/// If `split_debug_info` is set, code compiled here includes debug info suitable
+/// for splitting from the final binary. Object files from the distribution are
+/// linked as is.
+///
+/// Temporary files are written under `temp_dir_base` if set, or the system
+/// temporary directory otherwise.
+#[allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 pub fn link_libpython(
     logger: &slog::Logger,
     dist: &StandaloneDistribution,
@@ -86,10 +323,16 @@ pub fn link_libpython(
     host_triple: &str,
     target_triple: &str,
     opt_level: &str,
+    excluded_object_files: &BTreeSet<PathBuf>,
+    name_prefix: &str,
+    split_debug_info: bool,
+    temp_dir_base: Option<&Path>,
 ) -> Result<LibpythonInfo> {
     let mut cargo_metadata: Vec<String> = Vec::new();
+    let mut linker_args = Vec::new();
+    let python_path = dist.base_dir.join("python");
 
-    let temp_dir = tempdir::TempDir::new("libpython")?;
+    let temp_dir = new_temp_dir_in(temp_dir_base, "libpython")?;
     let temp_dir_path = temp_dir.path();
 
     let windows = match target_triple {
@@ -128,39 +371,62 @@ pub fn link_libpython(
     }
 
     warn!(logger, "compiling custom config.c to object file");
+    let mut build_commands = Vec::new();
     let mut build = cc::Build::new();
 
     for flag in &dist.inittab_cflags {
         build.flag(flag);
     }
 
+    if split_debug_info {
+        for flag in split_debug_info_cflags(target_triple) {
+            build.flag(flag);
+        }
+    }
+
     build
         .out_dir(out_dir)
         .host(host_triple)
         .target(target_triple)
         .opt_level_str(opt_level)
-        .file(config_c_temp_path)
         .include(temp_dir_path)
-        .cargo_metadata(false)
-        .compile("pyembeddedconfig");
+        .cargo_metadata(false);
+
+    let config_o_path = out_dir.join(if windows { "config.obj" } else { "config.o" });
+    build_commands.push((
+        format!("compile {}", config_o_path.display()),
+        compile_object(
+            logger,
+            &build,
+            target_triple,
+            &config_c_temp_path,
+            &config_o_path,
+        )?,
+    ));
 
     let libpyembeddedconfig_path = out_dir.join(if windows {
-        "pyembeddedconfig.lib"
+        format!("{}pyembeddedconfig.lib", name_prefix)
     } else {
-        "libpyembeddedconfig.a"
+        format!("lib{}pyembeddedconfig.a", name_prefix)
     });
+    build_commands.push((
+        format!("archive {}", libpyembeddedconfig_path.display()),
+        archive_objects(
+            logger,
+            target_triple,
+            &libpyembeddedconfig_path,
+            &[config_o_path],
+        )?,
+    ));
 
     // Since we disabled cargo metadata lines above.
-    cargo_metadata.push("cargo:rustc-link-lib=static=pyembeddedconfig".to_string());
+    cargo_metadata.push(format!(
+        "cargo:rustc-link-lib=static={}pyembeddedconfig",
+        name_prefix
+    ));
 
     warn!(logger, "resolving inputs for custom Python library...");
-    let mut build = cc::Build::new();
-    build.out_dir(out_dir);
-    build.host(host_triple);
-    build.target(target_triple);
-    build.opt_level_str(opt_level);
-    // We handle this ourselves.
-    build.cargo_metadata(false);
+    let mut objects = Vec::new();
 
     info!(
         logger,
@@ -180,13 +446,22 @@ pub fn link_libpython(
             continue;
         }
 
+        if excluded_object_files.contains(rel_path) {
+            warn!(
+                logger,
+                "excluding {} from libpython as requested",
+                rel_path.display()
+            );
+            continue;
+        }
+
         let parent = temp_dir_path.join(rel_path.parent().unwrap());
         create_dir_all(parent)?;
 
         let full = temp_dir_path.join(rel_path);
         fs::copy(fs_path, &full)?;
 
-        build.object(&full);
+        objects.push(full);
     }
 
     // For each extension module, extract and use its object file. We also
@@ -214,21 +489,40 @@ pub fn link_libpython(
 
     let linking_info = resources.resolve_libpython_linking_info(logger)?;
 
+    let rpath_entries = linking_info.rpath_entries(target_triple);
+    let rpath_linker_args = linking_info.rpath_linker_args(target_triple);
+
     needed_libraries.extend(linking_info.link_libraries);
     needed_frameworks.extend(linking_info.link_frameworks);
     needed_system_libraries.extend(linking_info.link_system_libraries);
     needed_libraries_external.extend(linking_info.link_libraries_external);
 
+    for entry in &rpath_entries {
+        warn!(logger, "adding rpath entry {}", entry);
+    }
+    linker_args.extend(rpath_linker_args);
+
     for (i, object_file) in linking_info.object_files.iter().enumerate() {
         match object_file {
             DataLocation::Memory(data) => {
                 let out_path = temp_dir_path.join(format!("libpython.{}.o", i));
 
                 fs::write(&out_path, data)?;
-                build.object(&out_path);
+                objects.push(out_path);
             }
             DataLocation::Path(p) => {
-                build.object(&p);
+                if let Ok(rel_path) = p.strip_prefix(&python_path) {
+                    if excluded_object_files.contains(rel_path) {
+                        warn!(
+                            logger,
+                            "excluding {} from libpython as requested",
+                            rel_path.display()
+                        );
+                        continue;
+                    }
+                }
+
+                objects.push(p.clone());
             }
         }
     }
@@ -292,17 +586,23 @@ pub fn link_libpython(
     // Our current workaround is to produce a ``pythonXY.lib`` file. This satisfies
     // the requirement of ``python3-sys`` that a ``pythonXY.lib`` file exists.
 
-    warn!(logger, "compiling libpythonXY...");
-    build.compile("pythonXY");
-    warn!(logger, "libpythonXY created");
-
     let libpython_path = out_dir.join(if windows {
-        "pythonXY.lib"
+        format!("{}pythonXY.lib", name_prefix)
     } else {
-        "libpythonXY.a"
+        format!("lib{}pythonXY.a", name_prefix)
     });
 
-    cargo_metadata.push("cargo:rustc-link-lib=static=pythonXY".to_string());
+    warn!(logger, "compiling libpythonXY...");
+    build_commands.push((
+        format!("archive {}", libpython_path.display()),
+        archive_objects(logger, target_triple, &libpython_path, &objects)?,
+    ));
+    warn!(logger, "libpythonXY created");
+
+    cargo_metadata.push(format!(
+        "cargo:rustc-link-lib=static={}pythonXY",
+        name_prefix
+    ));
     cargo_metadata.push(format!(
         "cargo:rustc-link-search=native={}",
         out_dir.display()
@@ -329,6 +629,10 @@ pub fn link_libpython(
         libpython_path,
         libpyembeddedconfig_path,
         cargo_metadata,
+        linker_args,
         license_infos,
+        target_triple: target_triple.to_string(),
+        build_commands,
+        objects,
     })
 }
@@ -14,8 +14,11 @@ pub mod distribution;
 pub mod distutils;
 pub mod embedded_resource;
 pub mod filtering;
+pub mod import_graph;
 pub mod libpython;
 pub mod packaging_tool;
 pub mod pyembed;
 pub mod resource;
+pub mod resource_snapshot;
 pub mod standalone_distribution;
+pub mod warnings;
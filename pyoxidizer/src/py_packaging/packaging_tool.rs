@@ -11,6 +11,8 @@ use {
     super::distribution::{download_distribution, PythonDistribution},
     super::distutils::read_built_extensions,
     super::standalone_distribution::resolve_python_paths,
+    super::warnings::BuildWarnings,
+    crate::environment::new_temp_dir,
     crate::python_distributions::GET_PIP_PY_19,
     anyhow::{anyhow, Context, Result},
     python_packaging::filesystem_scanning::find_python_resources,
@@ -18,7 +20,7 @@ use {
     slog::warn,
     std::collections::HashMap,
     std::hash::BuildHasher,
-    std::io::{BufRead, BufReader},
+    std::io::{BufRead, BufReader, Cursor, Read},
     std::path::{Path, PathBuf},
 };
 
@@ -76,7 +78,7 @@ pub fn bootstrap_packaging_tools(
     let get_pip_py_path =
         download_distribution(&GET_PIP_PY_19.url, &GET_PIP_PY_19.sha256, cache_dir)?;
 
-    let temp_dir = tempdir::TempDir::new("pyoxidizer-bootstrap-packaging")?;
+    let temp_dir = new_temp_dir("bootstrap-packaging")?;
 
     // We need to hack `get-pip.py`'s source code to allow exclusive use of a
     // requirements file for installing `pip`. The `implicit_*` variables control
@@ -172,6 +174,7 @@ pub fn bootstrap_packaging_tools(
 /// Find resources installed as part of a packaging operation.
 pub fn find_resources(
     logger: &slog::Logger,
+    warnings: &mut BuildWarnings,
     dist: &dyn PythonDistribution,
     path: &Path,
     state_dir: Option<PathBuf>,
@@ -208,19 +211,53 @@ pub fn find_resources(
         }
     }
 
-    dist.filter_compatible_python_resources(logger, &res)
+    dist.filter_compatible_python_resources(logger, warnings, &res)
+}
+
+/// Find resources in a tar archive of a Python package tree.
+///
+/// The archive may be uncompressed or compressed with gzip or zstd. The
+/// compression format is detected from the content of the archive.
+///
+/// The archive is extracted to a temporary directory that is deleted before
+/// returning. Returned resources are backed by memory.
+pub fn find_resources_in_tarball(
+    logger: &slog::Logger,
+    warnings: &mut BuildWarnings,
+    dist: &dyn PythonDistribution,
+    path: &Path,
+) -> Result<Vec<PythonResource>> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+    let reader: Box<dyn Read> = if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::read::GzDecoder::new(Cursor::new(data)))
+    } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::stream::Decoder::new(Cursor::new(data))?)
+    } else {
+        Box::new(Cursor::new(data))
+    };
+
+    let temp_dir = new_temp_dir("package-tarball")?;
+
+    warn!(logger, "extracting {}", path.display());
+    tar::Archive::new(reader)
+        .unpack(temp_dir.path())
+        .with_context(|| format!("extracting {}", path.display()))?;
+
+    find_resources(logger, warnings, dist, temp_dir.path(), None)
 }
 
 /// Run `pip install` and return found resources.
 pub fn pip_install<S: BuildHasher>(
     logger: &slog::Logger,
+    warnings: &mut BuildWarnings,
     dist: &dyn PythonDistribution,
     libpython_link_mode: LibpythonLinkMode,
     verbose: bool,
     install_args: &[String],
     extra_envs: &HashMap<String, String, S>,
 ) -> Result<Vec<PythonResource>> {
-    let temp_dir = tempdir::TempDir::new("pyoxidizer-pip-install")?;
+    let temp_dir = new_temp_dir("pip-install")?;
 
     dist.ensure_pip(logger)?;
 
@@ -280,23 +317,25 @@ pub fn pip_install<S: BuildHasher>(
         None => None,
     };
 
-    find_resources(logger, dist, &target_dir, state_dir)
+    find_resources(logger, warnings, dist, &target_dir, state_dir)
 }
 
 /// Discover Python resources from a populated virtualenv directory.
 pub fn read_virtualenv(
     logger: &slog::Logger,
+    warnings: &mut BuildWarnings,
     dist: &dyn PythonDistribution,
     path: &Path,
 ) -> Result<Vec<PythonResource>> {
     let python_paths = resolve_python_paths(path, &dist.python_major_minor_version());
 
-    find_resources(logger, dist, &python_paths.site_packages, None)
+    find_resources(logger, warnings, dist, &python_paths.site_packages, None)
 }
 
 /// Run `setup.py install` against a path and return found resources.
 pub fn setup_py_install<S: BuildHasher>(
     logger: &slog::Logger,
+    warnings: &mut BuildWarnings,
     dist: &dyn PythonDistribution,
     libpython_link_mode: LibpythonLinkMode,
     package_path: &Path,
@@ -311,7 +350,7 @@ pub fn setup_py_install<S: BuildHasher>(
         ));
     }
 
-    let temp_dir = tempdir::TempDir::new("pyoxidizer-setup-py-install")?;
+    let temp_dir = new_temp_dir("setup-py-install")?;
 
     let target_dir_path = temp_dir.path().join("install");
     let target_dir_s = target_dir_path.display().to_string();
@@ -381,7 +420,13 @@ pub fn setup_py_install<S: BuildHasher>(
         "scanning {} for resources",
         python_paths.site_packages.display()
     );
-    find_resources(logger, dist, &python_paths.site_packages, state_dir)
+    find_resources(
+        logger,
+        warnings,
+        dist,
+        &python_paths.site_packages,
+        state_dir,
+    )
 }
 
 #[cfg(test)]
@@ -395,6 +440,7 @@ mod tests {
 
         let resources: Vec<PythonResource> = pip_install(
             &logger,
+            &mut BuildWarnings::default(),
             distribution.deref().as_ref(),
             LibpythonLinkMode::Dynamic,
             false,
@@ -417,6 +463,7 @@ mod tests {
 
         let resources: Vec<PythonResource> = pip_install(
             &logger,
+            &mut BuildWarnings::default(),
             distribution.deref().as_ref(),
             LibpythonLinkMode::Dynamic,
             false,
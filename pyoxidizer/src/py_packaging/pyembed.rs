@@ -8,16 +8,30 @@ Functionality related to the pyembed crate.
 
 use anyhow::Result;
 use itertools::Itertools;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use super::config::{EmbeddedPythonConfig, RawAllocator, RunMode, TerminfoResolution};
 
+/// Obtain the name of the static holding embedded packed resources data.
+pub fn packed_resources_symbol(symbol_prefix: &str) -> String {
+    format!("{}PACKED_RESOURCES", symbol_prefix.to_uppercase())
+}
+
 /// Obtain the Rust source code to construct a PythonConfig instance.
+///
+/// `packed_resources_symbol` is the name of the static holding packed
+/// resources data, as written by `write_default_python_config_rs()`.
+///
+/// If `packed_resources_sidecar` is set, packed resources are not embedded
+/// in the binary and are instead loaded from that file, relative to the
+/// executable, at run-time.
 pub fn derive_python_config(
     embedded: &EmbeddedPythonConfig,
-    embedded_resources_path: &PathBuf,
+    packed_resources_symbol: &str,
+    packed_resources_sidecar: Option<&str>,
 ) -> String {
     format!(
         "pyembed::PythonConfig {{\n    \
@@ -41,8 +55,14 @@ pub fn derive_python_config(
          parser_debug: {},\n    \
          quiet: {},\n    \
          use_hash_seed: {},\n    \
+         hash_seed: {},\n    \
+         recursion_limit: {},\n    \
+         thread_stack_size: {},\n    \
+         fault_handler: {},\n    \
+         development_mode: {},\n    \
          verbose: {},\n    \
-         packed_resources: include_bytes!(r#\"{}\"#),\n    \
+         packed_resources: {},\n    \
+         packed_resources_path: {},\n    \
          extra_extension_modules: vec![],\n    \
          argvb: false,\n    \
          sys_frozen: {},\n    \
@@ -50,6 +70,7 @@ pub fn derive_python_config(
          raw_allocator: {},\n    \
          terminfo_resolution: {},\n    \
          write_modules_directory_env: {},\n    \
+         pre_import_code: {},\n    \
          run: {},\n\
          }}",
         match &embedded.stdio_encoding_name {
@@ -82,8 +103,30 @@ pub fn derive_python_config(
         embedded.parser_debug,
         embedded.quiet,
         embedded.use_hash_seed,
+        match embedded.hash_seed {
+            Some(value) => format!("Some({})", value),
+            None => "None".to_string(),
+        },
+        match embedded.recursion_limit {
+            Some(value) => format!("Some({})", value),
+            None => "None".to_string(),
+        },
+        match embedded.thread_stack_size {
+            Some(value) => format!("Some({})", value),
+            None => "None".to_string(),
+        },
+        embedded.fault_handler,
+        embedded.development_mode,
         embedded.verbose,
-        embedded_resources_path.display(),
+        if packed_resources_sidecar.is_some() {
+            "&[]".to_string()
+        } else {
+            packed_resources_symbol.to_string()
+        },
+        match packed_resources_sidecar {
+            Some(filename) => format!("Some(std::path::PathBuf::from(r###\"{}\"###))", filename),
+            None => "None".to_string(),
+        },
         embedded.sys_frozen,
         embedded.sys_meipass,
         match embedded.raw_allocator {
@@ -102,6 +145,10 @@ pub fn derive_python_config(
             Some(path) => "Some(\"".to_owned() + &path + "\".to_string())",
             _ => "None".to_owned(),
         },
+        match &embedded.pre_import_code {
+            Some(code) => format!("Some({:?}.to_string())", code),
+            None => "None".to_owned(),
+        },
         match embedded.run_mode {
             RunMode::Noop => "pyembed::PythonRunMode::None".to_owned(),
             RunMode::Repl => "pyembed::PythonRunMode::Repl".to_owned(),
@@ -124,26 +171,88 @@ pub fn derive_python_config(
     )
 }
 
-/// Write a standalone .rs file containing a function for obtaining the default PythonConfig.
-pub fn write_default_python_config_rs(path: &Path, python_config_rs: &str) -> Result<()> {
+/// Write a standalone .rs file containing functions for obtaining PythonConfig instances.
+///
+/// `default_python_config()` returns the default configuration.
+/// `named_python_config()` returns one of `named_configs_rs`, keyed by name.
+/// `selected_python_config()` returns the named configuration specified by
+/// the `selector_env` environment variable at run-time, or the default
+/// configuration if it isn't set.
+///
+/// If `embedded_resources_path` is set, a static holding the content of
+/// that file is defined for configurations to reference.
+///
+/// The names of the functions and of the static are prefixed with
+/// `symbol_prefix` so files for multiple embedded interpreters can be
+/// included in the same module.
+pub fn write_default_python_config_rs(
+    path: &Path,
+    symbol_prefix: &str,
+    embedded_resources_path: Option<&Path>,
+    python_config_rs: &str,
+    named_configs_rs: &BTreeMap<String, String>,
+    selector_env: &str,
+) -> Result<()> {
     let mut f = File::create(&path)?;
 
+    if let Some(embedded_resources_path) = embedded_resources_path {
+        f.write_fmt(format_args!(
+            "/// Packed resources data embedded in the binary.\n\
+             #[allow(dead_code)]\n\
+             pub static {}: &[u8] = include_bytes!(r#\"{}\"#);\n\n",
+            packed_resources_symbol(symbol_prefix),
+            embedded_resources_path.display()
+        ))?;
+    }
+
     // Ideally we would have a const struct, but we need to do some
     // dynamic allocations. Using a function avoids having to pull in a
     // dependency on lazy_static.
-    let indented = python_config_rs
-        .split('\n')
-        .map(|line| "    ".to_owned() + line)
-        .join("\n");
-
+    //
+    // The source isn't indented because it may contain multi-line string
+    // literals, whose content indentation would alter.
     f.write_fmt(format_args!(
         "/// Obtain the default Python configuration\n\
          ///\n\
          /// The crate is compiled with a default Python configuration embedded\n\
          /// in the crate. This function will return an instance of that\n\
          /// configuration.\n\
-         pub fn default_python_config<'a>() -> pyembed::PythonConfig<'a> {{\n{}\n}}\n",
-        indented
+         pub fn {}default_python_config<'a>() -> pyembed::PythonConfig<'a> {{\n{}\n}}\n",
+        symbol_prefix, python_config_rs
+    ))?;
+
+    let arms = named_configs_rs
+        .iter()
+        .map(|(name, config_rs)| format!("        \"{}\" => Some({}),\n", name, config_rs))
+        .join("");
+
+    f.write_fmt(format_args!(
+        "\n\
+         /// Obtain the Python configuration with the given name.\n\
+         ///\n\
+         /// Returns `None` if no configuration has that name.\n\
+         #[allow(dead_code)]\n\
+         pub fn {prefix}named_python_config<'a>(name: &str) -> Option<pyembed::PythonConfig<'a>> {{\n    \
+         match name {{\n\
+         {}        _ => None,\n    \
+         }}\n\
+         }}\n\
+         \n\
+         /// Obtain the Python configuration selected at run-time.\n\
+         ///\n\
+         /// If the `{env}` environment variable is set, the named configuration\n\
+         /// it specifies is returned. Otherwise the default configuration is returned.\n\
+         #[allow(dead_code)]\n\
+         pub fn {prefix}selected_python_config<'a>() -> Result<pyembed::PythonConfig<'a>, String> {{\n    \
+         match std::env::var(\"{env}\") {{\n        \
+         Ok(name) => {prefix}named_python_config(&name)\n            \
+         .ok_or_else(|| format!(\"unknown Python configuration {{}} specified by {env}\", name)),\n        \
+         Err(_) => Ok({prefix}default_python_config()),\n    \
+         }}\n\
+         }}\n",
+        arms,
+        env = selector_env,
+        prefix = symbol_prefix
     ))?;
 
     Ok(())
@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Recording and comparing the set of resources in a build.
+
+A `ResourceSnapshot` captures the name, type, and size of each resource
+added to a binary builder. Snapshots serialize to JSON so they can be
+stored and compared against later builds to detect unexpected growth
+or unwanted resources.
+*/
+
+use {
+    anyhow::{Context, Result},
+    python_packaging::resource::DataLocation,
+    python_packaging::resource_collection::{PrePackagedResource, PythonModuleBytecodeProvider},
+    python_packed_resources::data::ResourceFlavor,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// Version of the serialized snapshot format.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Describes a single resource in a `ResourceSnapshot`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResourceSnapshotEntry {
+    /// The type of resource.
+    pub flavor: String,
+
+    /// Total size in bytes of all data associated with the resource.
+    ///
+    /// Bytecode that will be compiled from source is counted using the
+    /// size of the source.
+    pub size: u64,
+}
+
+/// A serializable record of resources in a build.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResourceSnapshot {
+    /// Version of the snapshot format.
+    pub version: u32,
+
+    /// Resources keyed by name.
+    pub resources: BTreeMap<String, ResourceSnapshotEntry>,
+}
+
+/// Differences between two `ResourceSnapshot`s.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceDelta {
+    /// Names of resources only present in the current snapshot.
+    pub added: Vec<String>,
+
+    /// Names of resources only present in the previous snapshot.
+    pub removed: Vec<String>,
+
+    /// Resources whose size changed, as (name, previous size, current size).
+    pub resized: Vec<(String, u64, u64)>,
+}
+
+impl ResourceDelta {
+    /// Whether there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.resized.is_empty()
+    }
+}
+
+fn flavor_name(flavor: ResourceFlavor) -> &'static str {
+    match flavor {
+        ResourceFlavor::None => "none",
+        ResourceFlavor::Module => "module",
+        ResourceFlavor::BuiltinExtensionModule => "builtin-extension-module",
+        ResourceFlavor::FrozenModule => "frozen-module",
+        ResourceFlavor::Extension => "extension",
+        ResourceFlavor::SharedLibrary => "shared-library",
+    }
+}
+
+fn location_size(location: &DataLocation) -> Result<u64> {
+    match location {
+        DataLocation::Memory(data) => Ok(data.len() as u64),
+        DataLocation::Path(path) => Ok(std::fs::metadata(path)
+            .with_context(|| format!("obtaining size of {}", path.display()))?
+            .len()),
+    }
+}
+
+fn bytecode_size(provider: &PythonModuleBytecodeProvider) -> Result<u64> {
+    match provider {
+        PythonModuleBytecodeProvider::Provided(location) => location_size(location),
+        PythonModuleBytecodeProvider::FromSource(location) => location_size(location),
+    }
+}
+
+fn resource_size(resource: &PrePackagedResource) -> Result<u64> {
+    let mut size = 0;
+
+    for location in [
+        &resource.in_memory_source,
+        &resource.in_memory_extension_module_shared_library,
+        &resource.in_memory_shared_library,
+    ]
+    .iter()
+    .filter_map(|x| x.as_ref())
+    {
+        size += location_size(location)?;
+    }
+
+    for provider in [
+        &resource.in_memory_bytecode,
+        &resource.in_memory_bytecode_opt1,
+        &resource.in_memory_bytecode_opt2,
+    ]
+    .iter()
+    .filter_map(|x| x.as_ref())
+    {
+        size += bytecode_size(provider)?;
+    }
+
+    for resources in [
+        &resource.in_memory_resources,
+        &resource.in_memory_distribution_resources,
+    ]
+    .iter()
+    .filter_map(|x| x.as_ref())
+    {
+        for location in resources.values() {
+            size += location_size(location)?;
+        }
+    }
+
+    if let Some((_, location)) = &resource.relative_path_module_source {
+        size += location_size(location)?;
+    }
+
+    for value in [
+        &resource.relative_path_bytecode,
+        &resource.relative_path_bytecode_opt1,
+        &resource.relative_path_bytecode_opt2,
+    ]
+    .iter()
+    .filter_map(|x| x.as_ref())
+    {
+        size += bytecode_size(&value.2)?;
+    }
+
+    if let Some((_, _, location)) = &resource.relative_path_extension_module_shared_library {
+        size += location_size(location)?;
+    }
+
+    for resources in [
+        &resource.relative_path_package_resources,
+        &resource.relative_path_distribution_resources,
+    ]
+    .iter()
+    .filter_map(|x| x.as_ref())
+    {
+        for (_, _, location) in resources.values() {
+            size += location_size(location)?;
+        }
+    }
+
+    if let Some((_, location)) = &resource.relative_path_shared_library {
+        size += location_size(location)?;
+    }
+
+    Ok(size)
+}
+
+impl ResourceSnapshot {
+    /// Construct an instance from an iterator of resources.
+    pub fn from_resources<'a>(
+        resources: impl Iterator<Item = (&'a String, &'a PrePackagedResource)>,
+    ) -> Result<Self> {
+        let mut res = BTreeMap::new();
+
+        for (name, resource) in resources {
+            res.insert(
+                name.clone(),
+                ResourceSnapshotEntry {
+                    flavor: flavor_name(resource.flavor).to_string(),
+                    size: resource_size(resource)?,
+                },
+            );
+        }
+
+        Ok(Self {
+            version: SNAPSHOT_FORMAT_VERSION,
+            resources: res,
+        })
+    }
+
+    /// Parse an instance from JSON.
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    /// Serialize this instance to JSON.
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Compute differences from a previous snapshot.
+    pub fn diff(&self, previous: &ResourceSnapshot) -> ResourceDelta {
+        let mut delta = ResourceDelta::default();
+
+        for (name, entry) in &self.resources {
+            match previous.resources.get(name) {
+                Some(prev) if prev.size != entry.size => {
+                    delta.resized.push((name.clone(), prev.size, entry.size));
+                }
+                Some(_) => {}
+                None => delta.added.push(name.clone()),
+            }
+        }
+
+        for name in previous.resources.keys() {
+            if !self.resources.contains_key(name) {
+                delta.removed.push(name.clone());
+            }
+        }
+
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str, source: &[u8]) -> PrePackagedResource {
+        PrePackagedResource {
+            flavor: ResourceFlavor::Module,
+            name: name.to_string(),
+            in_memory_source: Some(DataLocation::Memory(source.to_vec())),
+            ..PrePackagedResource::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshot_diff() -> Result<()> {
+        let previous = vec![
+            ("a".to_string(), module("a", b"a = 1")),
+            ("b".to_string(), module("b", b"b = 1")),
+        ];
+        let current = vec![
+            ("a".to_string(), module("a", b"a = 42")),
+            ("c".to_string(), module("c", b"c = 1")),
+        ];
+
+        let previous = ResourceSnapshot::from_resources(previous.iter().map(|(k, v)| (k, v)))?;
+        let current = ResourceSnapshot::from_resources(current.iter().map(|(k, v)| (k, v)))?;
+
+        assert_eq!(previous.resources.get("a").unwrap().size, 5);
+        assert_eq!(previous.resources.get("a").unwrap().flavor, "module");
+
+        let delta = current.diff(&previous);
+        assert_eq!(delta.added, vec!["c".to_string()]);
+        assert_eq!(delta.removed, vec!["b".to_string()]);
+        assert_eq!(delta.resized, vec![("a".to_string(), 5, 6)]);
+
+        assert!(current.diff(&current).is_empty());
+
+        let roundtrip = ResourceSnapshot::from_json(&current.to_json()?)?;
+        assert_eq!(roundtrip, current);
+
+        Ok(())
+    }
+}
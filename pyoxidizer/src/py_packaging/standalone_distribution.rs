@@ -6,41 +6,57 @@
 
 use {
     super::binary::{
-        EmbeddedPythonBinaryData, EmbeddedResourcesBlobs, LibpythonLinkMode, PythonBinaryBuilder,
-        PythonLinkingInfo,
+        check_source_compiles, validate_artifact_name_prefix, EmbeddedPythonBinaryData,
+        EmbeddedResourcesBlobs, LibpythonLinkMode, PolicyReport, PolicyTallies,
+        PythonBinaryBuilder, PythonLinkingInfo,
     },
-    super::config::{EmbeddedPythonConfig, RawAllocator},
+    super::config::{EmbeddedPythonConfig, RawAllocator, RunMode, DEFAULT_CONFIG_SELECTOR_ENV},
     super::distribution::{
         is_stdlib_test_package, resolve_python_distribution_from_location, BinaryLibpythonLinkMode,
         DistributionExtractLock, PythonDistribution, PythonDistributionLocation,
     },
     super::distutils::prepare_hacked_distutils,
-    super::embedded_resource::{EmbeddedPythonResources, PrePackagedResources},
-    super::libpython::link_libpython,
-    super::packaging_tool::{find_resources, pip_install, read_virtualenv, setup_py_install},
+    super::embedded_resource::{
+        EmbeddedPythonResources, PrePackagedResources, ResourceCompressionOptions,
+        UnresolvableResourcePolicy,
+    },
+    super::libpython::{link_libpython, split_debug_info_linker_args},
+    super::packaging_tool::{
+        find_resources, find_resources_in_tarball, pip_install, read_virtualenv, setup_py_install,
+    },
+    super::warnings::BuildWarnings,
+    crate::analyze::object_file_architecture,
     crate::app_packaging::resource::FileContent,
+    crate::environment::{fast_build_enabled, new_temp_dir, new_temp_dir_in, FAST_BUILD_ENV},
     anyhow::{anyhow, Context, Result},
     copy_dir::copy_dir,
+    fs2::FileExt,
     lazy_static::lazy_static,
     path_dedot::ParseDot,
-    python_packaging::bytecode::BytecodeCompiler,
+    python_packaging::bytecode::{BytecodeCompiler, CompileMode},
     python_packaging::filesystem_scanning::{find_python_resources, walk_tree_files},
     python_packaging::module_util::{is_package_from_path, PythonModuleSuffixes},
-    python_packaging::policy::{PythonPackagingPolicy, PythonResourcesPolicy},
+    python_packaging::policy::{
+        ExtensionModuleFilter, PythonPackagingPolicy, PythonResourcesPolicy,
+    },
     python_packaging::resource::{
         BytecodeOptimizationLevel, DataLocation, LibraryDependency, PythonExtensionModule,
-        PythonExtensionModuleVariants, PythonModuleBytecodeFromSource, PythonModuleSource,
-        PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+        PythonExtensionModuleVariants, PythonModuleBytecode, PythonModuleBytecodeFromSource,
+        PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
+        PythonResource, VariantDescription,
     },
     python_packaging::resource_collection::{ConcreteResourceLocation, PrePackagedResource},
+    python_packed_resources::data::ResourceFlavor,
     serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
     slog::{info, warn},
-    std::collections::{BTreeMap, HashMap},
+    std::borrow::Cow,
+    std::collections::{BTreeMap, BTreeSet, HashMap},
     std::convert::TryFrom,
     std::io::{BufRead, BufReader, Read},
     std::path::{Path, PathBuf},
-    std::sync::Arc,
-    tempdir::TempDir,
+    std::sync::{Arc, Mutex},
+    std::time::SystemTime,
 };
 
 // This needs to be kept in sync with *compiler.py
@@ -58,16 +74,72 @@ const PIP_EXE_BASENAME: &str = "pip3.exe";
 #[cfg(unix)]
 const PIP_EXE_BASENAME: &str = "pip3";
 
+/// Python packages whose modules are all required by a running interpreter.
+///
+/// Modules imported during interpreter initialization are resolved from the
+/// distribution. But codecs in `encodings` are imported on demand and
+/// `importlib` backs the module importer, so these packages are added to
+/// builders in their entirety even when the standard library is excluded.
+pub const BOOTSTRAP_PACKAGES: &[&str] = &["encodings", "importlib"];
+
+/// Python code printing the names of modules imported by interpreter startup.
+const STARTUP_MODULES_CODE: &str = "import sys; print('\\n'.join(sorted(sys.modules)))";
+
+/// Resolve names of modules imported when a Python interpreter initializes.
+///
+/// This runs the interpreter, so platform specific modules it imports
+/// (e.g. `posixpath` vs `ntpath`) are accounted for.
+fn resolve_startup_module_names(python_exe: &Path) -> Result<BTreeSet<String>> {
+    let output = std::process::Command::new(python_exe)
+        .args(&["-E", "-s", "-c", STARTUP_MODULES_CODE])
+        .output()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "unable to resolve startup modules of {}: {}",
+            python_exe.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Public libpython symbols used to embed Python.
+pub const LIBPYTHON_EMBEDDING_SYMBOLS: &[&str] = &[
+    "PyImport_AppendInittab",
+    "PyImport_ExtendInittab",
+    "PyImport_Inittab",
+    "Py_BytesMain",
+    "Py_Finalize",
+    "Py_FinalizeEx",
+    "Py_Initialize",
+    "Py_InitializeEx",
+    "Py_InitializeFromConfig",
+    "Py_Main",
+    "Py_RunMain",
+];
+
+/// Filename listing resources whose data was omitted because it no longer exists.
+pub const OMITTED_RESOURCES_FILENAME: &str = "omitted-resources.txt";
+
 lazy_static! {
     /// Target triples for Linux.
     pub static ref LINUX_TARGET_TRIPLES: Vec<&'static str> = vec![
+        "aarch64-unknown-linux-gnu",
         "x86_64-unknown-linux-gnu",
         "x86_64-unknown-linux-musl",
     ];
 
     /// Target triples for macOS.
     pub static ref MACOS_TARGET_TRIPLES: Vec<&'static str> = vec![
-        "x86_64-apple-ios",
+        "aarch64-apple-darwin",
+        "x86_64-apple-darwin",
     ];
 
     /// Target triples for Windows.
@@ -99,7 +171,7 @@ lazy_static! {
     ];
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct LinkEntry {
     name: String,
     path_static: Option<String>,
@@ -127,7 +199,7 @@ impl LinkEntry {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct PythonBuildExtensionInfo {
     in_core: bool,
     init_fn: String,
@@ -142,7 +214,7 @@ struct PythonBuildExtensionInfo {
     variant: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct PythonBuildCoreInfo {
     objs: Vec<String>,
     links: Vec<LinkEntry>,
@@ -150,7 +222,7 @@ struct PythonBuildCoreInfo {
     static_lib: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct PythonBuildInfo {
     core: PythonBuildCoreInfo,
     extensions: BTreeMap<String, Vec<PythonBuildExtensionInfo>>,
@@ -160,6 +232,57 @@ struct PythonBuildInfo {
     object_file_format: String,
 }
 
+/// Build metadata parsed from a distribution's `PYTHON.json`.
+///
+/// This exposes the object files, link entries, and inittab details that
+/// determine how libpython is linked. It exists to aid debugging
+/// distribution issues.
+#[derive(Clone, Debug)]
+pub struct BuildInfoDebug {
+    build_info: PythonBuildInfo,
+}
+
+impl BuildInfoDebug {
+    /// Object files constituting the Python core, relative to the distribution.
+    pub fn core_objects(&self) -> &[String] {
+        &self.build_info.core.objs
+    }
+
+    /// Names of libraries the Python core links against.
+    pub fn core_link_names(&self) -> Vec<&str> {
+        self.build_info
+            .core
+            .links
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    /// Names of extension modules and their variants.
+    pub fn extension_variants(&self) -> BTreeMap<&str, Vec<&str>> {
+        self.build_info
+            .extensions
+            .iter()
+            .map(|(name, variants)| {
+                (
+                    name.as_str(),
+                    variants.iter().map(|v| v.variant.as_str()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Object file defining `_PyImport_Inittab`, relative to the distribution.
+    pub fn inittab_object(&self) -> &str {
+        &self.build_info.inittab_object
+    }
+
+    /// Serialize all build metadata to pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.build_info)?)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PythonJsonMain {
     version: String,
@@ -189,8 +312,56 @@ struct PythonJsonMain {
     license_path: Option<String>,
     tcl_library_path: Option<String>,
     tcl_library_paths: Option<Vec<String>>,
+    /// Python config vars. Introduced in version 6.
+    python_config_vars: Option<HashMap<String, String>>,
+    /// Fields we don't know about, such as ones added by newer versions.
+    #[serde(flatten)]
+    unknown_fields: HashMap<String, serde_json::Value>,
+}
+
+/// Versions of `PYTHON.json` we know how to parse.
+///
+/// Newer versions only add fields, which are parsed as optional.
+const SUPPORTED_PYTHON_JSON_VERSIONS: &[&str] = &["5", "6"];
+
+/// Describes a problem with the content of a standalone distribution.
+///
+/// Functions analyzing distributions return `anyhow::Error`. When they fail
+/// because of one of these problems, the error can be recovered via
+/// `anyhow::Error::downcast_ref::<DistributionError>()`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistributionError {
+    /// `PYTHON.json` has a version we don't know how to handle.
+    UnsupportedJsonVersion { found: String },
+
+    /// A path required by the distribution is not defined in `PYTHON.json`.
+    MissingPath { key: String },
+
+    /// The distribution's libpython link mode is not known.
+    UnhandledLinkMode(String),
+}
+
+impl std::fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DistributionError::UnsupportedJsonVersion { found } => write!(
+                f,
+                "expected version {} standalone distribution; found version {}",
+                SUPPORTED_PYTHON_JSON_VERSIONS.join(" or "),
+                found
+            ),
+            DistributionError::MissingPath { key } => {
+                write!(f, "{} path not defined in distribution", key)
+            }
+            DistributionError::UnhandledLinkMode(mode) => {
+                write!(f, "unhandled link mode: {}", mode)
+            }
+        }
+    }
 }
 
+impl std::error::Error for DistributionError {}
+
 fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
     if !path.exists() {
         return Err(anyhow!("PYTHON.json does not exist; are you using an up-to-date Python distribution that conforms with our requirements?"));
@@ -209,11 +380,11 @@ fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
                 .as_str()
                 .ok_or_else(|| anyhow!("unable to parse version as a string"))?;
 
-            if version != "5" {
-                return Err(anyhow!(
-                    "expected version 5 standalone distribution; found version {}",
-                    version
-                ));
+            if !SUPPORTED_PYTHON_JSON_VERSIONS.contains(&version) {
+                return Err(DistributionError::UnsupportedJsonVersion {
+                    found: version.to_string(),
+                }
+                .into());
             }
         }
         None => return Err(anyhow!("version key not present in PYTHON.json")),
@@ -224,9 +395,47 @@ fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
     Ok(v)
 }
 
-fn parse_python_json_from_distribution(dist_dir: &Path) -> Result<PythonJsonMain> {
+lazy_static! {
+    /// Parsed `PYTHON.json` files, keyed by path.
+    ///
+    /// Entries record the modification time and size of the file when it
+    /// was parsed so changes to the file are detected.
+    static ref PYTHON_JSON_CACHE: Mutex<HashMap<PathBuf, (SystemTime, u64, Arc<PythonJsonMain>)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Parse a `PYTHON.json` file, reusing a previous parse if the file is unchanged.
+fn parse_python_json_cached(path: &Path) -> Result<Arc<PythonJsonMain>> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        // Let the uncached parser produce a consistent error.
+        Err(_) => return Ok(Arc::new(parse_python_json(path)?)),
+    };
+    let mtime = metadata.modified()?;
+    let size = metadata.len();
+
+    let mut cache = PYTHON_JSON_CACHE.lock().unwrap();
+
+    if let Some((cached_mtime, cached_size, pi)) = cache.get(path) {
+        if *cached_mtime == mtime && *cached_size == size {
+            return Ok(pi.clone());
+        }
+    }
+
+    let pi = Arc::new(parse_python_json(path)?);
+    cache.insert(path.to_path_buf(), (mtime, size, pi.clone()));
+
+    Ok(pi)
+}
+
+fn parse_python_json_from_distribution(dist_dir: &Path) -> Result<Arc<PythonJsonMain>> {
     let python_json_path = dist_dir.join("python").join("PYTHON.json");
-    parse_python_json(&python_json_path)
+    parse_python_json_cached(&python_json_path)
+}
+
+/// Obtain a logger that discards all messages.
+fn discard_logger() -> slog::Logger {
+    slog::Logger::root(slog::Discard, slog::o!())
 }
 
 /// Resolve the path to a `python` executable in a Python distribution.
@@ -236,7 +445,7 @@ pub fn python_exe_path(dist_dir: &Path) -> Result<PathBuf> {
     Ok(dist_dir.join("python").join(&pi.python_exe))
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PythonPaths {
     pub prefix: PathBuf,
     pub bin_dir: PathBuf,
@@ -291,11 +500,162 @@ pub fn resolve_python_paths(base: &Path, python_version: &str) -> PythonPaths {
     }
 }
 
-pub fn invoke_python(python_paths: &PythonPaths, logger: &slog::Logger, args: &[&str]) {
+/// Vendors of distribution specific Rust targets for Linux with glibc.
+const LINUX_GNU_VENDORS: &[&str] = &["pc", "redhat", "suse"];
+
+/// Vendors of distribution specific Rust targets for Linux with musl.
+const LINUX_MUSL_VENDORS: &[&str] = &["alpine", "chimera"];
+
+/// Resolve the Rust target triples a distribution can produce binaries for.
+///
+/// Distributions may annotate their triple with a suffix describing how they
+/// were built (e.g. `x86_64-pc-windows-msvc-shared`). This suffix is removed.
+///
+/// Linux distributions dynamically linking glibc work with any glibc based
+/// target for the same architecture, so distribution specific vendors of
+/// `-linux-gnu` triples are also compatible. The same holds for statically
+/// linked musl distributions and `-linux-musl` triples. `crt_features` are
+/// the C runtime features the distribution was built with.
+pub fn compatible_target_triples(
+    distribution_triple: &str,
+    crt_features: &[String],
+) -> Vec<String> {
+    let distribution_triple = distribution_triple
+        .trim_end_matches("-shared")
+        .trim_end_matches("-static");
+
+    let mut triples = vec![distribution_triple.to_string()];
+
+    let parts = distribution_triple.splitn(4, '-').collect::<Vec<_>>();
+    if let [arch, "unknown", "linux", env] = parts.as_slice() {
+        let has_feature = |feature: &str| crt_features.iter().any(|x| x == feature);
+
+        let vendors: &[&str] = match *env {
+            "gnu" if has_feature("glibc-dynamic") => LINUX_GNU_VENDORS,
+            "musl" if has_feature("static") => LINUX_MUSL_VENDORS,
+            _ => &[],
+        };
+
+        triples.extend(
+            vendors
+                .iter()
+                .map(|vendor| format!("{}-{}-linux-{}", arch, vendor, env)),
+        );
+    }
+
+    triples
+}
+
+/// Whether a distribution's target triple can be used to build for a Rust target triple.
+///
+/// See `compatible_target_triples()` for the triples that are accepted.
+pub fn is_target_triple_compatible(
+    distribution_triple: &str,
+    crt_features: &[String],
+    target_triple: &str,
+) -> bool {
+    compatible_target_triples(distribution_triple, crt_features)
+        .iter()
+        .any(|x| x == target_triple)
+}
+
+/// Ensure a target triple is one distributions can be built for.
+///
+/// The host triple doesn't need to match, so cross-compiling to any known
+/// target is allowed.
+fn validate_target_triple(target_triple: &str) -> Result<()> {
+    let known = LINUX_TARGET_TRIPLES
+        .iter()
+        .chain(MACOS_TARGET_TRIPLES.iter())
+        .chain(WINDOWS_TARGET_TRIPLES.iter())
+        .collect::<Vec<_>>();
+
+    if known.iter().any(|x| **x == target_triple) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "unrecognized target triple: {}; valid target triples are: {}",
+            target_triple,
+            known
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+/// Determine whether static and dynamic linking of libpython are supported.
+///
+/// `target_triple` is the distribution's target triple and `has_shared_library`
+/// whether the distribution provides a shared libpython. Returns a tuple of
+/// whether static and dynamic linking are supported, respectively.
+fn libpython_link_support(target_triple: &str, has_shared_library: bool) -> (bool, bool) {
+    let triple = target_triple
+        .trim_end_matches("-shared")
+        .trim_end_matches("-static");
+
+    if triple.contains("pc-windows") {
+        // On Windows, support for libpython linkage is determined
+        // by presence of a shared library in the distribution. This
+        // isn't entirely semantically correct. Since we use `dllexport`
+        // for all symbols in standalone distributions, it may
+        // theoretically be possible to produce both a static and dynamic
+        // libpython from the same object files. But since the
+        // static and dynamic distributions are built so differently, we
+        // don't want to take any chances and we force each distribution
+        // to its own domain.
+        (!has_shared_library, has_shared_library)
+    } else if triple.contains("linux-musl") {
+        // Musl distributions are traditionally static only. But newer
+        // distributions may ship a usable shared libpython, so dynamic
+        // linking is supported if one is present.
+        (true, has_shared_library)
+    } else if LINUX_TARGET_TRIPLES.contains(&triple) || MACOS_TARGET_TRIPLES.contains(&triple) {
+        // On Linux and macOS we can choose which link mode to use.
+        (true, true)
+    } else {
+        // Elsewhere, only link dynamically if there is something to link against.
+        (true, has_shared_library)
+    }
+}
+
+/// Log every line read from `reader` until end of file.
+///
+/// Lines are read as bytes so output that isn't valid UTF-8 is logged
+/// lossily instead of ending the read.
+fn log_output_lines<R: Read, F: Fn(&str)>(reader: R, log: F) -> std::io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+
+        let text = String::from_utf8_lossy(&line);
+        log(text.trim_end_matches(|c| c == '\n' || c == '\r'));
+    }
+}
+
+/// Run the Python interpreter from `python_paths` with arguments.
+///
+/// stdout is logged at info level and stderr at warn level as the process
+/// runs. Both are read to the end before the process is waited on. Returns
+/// the process exit status. Errors if the process could not be run.
+pub fn invoke_python(
+    python_paths: &PythonPaths,
+    logger: &slog::Logger,
+    args: &[&str],
+) -> Result<std::process::ExitStatus> {
     let site_packages_s = python_paths.site_packages.display().to_string();
 
     if site_packages_s.starts_with("\\\\?\\") {
-        panic!("Unexpected Windows UNC path in site-packages path");
+        return Err(anyhow!(
+            "unexpected Windows UNC path in site-packages path: {}",
+            site_packages_s
+        ));
     }
 
     info!(logger, "setting PYTHONPATH {}", site_packages_s);
@@ -303,1702 +663,6291 @@ pub fn invoke_python(python_paths: &PythonPaths, logger: &slog::Logger, args: &[
     let mut extra_envs = HashMap::new();
     extra_envs.insert("PYTHONPATH".to_string(), site_packages_s);
 
-    info!(
-        logger,
-        "running {} {}",
-        python_paths.python_exe.display(),
-        args.join(" ")
-    );
+    let command_line = format!("{} {}", python_paths.python_exe.display(), args.join(" "));
+
+    info!(logger, "running {}", command_line);
 
     let mut cmd = std::process::Command::new(&python_paths.python_exe)
         .args(args)
         .envs(&extra_envs)
         .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
         .spawn()
-        .unwrap_or_else(|_| {
-            panic!(
-                "failed to run {} {}",
-                python_paths.python_exe.display(),
-                args.join(" ")
-            )
-        });
-    {
-        let stdout = cmd.stdout.as_mut().unwrap();
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            warn!(logger, "{}", line.unwrap());
-        }
+        .with_context(|| format!("failed to run {}", command_line))?;
+
+    // Drain stderr on its own thread so neither pipe can fill up and block
+    // the process.
+    let stderr = cmd.stderr.take().unwrap();
+    let stderr_logger = logger.clone();
+    let stderr_thread = std::thread::spawn(move || {
+        log_output_lines(stderr, |line| warn!(stderr_logger, "{}", line))
+    });
+
+    let stdout_result =
+        log_output_lines(cmd.stdout.take().unwrap(), |line| info!(logger, "{}", line));
+    let stderr_result = stderr_thread
+        .join()
+        .map_err(|_| anyhow!("panic reading stderr of {}", command_line))?;
+
+    let status = cmd
+        .wait()
+        .with_context(|| format!("waiting on {}", command_line))?;
+
+    stdout_result.with_context(|| format!("reading stdout of {}", command_line))?;
+    stderr_result.with_context(|| format!("reading stderr of {}", command_line))?;
+
+    Ok(status)
+}
+
+/// Run the Python interpreter from `python_paths`, requiring it to succeed.
+///
+/// Like `invoke_python()` but errors if the process exits with a nonzero status.
+pub fn invoke_python_checked(
+    python_paths: &PythonPaths,
+    logger: &slog::Logger,
+    args: &[&str],
+) -> Result<()> {
+    let status = invoke_python(python_paths, logger, args)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} {} failed: {}",
+            python_paths.python_exe.display(),
+            args.join(" "),
+            status
+        ))
     }
 }
 
-/// Describes license information for a library.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct LicenseInfo {
-    /// SPDX license shortnames.
-    pub licenses: Vec<String>,
-    /// Suggested filename for the license.
-    pub license_filename: String,
-    /// Text of the license.
-    pub license_text: String,
+/// A regular file read from a tar archive whose write is deferred.
+struct PendingFile {
+    header: tar::Header,
+    path: PathBuf,
+    data: Vec<u8>,
 }
 
-/// Describes how libpython is linked in a standalone distribution.
-#[derive(Clone, Debug, PartialEq)]
-pub enum StandaloneDistributionLinkMode {
-    Static,
-    Dynamic,
+impl PendingFile {
+    /// Write the file beneath `dest` as `tar::Entry::unpack_in()` would.
+    ///
+    /// The file is re-encoded as a single member archive so path validation,
+    /// permissions, and modification times are handled by the `tar` crate
+    /// exactly as for entries extracted directly.
+    fn unpack_in(mut self, dest: &Path) -> Result<()> {
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_data(&mut self.header, &self.path, self.data.as_slice())?;
+        let data = builder.into_inner()?;
+
+        let mut archive = tar::Archive::new(std::io::Cursor::new(data));
+        for entry in archive.entries()? {
+            entry?
+                .unpack_in(dest)
+                .with_context(|| format!("unable to extract {}", self.path.display()))?;
+        }
+
+        Ok(())
+    }
 }
 
-/// Represents a standalone Python distribution.
+/// Run a function over items using up to `threads` threads.
 ///
-/// This is a Python distributed produced by the `python-build-standalone`
-/// project. It is derived from a tarball containing a `PYTHON.json` file
-/// describing the distribution.
-#[allow(unused)]
-#[derive(Clone, Debug)]
-pub struct StandaloneDistribution {
-    /// Directory where distribution lives in the filesystem.
-    pub base_dir: PathBuf,
+/// Items are distributed evenly across threads. The first error encountered
+/// is returned after all threads finish.
+fn run_parallel<T, F>(items: Vec<T>, threads: usize, f: F) -> Result<()>
+where
+    T: Send + 'static,
+    F: Fn(T) -> Result<()> + Send + Sync + 'static,
+{
+    let threads = threads.max(1).min(items.len().max(1));
+    let mut chunks = (0..threads).map(|_| Vec::new()).collect::<Vec<_>>();
+    for (i, item) in items.into_iter().enumerate() {
+        chunks[i % threads].push(item);
+    }
 
-    /// Rust target triple that this distribution runs on.
-    pub target_triple: String,
+    let f = Arc::new(f);
+    let handles = chunks
+        .into_iter()
+        .map(|chunk| {
+            let f = f.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for item in chunk {
+                    f(item)?;
+                }
 
-    /// PEP 425 Python tag value.
-    pub python_tag: String,
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut res = Ok(());
+    for handle in handles {
+        let thread_res = handle
+            .join()
+            .map_err(|_| anyhow!("extraction thread panicked"))
+            .and_then(|r| r);
+        if res.is_ok() {
+            res = thread_res;
+        }
+    }
 
-    /// PEP 425 Python ABI tag.
-    pub python_abi_tag: Option<String>,
+    res
+}
 
-    /// PEP 425 Python platform tag.
-    pub python_platform_tag: String,
+/// Wrap a zstd compressed stream in a decoder fed by a `zstd_buffer_size` buffer.
+fn zstd_decoder<R: Read>(
+    source: R,
+    options: &DistributionExtractOptions,
+) -> Result<zstd::stream::Decoder<BufReader<R>>> {
+    Ok(zstd::stream::Decoder::with_buffer(
+        BufReader::with_capacity(options.zstd_buffer_size, source),
+    )?)
+}
 
-    /// Python version string.
-    pub version: String,
+/// Extract a distribution tar stream into the absolute path `dest`.
+///
+/// With more than 1 extraction thread, regular files are read into memory
+/// and written by a pool of threads once the archive has been read. Other
+/// entries are extracted as they are read.
+fn extract_tar<R: Read>(
+    logger: &slog::Logger,
+    source: R,
+    dest: &Path,
+    options: &DistributionExtractOptions,
+) -> Result<()> {
+    let mut tf = tar::Archive::new(source);
+    let threads = options.extract_threads.max(1);
+
+    let mut symlinks = vec![];
+    let mut pending_files = vec![];
+    let mut progress = ExtractProgress::new(options);
+
+    for entry in tf.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        progress.record(logger, entry.header().size().unwrap_or(0));
+
+        // Windows doesn't support symlinks without special permissions.
+        // So we track symlinks explicitly and copy files post extract if
+        // running on that platform or if explicitly requested.
+        let link_name = entry.link_name().unwrap_or(None);
+        let entry_type = entry.header().entry_type();
+
+        if link_name.is_some() && (cfg!(target_family = "windows") || options.materialize_symlinks)
+        {
+            symlinks.push(resolve_archive_symlink(
+                dest,
+                &entry.path()?,
+                &link_name.unwrap(),
+            )?);
+        } else if threads > 1 && entry_type.is_file() {
+            let mut data = Vec::with_capacity(entry.header().size().unwrap_or(0) as usize);
+            entry
+                .read_to_end(&mut data)
+                .with_context(|| "unable to read tar member")?;
+
+            pending_files.push(PendingFile {
+                header: entry.header().clone(),
+                path: entry.path()?.to_path_buf(),
+                data,
+            });
+        } else {
+            // Hard links need their targets to exist.
+            if entry_type.is_hard_link() && !pending_files.is_empty() {
+                let dest = dest.to_path_buf();
+                run_parallel(std::mem::take(&mut pending_files), threads, move |file| {
+                    file.unpack_in(&dest)
+                })?;
+            }
 
-    /// Path to Python interpreter executable.
-    pub python_exe: PathBuf,
+            entry
+                .unpack_in(dest)
+                .with_context(|| "unable to extract tar member")?;
+        }
+    }
 
-    /// Path to Python standard library.
-    pub stdlib_path: PathBuf,
+    progress.finish(logger);
 
-    /// How libpython is linked in this distribution.
-    link_mode: StandaloneDistributionLinkMode,
+    if !pending_files.is_empty() {
+        let dest = dest.to_path_buf();
+        run_parallel(pending_files, threads, move |file| file.unpack_in(&dest))?;
+    }
 
-    /// Symbol visibility for Python symbols.
-    python_symbol_visibility: String,
+    finish_extraction(logger, dest, symlinks, options)
+}
 
-    /// Capabilities of distribution to load extension modules.
-    extension_module_loading: Vec<String>,
+/// Resolve a symlink archive member to be materialized as a copy.
+///
+/// `path` is the member's path and `link_name` its link target. Returns a
+/// tuple of the absolute paths of the file to copy and the file to write.
+fn resolve_archive_symlink(
+    dest: &Path,
+    path: &Path,
+    link_name: &Path,
+) -> Result<(PathBuf, PathBuf)> {
+    // The entry's path is the file to write, relative to the archive's
+    // root. We need to expand to an absolute path to facilitate copying.
+
+    // The link name is the file to symlink to, or the file we're copying.
+    // This path is relative to the entry path. So we need join with the
+    // entry's directory and canonicalize. There is also a security issue
+    // at play: archives could contain bogus symlinks pointing outside the
+    // archive. So we detect this, just in case.
+
+    let mut dest_path = dest.to_path_buf();
+    dest_path.extend(path.components());
+    let dest_path = dest_path
+        .parse_dot()
+        .with_context(|| "dedotting symlinked source")?;
+
+    let mut source = dest_path
+        .parent()
+        .ok_or_else(|| anyhow!("unable to resolve parent"))?
+        .to_path_buf();
+    source.extend(link_name.components());
+    let source = source
+        .parse_dot()
+        .with_context(|| "dedotting symlink destination")?;
+
+    if !source.starts_with(dest) {
+        return Err(anyhow!("malicious symlink detected in archive"));
+    }
 
-    /// SPDX license shortnames that apply to this distribution.
-    ///
-    /// Licenses only cover the core distribution. Licenses for libraries
-    /// required by extensions are stored next to the extension's linking
-    /// info.
-    pub licenses: Option<Vec<String>>,
+    Ok((source, dest_path))
+}
 
-    /// Path to file holding license text for this distribution.
-    pub license_path: Option<PathBuf>,
+/// Complete the extraction of an archive into `dest`.
+///
+/// `symlinks` are (source, dest) pairs of deferred symlinks to materialize
+/// as copies. Each conversion is logged, followed by a warning summarizing
+/// how many symlinks were converted.
+fn finish_extraction(
+    logger: &slog::Logger,
+    dest: &Path,
+    symlinks: Vec<(PathBuf, PathBuf)>,
+    options: &DistributionExtractOptions,
+) -> Result<()> {
+    let symlink_count = symlinks.len();
+
+    for (source, dest) in symlinks {
+        std::fs::copy(&source, &dest).with_context(|| {
+            format!(
+                "copying symlinked file {} -> {}",
+                source.display(),
+                dest.display(),
+            )
+        })?;
 
-    /// Path to Tcl library files.
-    pub tcl_library_path: Option<PathBuf>,
+        info!(
+            logger,
+            "converted symlink {} to a copy of {}",
+            dest.display(),
+            source.display()
+        );
+    }
 
-    /// Object files providing the core Python implementation.
-    ///
-    /// Keys are relative paths. Values are filesystem paths.
-    pub objs_core: BTreeMap<PathBuf, PathBuf>,
+    if symlink_count > 0 {
+        warn!(
+            logger,
+            "converted {} symlinks in distribution archive to file copies", symlink_count
+        );
+    }
 
-    /// Linking information for the core Python implementation.
-    pub links_core: Vec<LibraryDependency>,
+    if options.preserve_permissions {
+        return Ok(());
+    }
 
-    /// Filesystem location of pythonXY shared library for this distribution.
-    ///
-    /// Only set if `link_mode` is `StandaloneDistributionLinkMode::Dynamic`.
-    libpython_shared_library: Option<PathBuf>,
+    // Ensure unpacked files are writable. We've had issues where we
+    // consume archives with read-only file permissions. When we later
+    // copy these files, we can run into trouble overwriting a read-only
+    // file.
+    let mut paths = vec![];
+    for entry in walkdir::WalkDir::new(dest) {
+        paths.push(entry?.into_path());
+    }
 
-    /// Extension modules available to this distribution.
-    pub extension_modules: BTreeMap<String, PythonExtensionModuleVariants>,
+    run_parallel(paths, options.extract_threads.max(1), |path| {
+        let mut permissions = std::fs::metadata(&path)?.permissions();
 
-    pub frozen_c: Vec<u8>,
+        if permissions.readonly() {
+            permissions.set_readonly(false);
+            std::fs::set_permissions(&path, permissions)
+                .with_context(|| format!("unable to mark {} as writable", path.display()))?;
+        }
 
-    /// Include files for Python.
-    ///
-    /// Keys are relative paths. Values are filesystem paths.
-    pub includes: BTreeMap<String, PathBuf>,
+        Ok(())
+    })
+}
 
-    /// Static libraries available for linking.
-    ///
-    /// Keys are library names, without the "lib" prefix or file extension.
-    /// Values are filesystem paths where library is located.
-    pub libraries: BTreeMap<String, DataLocation>,
+/// Refuse to write `path` beneath `dest` if it would go through a symlink.
+///
+/// Every existing component of `path`, including the final one, is checked.
+/// Otherwise, an archive could write outside `dest` by first creating a
+/// symlink and then a member beneath it.
+fn ensure_no_symlink_components(dest: &Path, path: &Path) -> Result<()> {
+    let mut current = dest.to_path_buf();
 
-    pub py_modules: BTreeMap<String, PathBuf>,
+    for component in path.components() {
+        current.push(component);
 
-    /// Non-module Python resource files.
-    ///
-    /// Keys are package names. Values are maps of resource name to data for the resource
-    /// within that package.
-    pub resources: BTreeMap<String, BTreeMap<String, PathBuf>>,
+        match std::fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(anyhow!(
+                    "refusing to extract {} through symlink {}",
+                    path.display(),
+                    current.display()
+                ));
+            }
+            Ok(_) => {}
+            // Nothing exists beneath a missing path.
+            Err(_) => break,
+        }
+    }
 
-    /// Describes license info for things in this distribution.
-    pub license_infos: BTreeMap<String, Vec<LicenseInfo>>,
+    Ok(())
+}
 
-    /// Path to copy of hacked dist to use for packaging rules venvs
-    pub venv_base: PathBuf,
+/// A regular file read from a zip archive whose write is deferred.
+struct PendingZipFile {
+    path: PathBuf,
+    data: Vec<u8>,
+    mode: Option<u32>,
+}
 
-    /// Path to object file defining _PyImport_Inittab.
-    pub inittab_object: PathBuf,
+impl PendingZipFile {
+    /// Write the file beneath `dest`.
+    fn write_in(self, dest: &Path) -> Result<()> {
+        ensure_no_symlink_components(dest, &self.path)?;
 
-    /// Compiler flags to use to build object containing _PyImport_Inittab.
-    pub inittab_cflags: Vec<String>,
+        let dest_path = dest.join(&self.path);
 
-    /// Tag to apply to bytecode files.
-    ///
-    /// e.g. `cpython-37`.
-    pub cache_tag: String,
+        if let Some(parent) = dest_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating parent directory {}", parent.display()))?;
+        }
 
-    /// Suffixes for Python module types.
-    module_suffixes: PythonModuleSuffixes,
-}
+        std::fs::write(&dest_path, &self.data)
+            .with_context(|| format!("writing {}", dest_path.display()))?;
 
-impl StandaloneDistribution {
-    pub fn from_location(
-        logger: &slog::Logger,
-        location: &PythonDistributionLocation,
-        distributions_dir: &Path,
-    ) -> Result<Self> {
-        let (archive_path, extract_path) =
-            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if let Some(mode) = self.mode {
+                std::fs::set_permissions(&dest_path, std::fs::Permissions::from_mode(mode & 0o777))
+                    .with_context(|| format!("setting permissions of {}", dest_path.display()))?;
+            }
+        }
 
-        Self::from_tar_zst_file(logger, &archive_path, &extract_path)
+        Ok(())
     }
+}
 
-    /// Create an instance from a .tar.zst file.
-    ///
-    /// The distribution will be extracted to ``extract_dir`` if necessary.
-    pub fn from_tar_zst_file(
-        logger: &slog::Logger,
-        path: &Path,
-        extract_dir: &Path,
-    ) -> Result<Self> {
-        let basename = path
-            .file_name()
-            .ok_or_else(|| anyhow!("unable to determine filename"))?
-            .to_string_lossy();
+/// Extract a distribution zip archive into the absolute path `dest`.
+///
+/// Symlinks are represented as members having the symlink file type in their
+/// unix mode and the link target as content. They are handled the same way
+/// as symlinks in tar archives. Members are never written through symlinks.
+///
+/// With more than 1 extraction thread, regular files are read into memory
+/// and written by a pool of threads. Pending files are written before each
+/// symlink is created and once the archive has been read.
+fn extract_zip<R: Read + std::io::Seek>(
+    logger: &slog::Logger,
+    zf: &mut zip::ZipArchive<R>,
+    dest: &Path,
+    options: &DistributionExtractOptions,
+) -> Result<()> {
+    let threads = options.extract_threads.max(1);
+
+    let mut symlinks = vec![];
+    let mut pending_files = vec![];
+    let mut progress = ExtractProgress::new(options);
+    // The central directory knows how many entries there are.
+    progress.expected_entries = progress.expected_entries.or_else(|| Some(zf.len()));
+
+    let write_pending = |pending_files: Vec<PendingZipFile>| {
+        let dest = dest.to_path_buf();
+        run_parallel(pending_files, threads, move |file| file.write_in(&dest))
+    };
 
-        if !basename.ends_with(".tar.zst") {
-            return Err(anyhow!("unhandled distribution format: {}", path.display()));
+    for i in 0..zf.len() {
+        let mut f = zf.by_index(i)?;
+
+        progress.record(logger, f.size());
+
+        let path = f.sanitized_name();
+        let dest_path = dest.join(&path);
+        let mode = f.unix_mode();
+
+        if f.is_dir() {
+            ensure_no_symlink_components(dest, &path)?;
+            std::fs::create_dir_all(&dest_path)
+                .with_context(|| format!("creating directory {}", dest_path.display()))?;
+            continue;
         }
 
-        let fh = std::fs::File::open(path)
-            .with_context(|| format!("unable to open {}", path.display()))?;
+        let mut data = Vec::with_capacity(f.size() as usize);
+        f.read_to_end(&mut data)
+            .with_context(|| format!("reading zip member {}", path.display()))?;
 
-        let reader = BufReader::new(fh);
-        warn!(logger, "reading data from Python distribution...");
+        if mode
+            .map(|mode| mode & 0o170000 == 0o120000)
+            .unwrap_or(false)
+        {
+            let link_name = PathBuf::from(
+                String::from_utf8(data)
+                    .with_context(|| format!("decoding symlink target of {}", path.display()))?,
+            );
 
-        Self::from_tar_zst(reader, &extract_dir)
-    }
+            // Validated even when creating a real symlink so no symlink
+            // can point outside the archive.
+            let symlink = resolve_archive_symlink(dest, &path, &link_name)?;
 
-    /// Extract and analyze a standalone distribution from a zstd compressed tar stream.
-    pub fn from_tar_zst<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
-        let dctx = zstd::stream::Decoder::new(source)?;
+            if cfg!(target_family = "windows") || options.materialize_symlinks {
+                symlinks.push(symlink);
+            } else {
+                write_pending(std::mem::take(&mut pending_files))?;
+
+                ensure_no_symlink_components(dest, &path)?;
+                if let Some(parent) = dest_path.parent() {
+                    std::fs::create_dir_all(parent).with_context(|| {
+                        format!("creating parent directory {}", parent.display())
+                    })?;
+                }
+                create_symlink(&link_name, &dest_path)?;
+            }
+
+            continue;
+        }
 
-        Self::from_tar(dctx, extract_dir)
+        let file = PendingZipFile { path, data, mode };
+
+        if threads > 1 {
+            pending_files.push(file);
+        } else {
+            file.write_in(dest)?;
+        }
     }
 
-    /// Extract and analyze a standalone distribution from a tar stream.
-    pub fn from_tar<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
-        let mut tf = tar::Archive::new(source);
+    progress.finish(logger);
 
-        {
-            let _lock = DistributionExtractLock::new(extract_dir)?;
+    write_pending(pending_files)?;
 
-            // The content of the distribution could change between runs. But caching
-            // the extraction does keep things fast.
-            let test_path = extract_dir.join("python").join("PYTHON.json");
-            if !test_path.exists() {
-                std::fs::create_dir_all(extract_dir)?;
-                let absolute_path = std::fs::canonicalize(extract_dir)?;
-
-                let mut symlinks = vec![];
-
-                for entry in tf.entries()? {
-                    let mut entry =
-                        entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
-
-                    // Windows doesn't support symlinks without special permissions.
-                    // So we track symlinks explicitly and copy files post extract if
-                    // running on that platform.
-                    let link_name = entry.link_name().unwrap_or(None);
-
-                    if link_name.is_some() && cfg!(target_family = "windows") {
-                        // The entry's path is the file to write, relative to the archive's
-                        // root. We need to expand to an absolute path to facilitate copying.
-
-                        // The link name is the file to symlink to, or the file we're copying.
-                        // This path is relative to the entry path. So we need join with the
-                        // entry's directory and canonicalize. There is also a security issue
-                        // at play: archives could contain bogus symlinks pointing outside the
-                        // archive. So we detect this, just in case.
-
-                        let mut dest = absolute_path.clone();
-                        dest.extend(entry.path()?.components());
-                        let dest = dest
-                            .parse_dot()
-                            .with_context(|| "dedotting symlinked source")?;
-
-                        let mut source = dest
-                            .parent()
-                            .ok_or_else(|| anyhow!("unable to resolve parent"))?
-                            .to_path_buf();
-                        source.extend(link_name.unwrap().components());
-                        let source = source
-                            .parse_dot()
-                            .with_context(|| "dedotting symlink destination")?;
-
-                        if !source.starts_with(&absolute_path) {
-                            return Err(anyhow!("malicious symlink detected in archive"));
-                        }
+    finish_extraction(logger, dest, symlinks, options)
+}
 
-                        symlinks.push((source, dest));
-                    } else {
-                        entry
-                            .unpack_in(&absolute_path)
-                            .with_context(|| "unable to extract tar member")?;
-                    }
-                }
+/// Create a symlink at `path` pointing to `target`.
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, path)
+        .with_context(|| format!("creating symlink {}", path.display()))
+}
 
-                for (source, dest) in symlinks {
-                    std::fs::copy(&source, &dest).with_context(|| {
-                        format!(
-                            "copying symlinked file {} -> {}",
-                            source.display(),
-                            dest.display(),
-                        )
-                    })?;
-                }
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, path: &Path) -> Result<()> {
+    Err(anyhow!(
+        "symlinks not supported on this platform: {}",
+        path.display()
+    ))
+}
 
-                // Ensure unpacked files are writable. We've had issues where we
-                // consume archives with read-only file permissions. When we later
-                // copy these files, we can run into trouble overwriting a read-only
-                // file.
-                let walk = walkdir::WalkDir::new(&absolute_path);
-                for entry in walk.into_iter() {
-                    let entry = entry?;
-
-                    let metadata = entry.metadata()?;
-                    let mut permissions = metadata.permissions();
-
-                    if permissions.readonly() {
-                        permissions.set_readonly(false);
-                        std::fs::set_permissions(entry.path(), permissions).with_context(|| {
-                            format!("unable to mark {} as writable", entry.path().display())
-                        })?;
-                    }
+/// Compute the hex SHA-256 digest of a file.
+///
+/// The file is streamed through a buffer of `buffer_size` bytes.
+fn file_sha256(path: &Path, buffer_size: usize) -> Result<String> {
+    let fh =
+        std::fs::File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+    let mut reader = BufReader::with_capacity(buffer_size, fh);
+    let mut hasher = Sha256::new();
+
+    loop {
+        let buffer = reader
+            .fill_buf()
+            .with_context(|| format!("reading {}", path.display()))?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        hasher.input(buffer);
+        let count = buffer.len();
+        reader.consume(count);
+    }
+
+    Ok(hex::encode(hasher.result()))
+}
+
+/// Resolve the hex SHA-256 digest of an archive file being extracted.
+///
+/// The file is read once. If `options.expected_sha256` is set, the digest
+/// must match it.
+fn archive_file_sha256(path: &Path, options: &DistributionExtractOptions) -> Result<String> {
+    let actual = file_sha256(path, options.archive_buffer_size)?;
+
+    match &options.expected_sha256 {
+        Some(expected) if actual != expected.to_lowercase() => Err(anyhow!(
+            "sha256 of {} is {} but expected {}; the file may be truncated or corrupt",
+            path.display(),
+            actual,
+            expected
+        )),
+        _ => Ok(actual),
+    }
+}
+
+/// Name of the file recording which archive an extract directory came from.
+const EXTRACTION_MARKER: &str = ".extraction-marker";
+
+/// Extract an archive into `extract_dir` unless it already holds the archive.
+///
+/// An existing extraction is reused if its marker records `archive_sha256`.
+/// If `archive_sha256` is `None`, there is no way to tell which archive an
+/// existing extraction came from, so any existing extraction is reused.
+/// Otherwise, any previous extraction is removed before `extract` is called
+/// with the absolute path of `extract_dir`. Returns whether `extract` was
+/// called.
+fn extract_archive_cached<F>(
+    extract_dir: &Path,
+    archive_sha256: Option<&str>,
+    extract: F,
+) -> Result<bool>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let marker_path = extract_dir.join(EXTRACTION_MARKER);
+    let python_dir = extract_dir.join("python");
+
+    if python_dir.join("PYTHON.json").exists() {
+        match archive_sha256 {
+            None => return Ok(false),
+            Some(digest) => {
+                if std::fs::read_to_string(&marker_path).ok().as_deref() == Some(digest) {
+                    return Ok(false);
                 }
             }
         }
+    }
 
-        Self::from_directory(extract_dir)
+    // Don't let files from a previous archive linger.
+    if marker_path.exists() {
+        std::fs::remove_file(&marker_path)
+            .with_context(|| format!("removing {}", marker_path.display()))?;
+    }
+    if python_dir.exists() {
+        std::fs::remove_dir_all(&python_dir)
+            .with_context(|| format!("removing stale extraction {}", python_dir.display()))?;
     }
 
-    /// Obtain an instance by scanning a directory containing an extracted distribution.
-    #[allow(clippy::cognitive_complexity)]
-    pub fn from_directory(dist_dir: &Path) -> Result<Self> {
-        let mut objs_core: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
-        let mut links_core: Vec<LibraryDependency> = Vec::new();
-        let mut extension_modules: BTreeMap<String, PythonExtensionModuleVariants> =
-            BTreeMap::new();
-        let mut includes: BTreeMap<String, PathBuf> = BTreeMap::new();
-        let mut libraries: BTreeMap<String, DataLocation> = BTreeMap::new();
-        let frozen_c: Vec<u8> = Vec::new();
-        let mut py_modules: BTreeMap<String, PathBuf> = BTreeMap::new();
-        let mut resources: BTreeMap<String, BTreeMap<String, PathBuf>> = BTreeMap::new();
-        let mut license_infos: BTreeMap<String, Vec<LicenseInfo>> = BTreeMap::new();
+    std::fs::create_dir_all(extract_dir)?;
+    let absolute_path = std::fs::canonicalize(extract_dir)?;
 
-        for entry in std::fs::read_dir(dist_dir)? {
-            let entry = entry?;
+    extract(&absolute_path)?;
 
-            match entry.file_name().to_str() {
-                Some("python") => continue,
-                Some(value) => {
-                    return Err(anyhow!(
-                        "unexpected entry in distribution root directory: {}",
-                        value
-                    ))
-                }
-                _ => {
-                    return Err(anyhow!(
-                        "error listing root directory of Python distribution"
-                    ))
-                }
-            };
+    // Write the marker atomically so an interrupted write can't be mistaken
+    // for a complete extraction.
+    if let Some(digest) = archive_sha256 {
+        let temp_path = extract_dir.join(format!("{}.tmp", EXTRACTION_MARKER));
+        std::fs::write(&temp_path, digest)
+            .with_context(|| format!("writing {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &marker_path)
+            .with_context(|| format!("renaming {}", temp_path.display()))?;
+    }
+
+    Ok(true)
+}
+
+/// Compute SHA-256 digests of every file under a directory.
+///
+/// Keys are `/` separated paths relative to `root`. Values are hex digests.
+/// Symlinks are not followed.
+fn file_tree_digests(root: &Path) -> Result<BTreeMap<String, String>> {
+    let mut digests = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(root) {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
         }
 
-        let python_path = dist_dir.join("python");
+        let rel_path = entry
+            .path()
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
 
-        for entry in std::fs::read_dir(&python_path)? {
-            let entry = entry?;
+        let data = std::fs::read(entry.path())
+            .with_context(|| format!("reading {}", entry.path().display()))?;
 
-            match entry.file_name().to_str() {
-                Some("build") => continue,
-                Some("install") => continue,
-                Some("lib") => continue,
-                Some("licenses") => continue,
-                Some("LICENSE.rst") => continue,
-                Some("PYTHON.json") => continue,
-                Some(value) => {
-                    return Err(anyhow!("unexpected entry in python/ directory: {}", value))
+        digests.insert(rel_path, hex::encode(Sha256::digest(&data)));
+    }
+
+    Ok(digests)
+}
+
+/// Parse a manifest produced by `format_manifest()`.
+fn parse_manifest(data: &str) -> Result<BTreeMap<String, String>> {
+    let mut digests = BTreeMap::new();
+
+    for (i, line) in data.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, "  ");
+        match (parts.next(), parts.next()) {
+            (Some(digest), Some(path)) if digest.len() == 64 && !path.is_empty() => {
+                if digests
+                    .insert(path.to_string(), digest.to_lowercase())
+                    .is_some()
+                {
+                    return Err(anyhow!("line {}: duplicate path {}", i + 1, path));
                 }
-                _ => return Err(anyhow!("error listing python/ directory")),
-            };
+            }
+            _ => return Err(anyhow!("line {}: malformed manifest entry", i + 1)),
         }
+    }
 
-        let pi = parse_python_json_from_distribution(dist_dir)?;
+    Ok(digests)
+}
 
-        if let Some(ref python_license_path) = pi.license_path {
-            let license_path = python_path.join(python_license_path);
-            let license_text = std::fs::read_to_string(&license_path).with_context(|| {
-                format!("unable to read Python license {}", license_path.display())
-            })?;
+/// Format file digests as a manifest.
+///
+/// Each line holds a hex SHA-256 digest, two spaces, and a path, sorted by
+/// path. This is the format of `sha256sum`.
+fn format_manifest(digests: &BTreeMap<String, String>) -> String {
+    digests
+        .iter()
+        .map(|(path, digest)| format!("{}  {}\n", digest, path))
+        .collect()
+}
 
-            let mut licenses = Vec::new();
-            licenses.push(LicenseInfo {
-                licenses: pi.licenses.clone().unwrap(),
-                license_filename: "LICENSE.python.txt".to_string(),
-                license_text,
-            });
+/// Describe how actual file digests differ from expected ones.
+fn manifest_differences(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    for (path, digest) in expected {
+        match actual.get(path) {
+            Some(actual_digest) if actual_digest == digest => {}
+            Some(_) => differences.push(format!("modified: {}", path)),
+            None => differences.push(format!("removed: {}", path)),
+        }
+    }
 
-            license_infos.insert("python".to_string(), licenses);
+    for path in actual.keys() {
+        if !expected.contains_key(path) {
+            differences.push(format!("added: {}", path));
         }
+    }
 
-        // Collect object files for libpython.
-        for obj in &pi.build_info.core.objs {
-            let rel_path = PathBuf::from(obj);
-            let full_path = python_path.join(obj);
+    differences
+}
 
-            objs_core.insert(rel_path, full_path);
-        }
+/// Describes license information for a library.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LicenseInfo {
+    /// SPDX license shortnames.
+    pub licenses: Vec<String>,
+    /// Suggested filename for the license.
+    pub license_filename: String,
+    /// Text of the license.
+    pub license_text: String,
+}
 
-        for entry in &pi.build_info.core.links {
-            let depends = entry.to_library_dependency(&python_path);
+/// Callback receiving the number of archive entries extracted so far and the total, if known.
+#[derive(Clone)]
+pub struct ExtractProgressCallback(pub Arc<dyn Fn(usize, Option<usize>) + Send + Sync>);
 
-            if let Some(p) = &depends.static_library {
-                libraries.insert(depends.name.clone(), p.clone());
-            }
+impl std::fmt::Debug for ExtractProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ExtractProgressCallback")
+    }
+}
 
-            links_core.push(depends);
-        }
+/// Options influencing how a distribution archive is extracted.
+#[derive(Clone, Debug)]
+pub struct DistributionExtractOptions {
+    /// Size in bytes of the buffer used to read the archive file.
+    pub archive_buffer_size: usize,
 
-        let module_suffixes = PythonModuleSuffixes {
-            source: pi
-                .python_suffixes
-                .get("source")
-                .ok_or_else(|| anyhow!("distribution does not define source suffixes"))?
-                .clone(),
-            bytecode: pi
-                .python_suffixes
-                .get("bytecode")
-                .ok_or_else(|| anyhow!("distribution does not define bytecode suffixes"))?
-                .clone(),
-            debug_bytecode: pi
-                .python_suffixes
-                .get("debug_bytecode")
-                .ok_or_else(|| anyhow!("distribution does not define debug bytecode suffixes"))?
-                .clone(),
-            optimized_bytecode: pi
-                .python_suffixes
-                .get("optimized_bytecode")
-                .ok_or_else(|| anyhow!("distribution does not define optimized bytecode suffixes"))?
-                .clone(),
-            extension: pi
-                .python_suffixes
-                .get("extension")
-                .ok_or_else(|| anyhow!("distribution does not define extension suffixes"))?
-                .clone(),
-        };
+    /// Size in bytes of the buffer feeding the zstd decoder.
+    pub zstd_buffer_size: usize,
 
-        // Collect extension modules.
-        for (module, variants) in &pi.build_info.extensions {
-            let mut ems = PythonExtensionModuleVariants::default();
+    /// Whether to copy the targets of symlinks instead of creating links.
+    ///
+    /// Symlinks are always materialized as copies on Windows. Setting this
+    /// does the same on other platforms.
+    pub materialize_symlinks: bool,
 
-            for entry in variants.iter() {
-                let object_file_data = entry
-                    .objs
-                    .iter()
-                    .map(|p| DataLocation::Path(python_path.join(p)))
-                    .collect();
-                let mut links = Vec::new();
+    /// Report extraction progress every time this many bytes are extracted.
+    ///
+    /// `None` disables progress reporting.
+    pub progress_interval: Option<u64>,
 
-                for link in &entry.links {
-                    let depends = link.to_library_dependency(&python_path);
+    /// Total size in bytes of the archive's members, if known.
+    ///
+    /// Used to express progress as a percentage.
+    pub expected_size: Option<u64>,
 
-                    if let Some(p) = &depends.static_library {
-                        libraries.insert(depends.name.clone(), p.clone());
-                    }
+    /// Called as each archive entry is extracted.
+    pub progress_callback: Option<ExtractProgressCallback>,
 
-                    links.push(depends);
-                }
+    /// Number of entries in the archive, if known.
+    ///
+    /// Passed to `progress_callback`. Zip archives record their number of
+    /// entries, so it is filled in automatically for them.
+    pub expected_entries: Option<usize>,
 
-                if let Some(ref license_paths) = entry.license_paths {
-                    let mut licenses = Vec::new();
+    /// Number of threads used to write extracted files.
+    ///
+    /// With more than 1 thread, regular files are buffered in memory until the
+    /// whole archive has been read.
+    pub extract_threads: usize,
 
-                    for license_path in license_paths {
-                        let license_path = python_path.join(license_path);
-                        let license_text = std::fs::read_to_string(&license_path)
-                            .with_context(|| "unable to read license file")?;
+    /// Hex SHA-256 digest the archive file must have.
+    ///
+    /// Only checked when extracting from a file. The file is verified before
+    /// anything is decompressed.
+    pub expected_sha256: Option<String>,
 
-                        licenses.push(LicenseInfo {
-                            licenses: entry.licenses.clone().unwrap(),
-                            license_filename: license_path
-                                .file_name()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_string(),
-                            license_text,
-                        });
-                    }
+    /// Hex SHA-256 digest of the archive being extracted.
+    ///
+    /// Recorded in the extract directory and used to determine whether an
+    /// existing extraction came from the same archive. If `None`, the archive
+    /// is always extracted. This is set automatically when extracting from
+    /// a file.
+    pub archive_sha256: Option<String>,
 
-                    license_infos.insert(module.clone(), licenses);
-                }
+    /// Whether to keep the permissions of extracted files as they are.
+    ///
+    /// By default, read-only files are made writable after extraction so
+    /// copies of them can later be overwritten.
+    pub preserve_permissions: bool,
+}
 
-                ems.push(PythonExtensionModule {
-                    name: module.clone(),
-                    init_fn: Some(entry.init_fn.clone()),
-                    extension_file_suffix: "".to_string(),
-                    shared_library: if let Some(path) = &entry.shared_lib {
-                        Some(DataLocation::Path(python_path.join(path)))
-                    } else {
-                        None
-                    },
-                    object_file_data,
-                    is_package: false,
-                    link_libraries: links,
-                    is_stdlib: true,
-                    builtin_default: entry.in_core,
-                    required: entry.required,
-                    variant: Some(entry.variant.clone()),
-                    licenses: entry.licenses.clone(),
-                    license_texts: if let Some(licenses) = &entry.license_paths {
-                        Some(
-                            licenses
-                                .iter()
-                                .map(|p| DataLocation::Path(python_path.join(p)))
-                                .collect(),
-                        )
-                    } else {
-                        None
-                    },
-                    license_public_domain: entry.license_public_domain,
-                });
-            }
+impl Default for DistributionExtractOptions {
+    fn default() -> Self {
+        Self {
+            // Same as BufReader's default.
+            archive_buffer_size: 8 * 1024,
+            // Roughly the input size recommended by zstd for streaming decompression.
+            zstd_buffer_size: 128 * 1024,
+            materialize_symlinks: false,
+            progress_interval: None,
+            expected_size: None,
+            progress_callback: None,
+            expected_entries: None,
+            extract_threads: num_cpus::get(),
+            expected_sha256: None,
+            archive_sha256: None,
+            preserve_permissions: false,
+        }
+    }
+}
+
+/// Tracks and periodically reports progress of a distribution extraction.
+struct ExtractProgress {
+    interval: Option<u64>,
+    expected_size: Option<u64>,
+    callback: Option<ExtractProgressCallback>,
+    expected_entries: Option<usize>,
+    entries: u64,
+    bytes: u64,
+    next_report: u64,
+}
 
-            extension_modules.insert(module.clone(), ems);
+impl ExtractProgress {
+    fn new(options: &DistributionExtractOptions) -> Self {
+        Self {
+            interval: options.progress_interval,
+            expected_size: options.expected_size,
+            callback: options.progress_callback.clone(),
+            expected_entries: options.expected_entries,
+            entries: 0,
+            bytes: 0,
+            next_report: options.progress_interval.unwrap_or(0),
         }
+    }
 
-        let include_path = if let Some(p) = pi.python_paths.get("include") {
-            python_path.join(p)
-        } else {
-            return Err(anyhow!("include path not defined in distribution"));
-        };
+    fn describe(&self) -> String {
+        match self.expected_size {
+            Some(total) if total > 0 => format!(
+                "{} entries, {} of {} bytes ({}%)",
+                self.entries,
+                self.bytes,
+                total,
+                std::cmp::min(self.bytes * 100 / total, 100)
+            ),
+            _ => format!("{} entries, {} bytes", self.entries, self.bytes),
+        }
+    }
 
-        for entry in walk_tree_files(&include_path) {
-            let full_path = entry.path();
-            let rel_path = full_path
-                .strip_prefix(&include_path)
-                .expect("unable to strip prefix");
-            includes.insert(
-                String::from(rel_path.to_str().expect("path to string")),
-                full_path.to_path_buf(),
-            );
+    /// Record an extracted entry of `size` bytes, logging if an interval elapsed.
+    fn record(&mut self, logger: &slog::Logger, size: u64) {
+        self.entries += 1;
+        self.bytes += size;
+
+        if let Some(callback) = &self.callback {
+            (callback.0)(self.entries as usize, self.expected_entries);
         }
 
-        let stdlib_path = if let Some(p) = pi.python_paths.get("stdlib") {
-            python_path.join(p)
-        } else {
-            return Err(anyhow!("stdlib path not defined in distribution"));
-        };
+        if let Some(interval) = self.interval {
+            if self.bytes >= self.next_report {
+                warn!(logger, "extracting distribution: {}", self.describe());
 
-        for entry in find_python_resources(
-            &stdlib_path,
-            &pi.python_implementation_cache_tag,
-            &module_suffixes,
-        ) {
-            match entry? {
-                PythonResource::Resource(resource) => {
-                    if !resources.contains_key(&resource.leaf_package) {
-                        resources.insert(resource.leaf_package.clone(), BTreeMap::new());
-                    }
-
-                    resources.get_mut(&resource.leaf_package).unwrap().insert(
-                        resource.relative_name.clone(),
-                        match resource.data {
-                            DataLocation::Path(path) => path,
-                            DataLocation::Memory(_) => {
-                                return Err(anyhow!(
-                                    "should not have received in-memory resource data"
-                                ))
-                            }
-                        },
-                    );
+                // Skip over intervals consumed by a single large entry.
+                while self.next_report <= self.bytes {
+                    self.next_report += std::cmp::max(interval, 1);
                 }
-                PythonResource::ModuleSource(source) => match source.source {
-                    DataLocation::Path(path) => {
-                        py_modules.insert(source.name.clone(), path);
-                    }
-                    DataLocation::Memory(_) => {
-                        return Err(anyhow!("should not have received in-memory source data"))
-                    }
-                },
-                _ => {}
-            };
+            }
         }
-
-        let venv_base = dist_dir.parent().unwrap().join("hacked_base");
-
-        let (link_mode, libpython_shared_library) = if pi.libpython_link_mode == "static" {
-            (StandaloneDistributionLinkMode::Static, None)
-        } else if pi.libpython_link_mode == "shared" {
-            (
-                StandaloneDistributionLinkMode::Dynamic,
-                Some(python_path.join(pi.build_info.core.shared_lib.unwrap())),
-            )
-        } else {
-            return Err(anyhow!("unhandled link mode: {}", pi.libpython_link_mode));
-        };
-
-        let inittab_object = python_path.join(pi.build_info.inittab_object);
-
-        Ok(Self {
-            base_dir: dist_dir.to_path_buf(),
-            target_triple: pi.target_triple,
-            python_tag: pi.python_tag,
-            python_abi_tag: pi.python_abi_tag,
-            python_platform_tag: pi.python_platform_tag,
-            version: pi.python_version.clone(),
-            python_exe: python_exe_path(dist_dir)?,
-            stdlib_path,
-            link_mode,
-            python_symbol_visibility: pi.python_symbol_visibility,
-            extension_module_loading: pi.python_extension_module_loading,
-            licenses: pi.licenses.clone(),
-            license_path: match pi.license_path {
-                Some(ref path) => Some(PathBuf::from(path)),
-                None => None,
-            },
-            tcl_library_path: match pi.tcl_library_path {
-                Some(ref path) => Some(PathBuf::from(path)),
-                None => None,
-            },
-
-            extension_modules,
-            frozen_c,
-            includes,
-            links_core,
-            libraries,
-            objs_core,
-            libpython_shared_library,
-            py_modules,
-            resources,
-            license_infos,
-            venv_base,
-            inittab_object,
-            inittab_cflags: pi.build_info.inittab_cflags,
-            cache_tag: pi.python_implementation_cache_tag,
-            module_suffixes,
-        })
     }
 
-    /// Duplicate the python distribution, with distutils hacked
-    #[allow(unused)]
-    pub fn create_hacked_base(&self, logger: &slog::Logger) -> PythonPaths {
-        let venv_base = self.venv_base.clone();
-
-        let venv_dir_s = self.venv_base.display().to_string();
-
-        if !venv_base.exists() {
-            let dist_prefix = self.base_dir.join("python").join("install");
-
-            copy_dir(&dist_prefix, &venv_base).unwrap();
-
-            let dist_prefix_s = dist_prefix.display().to_string();
+    fn finish(&self, logger: &slog::Logger) {
+        if self.interval.is_some() {
             warn!(
                 logger,
-                "copied {} to create hacked base {}", dist_prefix_s, venv_dir_s
+                "extracted distribution: {} entries, {} bytes", self.entries, self.bytes
             );
         }
+    }
+}
 
-        let python_paths = resolve_python_paths(&venv_base, &self.version);
+/// Describes how libpython is linked in a standalone distribution.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StandaloneDistributionLinkMode {
+    Static,
+    Dynamic,
+}
 
-        invoke_python(&python_paths, &logger, &["-m", "ensurepip"]);
+/// Describes how an extension module in a distribution can be loaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtensionLoadingCapability {
+    /// The extension module is unknown or cannot be loaded.
+    Unavailable,
+    /// The extension module can only be linked into libpython as a builtin.
+    BuiltinOnly,
+    /// The extension module can only be loaded from a shared library.
+    SharedLibraryOnly,
+    /// The extension module can be a builtin or loaded from a shared library.
+    BuiltinOrSharedLibrary,
+}
 
-        prepare_hacked_distutils(logger, &self.stdlib_path.join("distutils"), &venv_base, &[])
-            .unwrap();
+/// How much of a distribution directory to analyze.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DistributionScan {
+    /// Everything needed to build binaries.
+    Full,
+    /// Python resources, bytecode, and licenses.
+    Resources,
+    /// Only standard library source modules and resources.
+    Stdlib,
+}
 
-        python_paths
+/// Number of attempts made to copy the distribution into the hacked base.
+const HACKED_BASE_COPY_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry of a failed hacked base copy.
+///
+/// The delay doubles after each failed attempt.
+const HACKED_BASE_COPY_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Run a fallible operation, retrying with exponential backoff on failure.
+///
+/// This is used for filesystem operations which can fail transiently. e.g.
+/// on Windows, antivirus scanners can hold handles to files being copied,
+/// leading to sharing violations. The error from the final attempt is
+/// returned if every attempt fails.
+fn retry_with_backoff<T, F>(
+    logger: &slog::Logger,
+    attempts: u32,
+    initial_delay: std::time::Duration,
+    mut f: F,
+) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut delay = initial_delay;
+
+    for attempt in 1..=attempts {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts => {
+                warn!(
+                    logger,
+                    "attempt {} of {} failed: {}; retrying in {}ms",
+                    attempt,
+                    attempts,
+                    e,
+                    delay.as_millis()
+                );
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(e) => return Err(e),
+        }
     }
 
-    /// Create a venv from the distribution at path.
-    #[allow(unused)]
-    pub fn create_venv(&self, logger: &slog::Logger, path: &Path) -> PythonPaths {
-        let venv_dir_s = path.display().to_string();
+    Err(anyhow!("operation not attempted"))
+}
 
-        // This will recreate it, if it was deleted
-        let python_paths = self.create_hacked_base(&logger);
+/// Name of the file marking a hacked base directory as completely copied.
+const HACKED_BASE_COMPLETE_MARKER: &str = ".hacked-base-complete";
 
-        if path.exists() {
-            warn!(logger, "re-using {} {}", "venv", venv_dir_s);
-        } else {
-            warn!(logger, "creating {} {}", "venv", venv_dir_s);
-            invoke_python(&python_paths, &logger, &["-m", "venv", venv_dir_s.as_str()]);
-        }
+/// Removes the hacked base directory of a distribution when dropped.
+///
+/// Obtained from `StandaloneDistribution::hacked_base_cleanup_guard()`. The
+/// guard holds a shared lock on the hacked base for its lifetime. When it is
+/// dropped, the directory is only removed if no other guard, in this or any
+/// other process, still holds the lock. Removal is serialized with
+/// `create_hacked_base()` through the distribution extraction lock. Builds
+/// that want to retain the hacked base across invocations should not create
+/// one.
+pub struct HackedBaseCleanupGuard<'a> {
+    distribution: &'a StandaloneDistribution,
+    usage_lock: std::fs::File,
+}
 
-        resolve_python_paths(&path, &self.version)
+impl<'a> Drop for HackedBaseCleanupGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.usage_lock.unlock();
+
+        // Errors can't be propagated from drop(). If removal fails part way,
+        // the leftover directory lacks its completion marker, so the next
+        // `create_hacked_base()` removes and recreates it.
+        if let Ok(_lock) = DistributionExtractLock::new(&self.distribution.venv_base) {
+            if self.usage_lock.try_lock_exclusive().is_ok() {
+                let _ = self.distribution.remove_hacked_base();
+                let _ = self.usage_lock.unlock();
+            }
+        }
     }
+}
 
-    /// Create or re-use an existing venv
-    #[allow(unused)]
-    pub fn prepare_venv(
-        &self,
-        logger: &slog::Logger,
-        venv_dir_path: &Path,
-    ) -> Result<(PythonPaths, HashMap<String, String>)> {
-        let python_paths = self.create_venv(logger, &venv_dir_path);
+/// Represents a standalone Python distribution.
+///
+/// This is a Python distributed produced by the `python-build-standalone`
+/// project. It is derived from a tarball containing a `PYTHON.json` file
+/// describing the distribution.
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct StandaloneDistribution {
+    /// Directory where distribution lives in the filesystem.
+    pub base_dir: PathBuf,
 
-        let mut extra_envs = HashMap::new();
+    /// Rust target triple that this distribution runs on.
+    pub target_triple: String,
 
-        let prefix_s = python_paths.prefix.display().to_string();
+    /// Features of the C runtime the distribution was built against.
+    pub crt_features: Vec<String>,
 
-        let venv_path_bin_s = python_paths.bin_dir.display().to_string();
+    /// PEP 425 Python tag value.
+    pub python_tag: String,
 
-        let path_separator = if cfg!(windows) { ";" } else { ":" };
+    /// PEP 425 Python ABI tag.
+    pub python_abi_tag: Option<String>,
 
-        if let Ok(path) = std::env::var("PATH") {
-            extra_envs.insert(
-                "PATH".to_string(),
-                format!("{}{}{}", venv_path_bin_s, path_separator, path),
-            );
-        } else {
-            extra_envs.insert("PATH".to_string(), venv_path_bin_s);
-        }
+    /// PEP 425 Python platform tag.
+    pub python_platform_tag: String,
 
-        let site_packages_s = python_paths.site_packages.display().to_string();
-        if site_packages_s.starts_with("\\\\?\\") {
-            panic!("unexpected Windows UNC path in site-packages");
-        }
+    /// Python version string.
+    pub version: String,
 
-        extra_envs.insert("VIRTUAL_ENV".to_string(), prefix_s);
-        extra_envs.insert("PYTHONPATH".to_string(), site_packages_s);
+    /// Path to Python interpreter executable.
+    pub python_exe: PathBuf,
 
-        extra_envs.insert("PYOXIDIZER".to_string(), "1".to_string());
+    /// Path to Python standard library.
+    pub stdlib_path: PathBuf,
 
-        Ok((python_paths, extra_envs))
-    }
+    /// How libpython is linked in this distribution.
+    link_mode: StandaloneDistributionLinkMode,
 
-    /// Whether the distribution is capable of loading filed-based Python extension modules.
-    pub fn is_extension_module_file_loadable(&self) -> bool {
-        self.extension_module_loading
-            .contains(&"shared-library".to_string())
-    }
-}
+    /// Symbol visibility for Python symbols.
+    python_symbol_visibility: String,
 
-impl PythonDistribution for StandaloneDistribution {
-    fn clone_box(&self) -> Box<dyn PythonDistribution> {
-        Box::new(self.clone())
-    }
+    /// Capabilities of distribution to load extension modules.
+    extension_module_loading: Vec<String>,
 
-    fn python_exe_path(&self) -> &Path {
-        &self.python_exe
-    }
+    /// SPDX license shortnames that apply to this distribution.
+    ///
+    /// Licenses only cover the core distribution. Licenses for libraries
+    /// required by extensions are stored next to the extension's linking
+    /// info.
+    pub licenses: Option<Vec<String>>,
 
-    fn python_major_minor_version(&self) -> String {
-        self.version[0..3].to_string()
-    }
+    /// Path to file holding license text for this distribution.
+    pub license_path: Option<PathBuf>,
 
-    fn cache_tag(&self) -> &str {
-        &self.cache_tag
-    }
+    /// Path to Tcl library files.
+    pub tcl_library_path: Option<PathBuf>,
 
-    fn python_module_suffixes(&self) -> Result<PythonModuleSuffixes> {
-        Ok(self.module_suffixes.clone())
-    }
+    /// Object files providing the core Python implementation.
+    ///
+    /// Keys are relative paths. Values are filesystem paths.
+    pub objs_core: BTreeMap<PathBuf, PathBuf>,
 
-    fn create_bytecode_compiler(&self) -> Result<BytecodeCompiler> {
-        BytecodeCompiler::new(&self.python_exe)
-    }
+    /// Linking information for the core Python implementation.
+    pub links_core: Vec<LibraryDependency>,
 
-    fn create_packaging_policy(&self) -> Result<PythonPackagingPolicy> {
-        let mut policy = PythonPackagingPolicy::default();
+    /// Filesystem location of pythonXY shared library for this distribution.
+    ///
+    /// Only set if `link_mode` is `StandaloneDistributionLinkMode::Dynamic`.
+    libpython_shared_library: Option<PathBuf>,
 
-        for triple in LINUX_TARGET_TRIPLES.iter() {
-            for ext in BROKEN_EXTENSIONS_LINUX.iter() {
-                policy.register_broken_extension(triple, ext);
-            }
-        }
+    /// Extension modules available to this distribution.
+    pub extension_modules: BTreeMap<String, PythonExtensionModuleVariants>,
 
-        for triple in MACOS_TARGET_TRIPLES.iter() {
-            for ext in BROKEN_EXTENSIONS_MACOS.iter() {
-                policy.register_broken_extension(triple, ext);
-            }
-        }
+    pub frozen_c: Vec<u8>,
 
-        Ok(policy)
-    }
+    /// Include files for Python.
+    ///
+    /// Keys are relative paths. Values are filesystem paths.
+    pub includes: BTreeMap<String, PathBuf>,
 
-    fn as_python_executable_builder(
-        &self,
-        _logger: &slog::Logger,
-        host_triple: &str,
-        target_triple: &str,
-        name: &str,
-        libpython_link_mode: BinaryLibpythonLinkMode,
-        policy: &PythonPackagingPolicy,
-        config: &EmbeddedPythonConfig,
-    ) -> Result<Box<dyn PythonBinaryBuilder>> {
-        let python_exe = self.python_exe.clone();
+    /// Static libraries available for linking.
+    ///
+    /// Keys are library names, without the "lib" prefix or file extension.
+    /// Values are filesystem paths where library is located.
+    pub libraries: BTreeMap<String, DataLocation>,
 
-        let (supports_static_libpython, supports_dynamic_libpython) =
-            if self.target_triple.contains("pc-windows") {
-                // On Windows, support for libpython linkage is determined
-                // by presence of a shared library in the distribution. This
-                // isn't entirely semantically correct. Since we use `dllexport`
-                // for all symbols in standalone distributions, it may
-                // theoretically be possible to produce both a static and dynamic
-                // libpython from the same object files. But since the
-                // static and dynamic distributions are built so differently, we
-                // don't want to take any chances and we force each distribution
-                // to its own domain.
-                (
-                    self.libpython_shared_library.is_none(),
-                    self.libpython_shared_library.is_some(),
-                )
-            } else if self.target_triple.contains("linux-musl") {
-                // Musl binaries don't support dynamic linking.
-                (true, false)
-            } else {
-                // Elsewhere we can choose which link mode to use.
-                (true, true)
-            };
+    pub py_modules: BTreeMap<String, PathBuf>,
 
-        let link_mode = match libpython_link_mode {
-            BinaryLibpythonLinkMode::Default => {
-                if supports_static_libpython {
-                    LibpythonLinkMode::Static
-                } else if supports_dynamic_libpython {
-                    LibpythonLinkMode::Dynamic
-                } else {
-                    return Err(anyhow!("no link modes supported; please report this bug"));
-                }
-            }
-            BinaryLibpythonLinkMode::Static => {
-                if !supports_static_libpython {
-                    return Err(anyhow!(
-                        "Python distribution does not support statically linking libpython"
-                    ));
-                }
+    /// Precompiled bytecode files shipped by the distribution.
+    ///
+    /// Keys are module names. Values are paths to `.pyc` files compiled at
+    /// optimization level 0.
+    pub py_bytecode: BTreeMap<String, PathBuf>,
 
-                LibpythonLinkMode::Static
-            }
-            BinaryLibpythonLinkMode::Dynamic => {
-                if !supports_dynamic_libpython {
-                    return Err(anyhow!(
-                        "Python distribution does not support dynamically linking libpython"
-                    ));
-                }
+    /// Non-module Python resource files.
+    ///
+    /// Keys are package names. Values are maps of resource name to data for the resource
+    /// within that package.
+    pub resources: BTreeMap<String, BTreeMap<String, PathBuf>>,
 
-                LibpythonLinkMode::Dynamic
-            }
-        };
+    /// Describes license info for things in this distribution.
+    pub license_infos: BTreeMap<String, Vec<LicenseInfo>>,
 
-        // Loading from memory is only supported on Windows where symbols are
-        // declspec(dllexport) and the distribution is capable of loading
-        // shared library extensions.
-        let supports_in_memory_dynamically_linked_extension_loading = target_triple
-            .contains("pc-windows")
-            && self.python_symbol_visibility == "dllexport"
-            && self
-                .extension_module_loading
-                .contains(&"shared-library".to_string());
+    /// Path to copy of hacked dist to use for packaging rules venvs
+    pub venv_base: PathBuf,
 
-        let mut builder = Box::new(StandalonePythonExecutableBuilder {
-            host_triple: host_triple.to_string(),
-            target_triple: target_triple.to_string(),
-            exe_name: name.to_string(),
-            // TODO can we avoid this clone()?
-            distribution: Arc::new(Box::new(self.clone())),
-            link_mode,
-            supports_in_memory_dynamically_linked_extension_loading,
-            packaging_policy: policy.clone(),
-            resources: PrePackagedResources::new(policy.get_resources_policy(), &self.cache_tag),
-            config: config.clone(),
-            python_exe,
-        });
+    /// Path to object file defining _PyImport_Inittab.
+    pub inittab_object: PathBuf,
 
-        builder.add_distribution_resources(&policy)?;
+    /// Path to C source file defining _PyImport_Inittab.
+    pub inittab_source: PathBuf,
 
-        Ok(builder)
-    }
+    /// Compiler flags to use to build object containing _PyImport_Inittab.
+    pub inittab_cflags: Vec<String>,
 
-    fn iter_extension_modules<'a>(
-        &'a self,
-    ) -> Box<dyn Iterator<Item = &'a PythonExtensionModule> + 'a> {
-        Box::new(
-            self.extension_modules
-                .iter()
-                .map(|(_, exts)| exts.iter())
-                .flatten(),
-        )
-    }
+    /// Tag to apply to bytecode files.
+    ///
+    /// e.g. `cpython-37`.
+    pub cache_tag: String,
 
-    fn source_modules(&self) -> Result<Vec<PythonModuleSource>> {
-        self.py_modules
-            .iter()
-            .map(|(name, path)| {
-                let is_package = is_package_from_path(&path);
+    /// Hex encoded magic number for bytecode files.
+    ///
+    /// e.g. `550d0d0a`.
+    pub python_bytecode_magic_number: String,
 
-                Ok(PythonModuleSource {
-                    name: name.clone(),
-                    source: DataLocation::Path(path.clone()),
-                    is_package,
-                    cache_tag: self.cache_tag.clone(),
-                    is_stdlib: true,
-                    is_test: is_stdlib_test_package(name),
-                })
-            })
-            .collect()
-    }
+    /// Suffixes for Python module types.
+    module_suffixes: PythonModuleSuffixes,
 
-    fn resource_datas(&self) -> Result<Vec<PythonPackageResource>> {
-        let mut res = Vec::new();
+    /// Names of standard library packages the distribution annotates as tests.
+    stdlib_test_packages: Vec<String>,
 
-        for (package, inner) in self.resources.iter() {
-            for (name, path) in inner.iter() {
-                res.push(PythonPackageResource {
-                    leaf_package: package.clone(),
-                    relative_name: name.clone(),
-                    data: DataLocation::Path(path.clone()),
-                    is_stdlib: true,
-                    is_test: is_stdlib_test_package(&package),
-                });
-            }
-        }
+    /// Optimizations the distribution was built with. e.g. `noopt` or `pgo+lto`.
+    optimizations: String,
 
-        Ok(res)
-    }
+    /// Whether only Python resources were analyzed.
+    ///
+    /// Instances with this set lack linking info and can't build binaries.
+    resources_only: bool,
 
-    /// Ensure pip is available to run in the distribution.
-    fn ensure_pip(&self, logger: &slog::Logger) -> Result<PathBuf> {
-        let dist_prefix = self.base_dir.join("python").join("install");
-        let python_paths = resolve_python_paths(&dist_prefix, &self.version);
+    /// Build metadata as parsed from `PYTHON.json`.
+    build_info: PythonBuildInfo,
+}
 
-        let pip_path = python_paths.bin_dir.join(PIP_EXE_BASENAME);
+impl StandaloneDistribution {
+    pub fn from_location(
+        logger: &slog::Logger,
+        location: &PythonDistributionLocation,
+        distributions_dir: &Path,
+    ) -> Result<Self> {
+        let (archive_path, extract_path) =
+            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
 
-        if !pip_path.exists() {
-            warn!(logger, "{} doesnt exist", pip_path.display().to_string());
-            invoke_python(&python_paths, &logger, &["-m", "ensurepip"]);
-        }
+        let sha256 = match location {
+            PythonDistributionLocation::Local { sha256, .. } => sha256,
+            PythonDistributionLocation::Url { sha256, .. } => sha256,
+        };
 
-        Ok(pip_path)
+        let options = DistributionExtractOptions {
+            expected_sha256: Some(sha256.clone()),
+            ..DistributionExtractOptions::default()
+        };
+
+        if archive_path.to_string_lossy().ends_with(".zip") {
+            Self::from_zip_file_with_options(logger, &archive_path, &extract_path, &options)
+        } else {
+            Self::from_tar_zst_file_with_options(logger, &archive_path, &extract_path, &options)
+        }
     }
 
-    fn resolve_distutils(
-        &self,
+    /// Create an instance from a compressed tar file.
+    ///
+    /// The compression format is determined from the filename suffix:
+    /// `.tar.zst` (zstd), `.tar.gz` (gzip), or `.tar.xz` (xz).
+    ///
+    /// The distribution will be extracted to ``extract_dir`` if necessary.
+    pub fn from_tar_zst_file(
         logger: &slog::Logger,
-        libpython_link_mode: LibpythonLinkMode,
-        dest_dir: &Path,
-        extra_python_paths: &[&Path],
-    ) -> Result<HashMap<String, String>> {
-        match libpython_link_mode {
-            // We need to patch distutils if the distribution is statically linked.
-            LibpythonLinkMode::Static => prepare_hacked_distutils(
-                logger,
-                &self.stdlib_path.join("distutils"),
-                dest_dir,
-                extra_python_paths,
-            ),
-            LibpythonLinkMode::Dynamic => Ok(HashMap::new()),
-        }
+        path: &Path,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_zst_file_with_options(
+            logger,
+            path,
+            extract_dir,
+            &DistributionExtractOptions::default(),
+        )
     }
 
-    fn filter_compatible_python_resources(
-        &self,
+    /// Create an instance from a compressed tar file using explicit extraction options.
+    ///
+    /// If `options.expected_sha256` is set, the file must have that hex
+    /// SHA-256 digest.
+    pub fn from_tar_zst_file_with_options(
         logger: &slog::Logger,
-        resources: &[PythonResource],
-    ) -> Result<Vec<PythonResource>> {
-        Ok(resources
-            .iter()
-            .filter(|resource| match resource {
-                // Extension modules defined as shared libraries are only compatible
-                // with some configurations.
-                PythonResource::ExtensionModuleDynamicLibrary { .. } => {
-                    if self.is_extension_module_file_loadable() {
-                        true
-                    } else {
-                        warn!(logger, "ignoring extension module {} because it isn't loadable for the target configuration",
-                            resource.full_name());
-                        false
-                    }
-                }
+        path: &Path,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        let basename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to determine filename"))?
+            .to_string_lossy();
 
-                // Only look at the raw object files if the distribution produces
-                // them.
-                // TODO have PythonDistribution expose API to determine this.
-                PythonResource::ExtensionModuleStaticallyLinked(_) =>
-                    self.link_mode == StandaloneDistributionLinkMode::Static,
+        let from_tar_compressed = if basename.ends_with(".tar.zst") {
+            Self::from_tar_zst_with_options::<BufReader<std::fs::File>>
+        } else if basename.ends_with(".tar.gz") {
+            Self::from_tar_gz_with_options::<BufReader<std::fs::File>>
+        } else if basename.ends_with(".tar.xz") {
+            Self::from_tar_xz_with_options::<BufReader<std::fs::File>>
+        } else {
+            return Err(anyhow!("unhandled distribution format: {}", path.display()));
+        };
 
-                PythonResource::ModuleSource { .. } => true,
-                PythonResource::ModuleBytecodeRequest { .. } => true,
-                PythonResource::ModuleBytecode { .. } => true,
-                PythonResource::Resource { .. } => true,
-                PythonResource::DistributionResource(_) => true,
-                PythonResource::EggFile(_) => false,
-                PythonResource::PathExtension(_) => false,
-            })
-            .cloned()
-            .collect())
-    }
-}
+        let options = DistributionExtractOptions {
+            archive_sha256: Some(archive_file_sha256(path, options)?),
+            ..options.clone()
+        };
 
-/// A self-contained Python executable before it is compiled.
-#[derive(Clone, Debug)]
-pub struct StandalonePythonExecutableBuilder {
-    /// The target triple we are running on.
-    host_triple: String,
+        let fh = std::fs::File::open(path)
+            .with_context(|| format!("unable to open {}", path.display()))?;
 
-    /// The target triple we are building for.
-    target_triple: String,
+        let reader = BufReader::with_capacity(options.archive_buffer_size, fh);
+        warn!(logger, "reading data from Python distribution...");
 
-    /// The name of the executable to build.
-    exe_name: String,
+        from_tar_compressed(logger, reader, extract_dir, &options)
+    }
 
-    /// The Python distribution being used to build this executable.
-    distribution: Arc<Box<StandaloneDistribution>>,
+    /// Create an instance from a .zip file.
+    ///
+    /// The archive must have the same layout as .tar.zst distributions. The
+    /// distribution will be extracted to ``extract_dir`` if necessary.
+    /// If `expected_sha256` is set, the file must have that hex SHA-256 digest.
+    pub fn from_zip_file(
+        logger: &slog::Logger,
+        path: &Path,
+        extract_dir: &Path,
+        expected_sha256: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_zip_file_with_options(
+            logger,
+            path,
+            extract_dir,
+            &DistributionExtractOptions {
+                expected_sha256: expected_sha256.map(|s| s.to_string()),
+                ..DistributionExtractOptions::default()
+            },
+        )
+    }
 
-    /// How libpython should be linked.
-    link_mode: LibpythonLinkMode,
+    /// Create an instance from a .zip file using explicit extraction options.
+    pub fn from_zip_file_with_options(
+        logger: &slog::Logger,
+        path: &Path,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        let archive_sha256 = archive_file_sha256(path, options)?;
 
-    /// Whether the built binary is capable of loading dynamically linked
-    /// extension modules from memory.
-    supports_in_memory_dynamically_linked_extension_loading: bool,
+        {
+            let _lock = DistributionExtractLock::new(extract_dir)?;
 
-    /// Policy to apply to added resources.
-    packaging_policy: PythonPackagingPolicy,
+            extract_archive_cached(extract_dir, Some(&archive_sha256), |dest| {
+                let fh = std::fs::File::open(path)
+                    .with_context(|| format!("unable to open {}", path.display()))?;
+                let mut zf =
+                    zip::ZipArchive::new(BufReader::with_capacity(options.archive_buffer_size, fh))
+                        .with_context(|| format!("reading zip archive {}", path.display()))?;
 
-    /// Python resources to be embedded in the binary.
-    resources: PrePackagedResources,
+                warn!(logger, "reading data from Python distribution...");
 
-    /// Configuration of the embedded Python interpreter.
-    config: EmbeddedPythonConfig,
+                extract_zip(logger, &mut zf, dest, options)
+            })?;
+        }
 
-    /// Path to python executable that can be invoked at build time.
-    python_exe: PathBuf,
-}
-
-impl StandalonePythonExecutableBuilder {
-    #[allow(clippy::too_many_arguments)]
-    fn add_distribution_resources(&mut self, policy: &PythonPackagingPolicy) -> Result<()> {
-        for ext in self.packaging_policy.resolve_python_extension_modules(
-            self.distribution.extension_modules.values(),
-            &self.target_triple,
-        )? {
-            self.add_distribution_extension_module(&ext)?;
-        }
-
-        for source in self.distribution.source_modules()? {
-            if policy.filter_python_resource(&source.clone().into()) {
-                self.add_module_source(&source)?;
-            }
-
-            let bytecode = source.as_bytecode_module(BytecodeOptimizationLevel::Zero);
-
-            if policy.filter_python_resource(&bytecode.clone().into()) {
-                self.add_module_bytecode(&bytecode)?;
-            }
-        }
-
-        for resource in self.distribution.resource_datas()? {
-            if policy.filter_python_resource(&resource.clone().into()) {
-                self.add_package_resource(&resource)?;
-            }
-        }
-
-        Ok(())
+        Self::from_directory_with_logger(logger, extract_dir)
     }
 
-    /// Build a Python library suitable for linking.
+    /// Extract and analyze a standalone distribution from a zstd compressed tar stream.
     ///
-    /// This will take the underlying distribution, resources, and
-    /// configuration and produce a new executable binary.
-    fn resolve_python_linking_info(
-        &self,
-        logger: &slog::Logger,
-        opt_level: &str,
-        resources: &EmbeddedPythonResources,
-    ) -> Result<PythonLinkingInfo> {
-        let libpythonxy_filename;
-        let mut cargo_metadata: Vec<String> = Vec::new();
-        let libpythonxy_data;
-        let libpython_filename: Option<PathBuf>;
-        let libpyembeddedconfig_data: Option<Vec<u8>>;
-        let libpyembeddedconfig_filename: Option<PathBuf>;
-
-        match self.link_mode {
-            LibpythonLinkMode::Static => {
-                let temp_dir = TempDir::new("pyoxidizer-build-exe")?;
-                let temp_dir_path = temp_dir.path();
-
-                warn!(
-                    logger,
-                    "generating custom link library containing Python..."
-                );
-                let library_info = link_libpython(
-                    logger,
-                    &self.distribution,
-                    resources,
-                    &temp_dir_path,
-                    &self.host_triple,
-                    &self.target_triple,
-                    opt_level,
-                )?;
-
-                libpythonxy_filename =
-                    PathBuf::from(library_info.libpython_path.file_name().unwrap());
-                cargo_metadata.extend(library_info.cargo_metadata);
-
-                libpythonxy_data = std::fs::read(&library_info.libpython_path)?;
-                libpython_filename = None;
-                libpyembeddedconfig_filename = Some(PathBuf::from(
-                    library_info.libpyembeddedconfig_path.file_name().unwrap(),
-                ));
-                libpyembeddedconfig_data =
-                    Some(std::fs::read(&library_info.libpyembeddedconfig_path)?);
-            }
-
-            LibpythonLinkMode::Dynamic => {
-                libpythonxy_filename = PathBuf::from("pythonXY.lib");
-                libpythonxy_data = Vec::new();
-                libpython_filename = self.distribution.libpython_shared_library.clone();
-                libpyembeddedconfig_filename = None;
-                libpyembeddedconfig_data = None;
-            }
-        }
-
-        Ok(PythonLinkingInfo {
-            libpythonxy_filename,
-            libpythonxy_data,
-            libpython_filename,
-            libpyembeddedconfig_filename,
-            libpyembeddedconfig_data,
-            cargo_metadata,
-        })
-    }
-}
-
-impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
-    fn clone_box(&self) -> Box<dyn PythonBinaryBuilder> {
-        Box::new(self.clone())
+    /// Log messages are discarded. See `from_tar_zst_with_logger()`.
+    pub fn from_tar_zst<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
+        Self::from_tar_zst_with_logger(&discard_logger(), source, extract_dir)
     }
 
-    fn name(&self) -> String {
-        self.exe_name.clone()
+    /// Extract and analyze a zstd compressed tar stream, logging to `logger`.
+    pub fn from_tar_zst_with_logger<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_zst_with_options(
+            logger,
+            source,
+            extract_dir,
+            &DistributionExtractOptions::default(),
+        )
     }
 
-    fn libpython_link_mode(&self) -> LibpythonLinkMode {
-        self.link_mode
+    /// Extract and analyze a standalone distribution from zstd compressed tar data in memory.
+    ///
+    /// The archive is never written to disk. But it is still extracted to
+    /// ``extract_dir``, which must be a real directory on the filesystem. An
+    /// existing extraction of the same data is reused.
+    pub fn from_tar_zst_bytes(
+        logger: &slog::Logger,
+        data: &[u8],
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_zst_with_options(
+            logger,
+            std::io::Cursor::new(data),
+            extract_dir,
+            &DistributionExtractOptions {
+                archive_sha256: Some(hex::encode(Sha256::digest(data))),
+                ..DistributionExtractOptions::default()
+            },
+        )
     }
 
-    fn cache_tag(&self) -> &str {
-        self.distribution.cache_tag()
-    }
+    /// Extract and analyze a zstd compressed tar stream using explicit extraction options.
+    pub fn from_tar_zst_with_options<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        let dctx = zstd_decoder(source, options)?;
 
-    fn python_packaging_policy(&self) -> &PythonPackagingPolicy {
-        &self.packaging_policy
+        Self::from_tar_with_options(logger, dctx, extract_dir, options)
     }
 
-    fn python_exe_path(&self) -> &Path {
-        &self.python_exe
+    /// Extract and analyze a standalone distribution from a gzip compressed tar stream.
+    pub fn from_tar_gz<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_gz_with_options(
+            logger,
+            source,
+            extract_dir,
+            &DistributionExtractOptions::default(),
+        )
     }
 
-    fn iter_resources<'a>(
-        &'a self,
-    ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a> {
-        Box::new(self.resources.iter_resources())
-    }
+    /// Extract and analyze a gzip compressed tar stream using explicit extraction options.
+    pub fn from_tar_gz_with_options<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        let decoder = flate2::read::GzDecoder::new(source);
 
-    fn builtin_extension_module_names<'a>(&'a self) -> Box<dyn Iterator<Item = &'a String> + 'a> {
-        Box::new(self.resources.builtin_extension_module_names())
+        Self::from_tar_with_options(logger, decoder, extract_dir, options)
     }
 
-    fn pip_install(
-        &self,
+    /// Extract and analyze a standalone distribution from an xz compressed tar stream.
+    pub fn from_tar_xz<R: Read>(
         logger: &slog::Logger,
-        verbose: bool,
-        install_args: &[String],
-        extra_envs: &HashMap<String, String>,
-    ) -> Result<Vec<PythonResource>> {
-        pip_install(
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_xz_with_options(
             logger,
-            &**self.distribution,
-            self.link_mode,
-            verbose,
-            install_args,
-            extra_envs,
+            source,
+            extract_dir,
+            &DistributionExtractOptions::default(),
         )
     }
 
-    fn read_package_root(
-        &self,
+    /// Extract and analyze an xz compressed tar stream using explicit extraction options.
+    pub fn from_tar_xz_with_options<R: Read>(
         logger: &slog::Logger,
-        path: &Path,
-        packages: &[String],
-    ) -> Result<Vec<PythonResource>> {
-        Ok(find_resources(&logger, &**self.distribution, path, None)?
-            .iter()
-            .filter_map(|x| {
-                if x.is_in_packages(packages) {
-                    Some(x.clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>())
+        source: R,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        let decoder = xz2::read::XzDecoder::new(source);
+
+        Self::from_tar_with_options(logger, decoder, extract_dir, options)
     }
 
-    fn read_virtualenv(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>> {
-        read_virtualenv(logger, &**self.distribution, path)
+    /// Extract and analyze a standalone distribution from a tar stream.
+    ///
+    /// Log messages are discarded. See `from_tar_with_logger()`.
+    pub fn from_tar<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
+        Self::from_tar_with_logger(&discard_logger(), source, extract_dir)
     }
 
-    fn setup_py_install(
-        &self,
+    /// Extract and analyze a standalone distribution from a tar stream, logging to `logger`.
+    pub fn from_tar_with_logger<R: Read>(
         logger: &slog::Logger,
-        package_path: &Path,
-        verbose: bool,
-        extra_envs: &HashMap<String, String>,
-        extra_global_arguments: &[String],
-    ) -> Result<Vec<PythonResource>> {
-        setup_py_install(
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_with_options(
             logger,
-            &**self.distribution,
-            self.link_mode,
-            package_path,
-            verbose,
-            extra_envs,
-            extra_global_arguments,
+            source,
+            extract_dir,
+            &DistributionExtractOptions::default(),
         )
     }
 
-    fn add_in_memory_module_source(&mut self, module: &PythonModuleSource) -> Result<()> {
-        self.resources
-            .add_python_module_source(module, &ConcreteResourceLocation::InMemory)
-    }
-
-    fn add_relative_path_module_source(
-        &mut self,
-        prefix: &str,
-        module: &PythonModuleSource,
-    ) -> Result<()> {
-        self.resources.add_python_module_source(
-            module,
-            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
-        )
-    }
+    /// Extract and analyze a tar stream using explicit extraction options.
+    ///
+    /// An existing extraction in `extract_dir` is only reused if
+    /// `options.archive_sha256` is set and matches the digest recorded when
+    /// it was extracted.
+    pub fn from_tar_with_options<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<Self> {
+        {
+            let _lock = DistributionExtractLock::new(extract_dir)?;
 
-    fn add_in_memory_module_bytecode(
-        &mut self,
-        module: &PythonModuleBytecodeFromSource,
-    ) -> Result<()> {
-        self.resources
-            .add_python_module_bytecode_from_source(module, &ConcreteResourceLocation::InMemory)
-    }
+            // The content of the distribution could change between runs. So
+            // only reuse an extraction known to come from the same archive.
+            extract_archive_cached(extract_dir, options.archive_sha256.as_deref(), |dest| {
+                extract_tar(logger, source, dest, options)
+            })?;
+        }
 
-    fn add_relative_path_module_bytecode(
-        &mut self,
-        prefix: &str,
-        module: &PythonModuleBytecodeFromSource,
-    ) -> Result<()> {
-        self.resources.add_python_module_bytecode_from_source(
-            module,
-            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
-        )
+        Self::from_directory_with_logger(logger, extract_dir)
     }
 
-    fn add_in_memory_package_resource(&mut self, resource: &PythonPackageResource) -> Result<()> {
-        self.resources
-            .add_python_package_resource(resource, &ConcreteResourceLocation::InMemory)
+    /// Obtain an instance by scanning a directory containing an extracted distribution.
+    ///
+    /// Log messages are discarded. See `from_directory_with_logger()`.
+    pub fn from_directory(dist_dir: &Path) -> Result<Self> {
+        Self::from_directory_with_logger(&discard_logger(), dist_dir)
     }
 
-    fn add_relative_path_package_resource(
-        &mut self,
-        prefix: &str,
-        resource: &PythonPackageResource,
-    ) -> Result<()> {
-        self.resources.add_python_package_resource(
-            resource,
-            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
-        )
+    /// Obtain an instance by scanning an extracted distribution, logging to `logger`.
+    pub fn from_directory_with_logger(logger: &slog::Logger, dist_dir: &Path) -> Result<Self> {
+        Self::from_directory_impl(logger, dist_dir, DistributionScan::Full)
     }
 
-    fn add_in_memory_package_distribution_resource(
-        &mut self,
-        resource: &PythonPackageDistributionResource,
-    ) -> Result<()> {
-        self.resources
-            .add_python_package_distribution_resource(resource, &ConcreteResourceLocation::InMemory)
+    /// Obtain an instance suitable only for inspecting Python resources.
+    ///
+    /// Only Python modules, resources, and module suffixes are analyzed.
+    /// Extension modules and linking info are not populated, so the
+    /// returned instance cannot be used to build binaries.
+    pub fn from_directory_light(logger: &slog::Logger, dist_dir: &Path) -> Result<Self> {
+        Self::from_directory_impl(logger, dist_dir, DistributionScan::Resources)
     }
 
-    fn add_relative_path_package_distribution_resource(
-        &mut self,
-        prefix: &str,
-        resource: &PythonPackageDistributionResource,
-    ) -> Result<()> {
-        self.resources.add_python_package_distribution_resource(
-            resource,
-            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
-        )
+    /// Obtain an instance describing only the standard library of a distribution.
+    ///
+    /// This is even lighter than `from_directory_light()`: only `PYTHON.json`
+    /// and the standard library source modules and resources are read.
+    /// Bytecode, licenses, and all linking info are left empty. The returned
+    /// instance is suitable for analyzing `source_modules()` and
+    /// `resource_datas()` but cannot be used to build binaries.
+    pub fn from_directory_stdlib_only(dist_dir: &Path) -> Result<Self> {
+        let logger = discard_logger();
+
+        Self::from_directory_impl(&logger, dist_dir, DistributionScan::Stdlib)
     }
 
-    fn add_builtin_distribution_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        self.resources
-            .add_builtin_distribution_extension_module(&extension_module)
-    }
+    #[allow(clippy::cognitive_complexity)]
+    fn from_directory_impl(
+        logger: &slog::Logger,
+        dist_dir: &Path,
+        scan: DistributionScan,
+    ) -> Result<Self> {
+        let resources_only = scan != DistributionScan::Full;
+        let mut objs_core: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+        let mut links_core: Vec<LibraryDependency> = Vec::new();
+        let mut extension_modules: BTreeMap<String, PythonExtensionModuleVariants> =
+            BTreeMap::new();
+        let mut includes: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let mut libraries: BTreeMap<String, DataLocation> = BTreeMap::new();
+        let frozen_c: Vec<u8> = Vec::new();
+        let mut py_modules: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let mut py_bytecode: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let mut resources: BTreeMap<String, BTreeMap<String, PathBuf>> = BTreeMap::new();
+        let mut license_infos: BTreeMap<String, Vec<LicenseInfo>> = BTreeMap::new();
 
-    fn add_in_memory_distribution_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        if !self.supports_in_memory_dynamically_linked_extension_loading {
-            return Err(anyhow!(
-                "loading extension modules from memory not supported by this build configuration"
-            ));
+        for entry in std::fs::read_dir(dist_dir)? {
+            let entry = entry?;
+
+            match entry.file_name().to_str() {
+                Some("python") | Some(EXTRACTION_MARKER) => continue,
+                Some(value) => {
+                    return Err(anyhow!(
+                        "unexpected entry in distribution root directory: {}",
+                        value
+                    ))
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "error listing root directory of Python distribution"
+                    ))
+                }
+            };
         }
 
-        self.resources
-            .add_in_memory_distribution_extension_module(extension_module)
-    }
+        let python_path = dist_dir.join("python");
 
-    fn add_relative_path_distribution_extension_module(
-        &mut self,
-        prefix: &str,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        if self.distribution.is_extension_module_file_loadable() {
-            self.resources
-                .add_relative_path_distribution_extension_module(prefix, extension_module)
-        } else {
-            Err(anyhow!(
-                "loading extension modules from files not supported by this build configuration"
-            ))
+        for entry in std::fs::read_dir(&python_path)? {
+            let entry = entry?;
+
+            match entry.file_name().to_str() {
+                Some("build") => continue,
+                Some("install") => continue,
+                Some("lib") => continue,
+                Some("licenses") => continue,
+                Some("LICENSE.rst") => continue,
+                Some("PYTHON.json") => continue,
+                Some(value) => {
+                    return Err(anyhow!("unexpected entry in python/ directory: {}", value))
+                }
+                _ => return Err(anyhow!("error listing python/ directory")),
+            };
         }
-    }
 
-    fn add_distribution_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        // Distribution extensions are special in that we allow them to be
-        // builtin extensions, even if it violates the resources policy that prohibits
-        // memory loading.
+        let pi = parse_python_json_from_distribution(dist_dir)?;
+        let build_info = pi.build_info.clone();
 
-        // Builtins always get added as such.
-        if extension_module.builtin_default {
-            return self.add_builtin_distribution_extension_module(&extension_module);
+        // Newer distributions may add fields. They shouldn't be required to
+        // use the distribution, so we continue without them.
+        let mut unknown_fields = pi.unknown_fields.keys().collect::<Vec<_>>();
+        unknown_fields.sort();
+        for field in unknown_fields {
+            warn!(
+                logger,
+                "ignoring unknown field {} in PYTHON.json of {}; a newer PyOxidizer may support it",
+                field,
+                dist_dir.display()
+            );
         }
 
-        match self.packaging_policy.get_resources_policy().clone() {
-            PythonResourcesPolicy::InMemoryOnly => match self.link_mode {
-                LibpythonLinkMode::Static => {
-                    self.add_builtin_distribution_extension_module(&extension_module)
-                }
-                LibpythonLinkMode::Dynamic => {
-                    self.add_in_memory_distribution_extension_module(&extension_module)
-                }
-            },
-            PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => match self.link_mode {
-                LibpythonLinkMode::Static => {
-                    self.add_builtin_distribution_extension_module(&extension_module)
-                }
-                LibpythonLinkMode::Dynamic => {
-                    self.add_relative_path_distribution_extension_module(&prefix, &extension_module)
+        if let Some(python_license_path) = pi
+            .license_path
+            .as_ref()
+            .filter(|_| scan != DistributionScan::Stdlib)
+        {
+            let license_path = python_path.join(python_license_path);
+            let license_text = std::fs::read_to_string(&license_path).with_context(|| {
+                format!("unable to read Python license {}", license_path.display())
+            })?;
+
+            let mut licenses = Vec::new();
+            licenses.push(LicenseInfo {
+                licenses: pi.licenses.clone().unwrap(),
+                license_filename: "LICENSE.python.txt".to_string(),
+                license_text,
+            });
+
+            license_infos.insert("python".to_string(), licenses);
+        }
+
+        if !resources_only {
+            // Collect object files for libpython.
+            for obj in &pi.build_info.core.objs {
+                let rel_path = PathBuf::from(obj);
+                let full_path = python_path.join(obj);
+
+                objs_core.insert(rel_path, full_path);
+            }
+
+            for entry in &pi.build_info.core.links {
+                let depends = entry.to_library_dependency(&python_path);
+
+                if let Some(p) = &depends.static_library {
+                    libraries.insert(depends.name.clone(), p.clone());
                 }
-            },
-            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(prefix) => {
-                match self.link_mode {
-                    LibpythonLinkMode::Static => {
-                        self.add_builtin_distribution_extension_module(&extension_module)
+
+                links_core.push(depends);
+            }
+        }
+
+        let module_suffixes = PythonModuleSuffixes {
+            source: pi
+                .python_suffixes
+                .get("source")
+                .ok_or_else(|| anyhow!("distribution does not define source suffixes"))?
+                .clone(),
+            bytecode: pi
+                .python_suffixes
+                .get("bytecode")
+                .ok_or_else(|| anyhow!("distribution does not define bytecode suffixes"))?
+                .clone(),
+            debug_bytecode: pi
+                .python_suffixes
+                .get("debug_bytecode")
+                .ok_or_else(|| anyhow!("distribution does not define debug bytecode suffixes"))?
+                .clone(),
+            optimized_bytecode: pi
+                .python_suffixes
+                .get("optimized_bytecode")
+                .ok_or_else(|| anyhow!("distribution does not define optimized bytecode suffixes"))?
+                .clone(),
+            extension: pi
+                .python_suffixes
+                .get("extension")
+                .ok_or_else(|| anyhow!("distribution does not define extension suffixes"))?
+                .clone(),
+        };
+
+        if !resources_only {
+            // Collect extension modules.
+            for (module, variants) in &pi.build_info.extensions {
+                let mut ems = PythonExtensionModuleVariants::default();
+
+                for entry in variants.iter() {
+                    let object_file_data = entry
+                        .objs
+                        .iter()
+                        .map(|p| DataLocation::Path(python_path.join(p)))
+                        .collect();
+                    let mut links = Vec::new();
+
+                    for link in &entry.links {
+                        let depends = link.to_library_dependency(&python_path);
+
+                        if let Some(p) = &depends.static_library {
+                            libraries.insert(depends.name.clone(), p.clone());
+                        }
+
+                        links.push(depends);
                     }
-                    LibpythonLinkMode::Dynamic => {
-                        // Try in-memory and fall back to file-based if that fails.
-                        let mut res =
-                            self.add_in_memory_distribution_extension_module(&extension_module);
 
-                        if res.is_err() {
-                            res = self.add_relative_path_distribution_extension_module(
-                                &prefix,
-                                &extension_module,
-                            )
+                    if let Some(ref license_paths) = entry.license_paths {
+                        let mut licenses = Vec::new();
+
+                        for license_path in license_paths {
+                            let license_path = python_path.join(license_path);
+                            let license_text = std::fs::read_to_string(&license_path)
+                                .with_context(|| "unable to read license file")?;
+
+                            licenses.push(LicenseInfo {
+                                licenses: entry.licenses.clone().unwrap(),
+                                license_filename: license_path
+                                    .file_name()
+                                    .unwrap()
+                                    .to_str()
+                                    .unwrap()
+                                    .to_string(),
+                                license_text,
+                            });
                         }
 
-                        res
+                        license_infos.insert(module.clone(), licenses);
                     }
+
+                    ems.push(PythonExtensionModule {
+                        name: module.clone(),
+                        init_fn: Some(entry.init_fn.clone()),
+                        extension_file_suffix: "".to_string(),
+                        shared_library: if let Some(path) = &entry.shared_lib {
+                            Some(DataLocation::Path(python_path.join(path)))
+                        } else {
+                            None
+                        },
+                        object_file_data,
+                        is_package: false,
+                        link_libraries: links,
+                        is_stdlib: true,
+                        builtin_default: entry.in_core,
+                        required: entry.required,
+                        variant: Some(entry.variant.clone()),
+                        licenses: entry.licenses.clone(),
+                        license_texts: if let Some(licenses) = &entry.license_paths {
+                            Some(
+                                licenses
+                                    .iter()
+                                    .map(|p| DataLocation::Path(python_path.join(p)))
+                                    .collect(),
+                            )
+                        } else {
+                            None
+                        },
+                        license_public_domain: entry.license_public_domain,
+                    });
+                }
+
+                extension_modules.insert(module.clone(), ems);
+            }
+
+            let include_path = if let Some(p) = pi.python_paths.get("include") {
+                python_path.join(p)
+            } else {
+                return Err(DistributionError::MissingPath {
+                    key: "include".to_string(),
                 }
+                .into());
+            };
+
+            for entry in walk_tree_files(&include_path) {
+                let full_path = entry.path();
+                let rel_path = full_path
+                    .strip_prefix(&include_path)
+                    .expect("unable to strip prefix");
+                includes.insert(
+                    String::from(rel_path.to_str().expect("path to string")),
+                    full_path.to_path_buf(),
+                );
             }
         }
-    }
 
-    fn add_in_memory_dynamic_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        if self.supports_in_memory_dynamically_linked_extension_loading
-            && extension_module.shared_library.is_some()
-        {
-            self.resources
-                .add_in_memory_extension_module_shared_library(
-                    &extension_module.name,
-                    extension_module.is_package,
-                    &extension_module
-                        .shared_library
-                        .as_ref()
-                        .unwrap()
-                        .resolve()?,
-                )
-        } else if !extension_module.object_file_data.is_empty() {
-            // TODO we shouldn't be adding a builtin extension module from this API.
-            self.resources
-                .add_builtin_extension_module(extension_module)
-        } else if extension_module.shared_library.is_some() {
-            Err(anyhow!(
-                "loading extension modules from memory not supported by this build configuration"
-            ))
+        let stdlib_path = if let Some(p) = pi.python_paths.get("stdlib") {
+            python_path.join(p)
         } else {
-            Err(anyhow!(
-                "cannot load extension module from memory due to missing object files"
-            ))
-        }
+            return Err(DistributionError::MissingPath {
+                key: "stdlib".to_string(),
+            }
+            .into());
+        };
+
+        for entry in find_python_resources(
+            &stdlib_path,
+            &pi.python_implementation_cache_tag,
+            &module_suffixes,
+        ) {
+            match entry? {
+                PythonResource::Resource(resource) => {
+                    if !resources.contains_key(&resource.leaf_package) {
+                        resources.insert(resource.leaf_package.clone(), BTreeMap::new());
+                    }
+
+                    resources.get_mut(&resource.leaf_package).unwrap().insert(
+                        resource.relative_name.clone(),
+                        match resource.data {
+                            DataLocation::Path(path) => path,
+                            DataLocation::Memory(_) => {
+                                return Err(anyhow!(
+                                    "should not have received in-memory resource data"
+                                ))
+                            }
+                        },
+                    );
+                }
+                PythonResource::ModuleSource(source) => match source.source {
+                    DataLocation::Path(path) => {
+                        py_modules.insert(source.name.clone(), path);
+                    }
+                    DataLocation::Memory(_) => {
+                        return Err(anyhow!("should not have received in-memory source data"))
+                    }
+                },
+                PythonResource::ModuleBytecode(bytecode) => {
+                    if scan != DistributionScan::Stdlib
+                        && bytecode.optimize_level == BytecodeOptimizationLevel::Zero
+                    {
+                        py_bytecode.insert(
+                            bytecode.name.clone(),
+                            bytecode.resolve_path(&stdlib_path.display().to_string()),
+                        );
+                    }
+                }
+                _ => {}
+            };
+        }
+
+        let venv_base = dist_dir.parent().unwrap().join("hacked_base");
+
+        let (link_mode, libpython_shared_library) = if pi.libpython_link_mode == "static" {
+            (StandaloneDistributionLinkMode::Static, None)
+        } else if pi.libpython_link_mode == "shared" {
+            (
+                StandaloneDistributionLinkMode::Dynamic,
+                if scan == DistributionScan::Stdlib {
+                    None
+                } else {
+                    Some(python_path.join(pi.build_info.core.shared_lib.as_ref().unwrap()))
+                },
+            )
+        } else {
+            return Err(
+                DistributionError::UnhandledLinkMode(pi.libpython_link_mode.clone()).into(),
+            );
+        };
+
+        let inittab_object = python_path.join(&pi.build_info.inittab_object);
+        let inittab_source = python_path.join(&pi.build_info.inittab_source);
+
+        Ok(Self {
+            base_dir: dist_dir.to_path_buf(),
+            target_triple: pi.target_triple.clone(),
+            crt_features: pi.crt_features.clone(),
+            python_tag: pi.python_tag.clone(),
+            python_abi_tag: pi.python_abi_tag.clone(),
+            python_platform_tag: pi.python_platform_tag.clone(),
+            version: pi.python_version.clone(),
+            python_exe: python_exe_path(dist_dir)?,
+            stdlib_path,
+            link_mode,
+            python_symbol_visibility: pi.python_symbol_visibility.clone(),
+            extension_module_loading: pi.python_extension_module_loading.clone(),
+            licenses: pi.licenses.clone(),
+            license_path: match pi.license_path {
+                Some(ref path) => Some(PathBuf::from(path)),
+                None => None,
+            },
+            tcl_library_path: match pi.tcl_library_path {
+                Some(ref path) => Some(PathBuf::from(path)),
+                None => None,
+            },
+
+            extension_modules,
+            frozen_c,
+            includes,
+            links_core,
+            libraries,
+            objs_core,
+            libpython_shared_library,
+            py_modules,
+            py_bytecode,
+            resources,
+            license_infos,
+            venv_base,
+            inittab_object,
+            inittab_source,
+            inittab_cflags: pi.build_info.inittab_cflags.clone(),
+            cache_tag: pi.python_implementation_cache_tag.clone(),
+            python_bytecode_magic_number: pi.python_bytecode_magic_number.clone(),
+            module_suffixes,
+            stdlib_test_packages: pi.python_stdlib_test_packages.clone(),
+            optimizations: pi.optimizations.clone(),
+            resources_only,
+            build_info,
+        })
+    }
+
+    /// Duplicate the python distribution, with distutils hacked
+    ///
+    /// The directory is created and prepared while holding the distribution
+    /// extraction lock, so concurrent builds don't race to set it up. A
+    /// directory that wasn't completely copied is removed and copied again.
+    #[allow(unused)]
+    pub fn create_hacked_base(&self, logger: &slog::Logger) -> Result<PythonPaths> {
+        let venv_base = self.venv_base.clone();
+
+        let venv_dir_s = self.venv_base.display().to_string();
+
+        let _lock = DistributionExtractLock::new(&venv_base)?;
+
+        if !venv_base.join(HACKED_BASE_COMPLETE_MARKER).exists() {
+            let dist_prefix = self.base_dir.join("python").join("install");
+
+            retry_with_backoff(
+                logger,
+                HACKED_BASE_COPY_ATTEMPTS,
+                HACKED_BASE_COPY_INITIAL_DELAY,
+                || {
+                    // Start each attempt from scratch so a partial copy from
+                    // a failed attempt doesn't make copy_dir() refuse to run.
+                    self.remove_hacked_base()?;
+
+                    let errors = copy_dir(&dist_prefix, &venv_base).with_context(|| {
+                        format!(
+                            "copying {} to {}",
+                            dist_prefix.display(),
+                            venv_base.display()
+                        )
+                    })?;
+
+                    // copy_dir() continues past errors on individual entries
+                    // and returns them instead of failing.
+                    if let Some(err) = errors.into_iter().next() {
+                        return Err(anyhow!(
+                            "copying {} to {}: {}",
+                            dist_prefix.display(),
+                            venv_base.display(),
+                            err
+                        ));
+                    }
+
+                    Ok(())
+                },
+            )?;
+
+            std::fs::write(venv_base.join(HACKED_BASE_COMPLETE_MARKER), b"")?;
+
+            let dist_prefix_s = dist_prefix.display().to_string();
+            warn!(
+                logger,
+                "copied {} to create hacked base {}", dist_prefix_s, venv_dir_s
+            );
+        }
+
+        let python_paths = resolve_python_paths(&venv_base, &self.version);
+
+        invoke_python_checked(&python_paths, &logger, &["-m", "ensurepip"])?;
+
+        prepare_hacked_distutils(logger, &self.stdlib_path.join("distutils"), &venv_base, &[])?;
+
+        Ok(python_paths)
+    }
+
+    /// Obtain the source file and compiler flags used to build `_PyImport_Inittab`.
+    ///
+    /// This allows embedders driving their own link pipeline to regenerate
+    /// the inittab object, possibly with different compiler flags.
+    pub fn inittab_build_inputs(&self) -> (&Path, &[String]) {
+        (&self.inittab_source, &self.inittab_cflags)
+    }
+
+    /// Remove the hacked base directory created by `create_hacked_base()`.
+    ///
+    /// This is a no-op if the directory doesn't exist. The directory will
+    /// be recreated on demand by subsequent calls to `create_hacked_base()`.
+    /// Removal waits on the distribution extraction lock but ignores cleanup
+    /// guards, so the directory must not be in use.
+    pub fn cleanup_hacked_base(&self) -> Result<()> {
+        let _lock = DistributionExtractLock::new(&self.venv_base)?;
+
+        self.remove_hacked_base()
+    }
+
+    /// Remove the hacked base directory without locking.
+    fn remove_hacked_base(&self) -> Result<()> {
+        if self.venv_base.exists() {
+            std::fs::remove_dir_all(&self.venv_base)
+                .with_context(|| format!("removing {}", self.venv_base.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Obtain a guard that removes the hacked base directory when dropped.
+    ///
+    /// Hold the guard for the duration of a one-shot build to avoid leaking
+    /// the directory once the build is finished. The guard should be obtained
+    /// before calling `create_hacked_base()` so the directory can't be
+    /// removed by another build's guard in between.
+    pub fn hacked_base_cleanup_guard(&self) -> Result<HackedBaseCleanupGuard> {
+        let lock_path = self.venv_base.with_file_name(format!(
+            "{}-usage-lock",
+            self.venv_base
+                .file_name()
+                .ok_or_else(|| anyhow!("unable to resolve hacked base name"))?
+                .to_string_lossy()
+        ));
+
+        let usage_lock = std::fs::File::create(&lock_path)
+            .with_context(|| format!("could not create {}", lock_path.display()))?;
+        usage_lock
+            .lock_shared()
+            .with_context(|| format!("failed to obtain lock for {}", lock_path.display()))?;
+
+        Ok(HackedBaseCleanupGuard {
+            distribution: self,
+            usage_lock,
+        })
+    }
+
+    /// Create a venv from the distribution at path.
+    #[allow(unused)]
+    pub fn create_venv(&self, logger: &slog::Logger, path: &Path) -> Result<PythonPaths> {
+        let venv_dir_s = path.display().to_string();
+
+        // This will recreate it, if it was deleted
+        let python_paths = self.create_hacked_base(&logger)?;
+
+        if path.exists() {
+            warn!(logger, "re-using {} {}", "venv", venv_dir_s);
+        } else {
+            warn!(logger, "creating {} {}", "venv", venv_dir_s);
+            invoke_python_checked(&python_paths, &logger, &["-m", "venv", venv_dir_s.as_str()])?;
+        }
+
+        Ok(resolve_python_paths(&path, &self.version))
+    }
+
+    /// Create or re-use an existing venv
+    #[allow(unused)]
+    pub fn prepare_venv(
+        &self,
+        logger: &slog::Logger,
+        venv_dir_path: &Path,
+    ) -> Result<(PythonPaths, HashMap<String, String>)> {
+        let python_paths = self.create_venv(logger, &venv_dir_path)?;
+
+        let mut extra_envs = HashMap::new();
+
+        let prefix_s = python_paths.prefix.display().to_string();
+
+        let venv_path_bin_s = python_paths.bin_dir.display().to_string();
+
+        let path_separator = if cfg!(windows) { ";" } else { ":" };
+
+        if let Ok(path) = std::env::var("PATH") {
+            extra_envs.insert(
+                "PATH".to_string(),
+                format!("{}{}{}", venv_path_bin_s, path_separator, path),
+            );
+        } else {
+            extra_envs.insert("PATH".to_string(), venv_path_bin_s);
+        }
+
+        let site_packages_s = python_paths.site_packages.display().to_string();
+        if site_packages_s.starts_with("\\\\?\\") {
+            panic!("unexpected Windows UNC path in site-packages");
+        }
+
+        extra_envs.insert("VIRTUAL_ENV".to_string(), prefix_s);
+        extra_envs.insert("PYTHONPATH".to_string(), site_packages_s);
+
+        extra_envs.insert("PYOXIDIZER".to_string(), "1".to_string());
+
+        Ok((python_paths, extra_envs))
+    }
+
+    /// Whether the distribution is capable of loading filed-based Python extension modules.
+    pub fn is_extension_module_file_loadable(&self) -> bool {
+        self.extension_module_loading
+            .contains(&"shared-library".to_string())
+    }
+
+    /// Obtain the build metadata parsed from this distribution's `PYTHON.json`.
+    pub fn build_info_debug(&self) -> BuildInfoDebug {
+        BuildInfoDebug {
+            build_info: self.build_info.clone(),
+        }
+    }
+
+    /// Generate a manifest of every file in the extracted distribution.
+    ///
+    /// Each line holds the hex SHA-256 digest of a file, two spaces, and the
+    /// file's path relative to the distribution root, sorted by path. This is
+    /// the format of `sha256sum`. Generate the manifest from a trusted
+    /// extraction before the distribution's interpreter is run, as running it
+    /// may write files such as bytecode caches.
+    pub fn generate_manifest(&self) -> Result<String> {
+        Ok(format_manifest(&file_tree_digests(&self.base_dir)?))
+    }
+
+    /// Verify the extracted distribution matches a manifest from `generate_manifest()`.
+    ///
+    /// Errors if any file was added, removed, or modified, listing every difference.
+    pub fn verify_against_manifest(&self, manifest: &Path) -> Result<()> {
+        let data = std::fs::read_to_string(manifest)
+            .with_context(|| format!("reading {}", manifest.display()))?;
+        let expected = parse_manifest(&data)
+            .with_context(|| format!("parsing manifest {}", manifest.display()))?;
+
+        let differences = manifest_differences(&expected, &file_tree_digests(&self.base_dir)?);
+
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "distribution {} does not match manifest {}:\n{}",
+                self.base_dir.display(),
+                manifest.display(),
+                differences.join("\n")
+            ))
+        }
+    }
+
+    /// Obtain the relative paths of all object files in this distribution.
+    ///
+    /// Paths are relative to the `python/` directory of the distribution and
+    /// cover object files for both the Python core and extension modules.
+    pub fn object_file_relative_paths(&self) -> BTreeSet<PathBuf> {
+        let python_path = self.base_dir.join("python");
+
+        let mut res = BTreeSet::new();
+        res.extend(self.objs_core.keys().cloned());
+
+        for variants in self.extension_modules.values() {
+            for em in variants.iter() {
+                for location in &em.object_file_data {
+                    if let DataLocation::Path(path) = location {
+                        if let Ok(rel_path) = path.strip_prefix(&python_path) {
+                            res.insert(rel_path.to_path_buf());
+                        }
+                    }
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Verify bytecode produced by a compiler is loadable by this distribution.
+    ///
+    /// A trivial module is compiled and the magic number at the start of the
+    /// resulting `.pyc` data is compared against the distribution's. A mismatch
+    /// means the compiler's interpreter is a different Python version and
+    /// bytecode it produces will fail to import at run-time.
+    pub fn verify_bytecode_magic(&self, compiler: &mut BytecodeCompiler) -> Result<()> {
+        let data = compiler.compile(
+            b"",
+            "<verify>",
+            BytecodeOptimizationLevel::Zero,
+            CompileMode::PycUncheckedHash,
+        )?;
+
+        if data.len() < 4 {
+            return Err(anyhow!("compiled bytecode is missing a header"));
+        }
+
+        let magic_number = hex::encode(&data[0..4]);
+        if magic_number != self.python_bytecode_magic_number.to_lowercase() {
+            return Err(anyhow!(
+                "Python interpreter {} has bytecode magic number {} but distribution has {}",
+                self.python_exe.display(),
+                magic_number,
+                self.python_bytecode_magic_number
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the distribution's precompiled bytecode for a module, if usable.
+    ///
+    /// The `.pyc` file must have been produced by this distribution's interpreter,
+    /// as determined by its magic number. Timestamp based `.pyc` files must also
+    /// match the modified time and size of `source`. `.pyc` files whose hash is
+    /// checked against source are never reused. Returns `None` if bytecode needs
+    /// to be compiled from source.
+    pub fn precompiled_bytecode(
+        &self,
+        source: &PythonModuleSource,
+    ) -> Result<Option<PythonModuleBytecode>> {
+        let path = match self.py_bytecode.get(&source.name) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        if data.len() < 16
+            || hex::encode(&data[0..4]) != self.python_bytecode_magic_number.to_lowercase()
+        {
+            return Ok(None);
+        }
+
+        let flags = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+
+        let usable = match flags {
+            // Modified time and source size.
+            0 => match &source.source {
+                DataLocation::Path(source_path) => {
+                    let metadata = std::fs::metadata(source_path)?;
+                    let mtime = metadata
+                        .modified()?
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_secs() as u32;
+
+                    data[8..12] == mtime.to_le_bytes()
+                        && data[12..16] == (metadata.len() as u32).to_le_bytes()
+                }
+                DataLocation::Memory(_) => false,
+            },
+            // Unchecked hash.
+            1 => true,
+            _ => false,
+        };
+
+        if !usable {
+            return Ok(None);
+        }
+
+        let mut bytecode = PythonModuleBytecode::new(
+            &source.name,
+            BytecodeOptimizationLevel::Zero,
+            source.is_package,
+            &source.cache_tag,
+            &data[16..],
+        );
+        bytecode.is_stdlib = source.is_stdlib;
+        bytecode.is_test = source.is_test;
+
+        Ok(Some(bytecode))
+    }
+
+    /// Obtain license info for the extension module with the given name.
+    ///
+    /// Returns `None` if the extension module is unknown or has no license
+    /// info, which is the case for extensions that are part of Python itself.
+    pub fn extension_licenses(&self, name: &str) -> Option<&[LicenseInfo]> {
+        if name == "python" {
+            return None;
+        }
+
+        self.license_infos.get(name).map(|li| li.as_slice())
+    }
+
+    /// Obtain license info for Python itself.
+    pub fn core_licenses(&self) -> Option<&[LicenseInfo]> {
+        self.license_infos.get("python").map(|li| li.as_slice())
+    }
+
+    /// Obtain the variants of the extension module with the given name.
+    pub fn extension_module_variants(&self, name: &str) -> Option<&PythonExtensionModuleVariants> {
+        self.extension_modules.get(name)
+    }
+
+    /// Iterate over every extension module variant and its library dependencies.
+    ///
+    /// Yields `(name, variant, link_libraries)` for each variant of each
+    /// extension module, ordered by extension module name.
+    pub fn iter_extension_module_link_requirements(
+        &self,
+    ) -> impl Iterator<Item = (&str, &PythonExtensionModule, Vec<LibraryDependency>)> {
+        self.extension_modules.iter().flat_map(|(name, variants)| {
+            variants
+                .iter()
+                .map(move |em| (name.as_str(), em, em.link_libraries.clone()))
+        })
+    }
+
+    /// Collect the licenses of everything from this distribution a builder embeds.
+    ///
+    /// The licenses of Python itself and of the builder's extension modules are
+    /// returned, deduplicated by license filename. Errors if the builder uses
+    /// a distribution for a different target.
+    pub fn collect_licenses(
+        &self,
+        builder: &StandalonePythonExecutableBuilder,
+    ) -> Result<Vec<LicenseInfo>> {
+        if builder.distribution.target_triple != self.target_triple {
+            return Err(anyhow!(
+                "builder uses a distribution for {}, not {}",
+                builder.distribution.target_triple,
+                self.target_triple
+            ));
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut res = vec![];
+
+        let names = std::iter::once("python".to_string())
+            .chain(builder.embedded_extension_module_names().into_iter());
+
+        for name in names {
+            for li in self.license_infos.get(&name).into_iter().flatten() {
+                if seen.insert(li.license_filename.clone()) {
+                    res.push(li.clone());
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Obtain the names of test packages in this distribution's standard library.
+    ///
+    /// Every package annotated as a test package is returned, including
+    /// subpackages of test packages.
+    pub fn list_test_packages(&self) -> Vec<&str> {
+        self.py_modules
+            .iter()
+            .filter(|(name, path)| is_stdlib_test_package(name) && is_package_from_path(path))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Obtain the optimizations the distribution was built with.
+    ///
+    /// e.g. `noopt` or `pgo+lto`.
+    pub fn optimizations(&self) -> &str {
+        &self.optimizations
+    }
+
+    /// Obtain the names of standard library test packages as annotated by the distribution.
+    ///
+    /// These are the top-level test packages listed in `PYTHON.json`.
+    /// Subpackages aren't listed. See `list_test_packages()` for every test
+    /// package present in the standard library.
+    pub fn stdlib_test_packages(&self) -> &[String] {
+        &self.stdlib_test_packages
+    }
+
+    /// Ensure object files in this distribution match its target architecture.
+    ///
+    /// Object files for the Python core and extension modules are examined.
+    /// Errors naming the first object file compiled for a different
+    /// architecture. Files whose architecture can't be determined are ignored.
+    pub fn validate_object_file_architectures(&self) -> Result<()> {
+        let expected = match self.target_triple.split('-').next() {
+            Some("i386") | Some("i586") | Some("i686") => "i686",
+            Some(arch) if arch.starts_with("arm") => "arm",
+            Some(arch) => arch,
+            None => return Ok(()),
+        };
+
+        let mut locations = self
+            .objs_core
+            .values()
+            .map(|path| DataLocation::Path(path.clone()))
+            .collect::<Vec<_>>();
+
+        for variants in self.extension_modules.values() {
+            for em in variants.iter() {
+                locations.extend(em.object_file_data.iter().cloned());
+            }
+        }
+
+        for location in locations {
+            let description = match &location {
+                DataLocation::Path(path) => path.display().to_string(),
+                DataLocation::Memory(_) => "<in-memory object file>".to_string(),
+            };
+
+            let data = location
+                .resolve()
+                .with_context(|| format!("reading {}", description))?;
+
+            if let Some(arch) = object_file_architecture(&data)
+                .with_context(|| format!("parsing {}", description))?
+            {
+                if arch != expected {
+                    return Err(anyhow!(
+                        "object file {} is for the wrong architecture: {} (expected {} for {})",
+                        description,
+                        arch,
+                        expected,
+                        self.target_triple
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Determine how an extension module can be loaded by this distribution.
+    ///
+    /// An extension is builtin-capable if a variant has object files. It is
+    /// shared library capable if a variant has a shared library and the
+    /// distribution is capable of loading shared library extension modules.
+    pub fn extension_loading_capability(&self, name: &str) -> ExtensionLoadingCapability {
+        let variants = match self.extension_modules.get(name) {
+            Some(variants) => variants,
+            None => return ExtensionLoadingCapability::Unavailable,
+        };
+
+        let builtin = variants.iter().any(|em| !em.object_file_data.is_empty());
+        let shared_library = self.is_extension_module_file_loadable()
+            && variants.iter().any(|em| em.shared_library.is_some());
+
+        match (builtin, shared_library) {
+            (true, true) => ExtensionLoadingCapability::BuiltinOrSharedLibrary,
+            (true, false) => ExtensionLoadingCapability::BuiltinOnly,
+            (false, true) => ExtensionLoadingCapability::SharedLibraryOnly,
+            (false, false) => ExtensionLoadingCapability::Unavailable,
+        }
+    }
+
+    /// Ensure the distribution provides the named extension modules.
+    ///
+    /// Errors listing every extension module not present in the distribution.
+    pub fn require_extensions(&self, names: &[&str]) -> Result<()> {
+        let missing = names
+            .iter()
+            .filter(|name| !self.extension_modules.contains_key(**name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Python distribution is missing required extension modules: {}",
+                missing.join(", ")
+            ))
+        }
+    }
+
+    /// Obtain the Rust target triples this distribution can produce binaries for.
+    pub fn compatible_target_triples(&self) -> Vec<String> {
+        compatible_target_triples(&self.target_triple, &self.crt_features)
+    }
+
+    /// Describe the available variants of an extension module.
+    ///
+    /// Returns an empty `Vec` if the extension module is not known.
+    pub fn describe_variants(&self, module: &str) -> Vec<VariantDescription> {
+        if let Some(variants) = self.extension_modules.get(module) {
+            variants.describe()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Obtain the suffixes of Python module files in this distribution.
+    ///
+    /// This is a borrowing alternative to `python_module_suffixes()`.
+    pub fn module_suffixes(&self) -> &PythonModuleSuffixes {
+        &self.module_suffixes
+    }
+
+    /// Obtain package resources in this distribution, excluding those matching globs.
+    ///
+    /// Patterns are matched against `<leaf_package>/<relative_name>`, e.g.
+    /// `json.tests/data.json`. `*` also matches path separators, so `*.json`
+    /// excludes every resource with a `.json` extension.
+    pub fn resource_datas_excluding(
+        &self,
+        patterns: &[&str],
+    ) -> Result<Vec<PythonPackageResource>> {
+        let patterns = patterns
+            .iter()
+            .map(|p| glob::Pattern::new(p).with_context(|| format!("parsing glob pattern {}", p)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(self
+            .resource_datas()?
+            .into_iter()
+            .filter(|resource| {
+                let name = format!("{}/{}", resource.leaf_package, resource.relative_name);
+
+                !patterns.iter().any(|pattern| pattern.matches(&name))
+            })
+            .collect())
+    }
+}
+
+impl PythonDistribution for StandaloneDistribution {
+    fn clone_box(&self) -> Box<dyn PythonDistribution> {
+        Box::new(self.clone())
+    }
+
+    fn python_exe_path(&self) -> &Path {
+        &self.python_exe
+    }
+
+    fn python_major_minor_version(&self) -> String {
+        self.version[0..3].to_string()
+    }
+
+    fn cache_tag(&self) -> &str {
+        &self.cache_tag
+    }
+
+    fn python_module_suffixes(&self) -> Result<PythonModuleSuffixes> {
+        Ok(self.module_suffixes.clone())
+    }
+
+    fn create_bytecode_compiler(&self) -> Result<BytecodeCompiler> {
+        // Bytecode produced by an interpreter not matching the distribution
+        // won't be loadable at run-time. So verify the interpreter is sane
+        // before compiling anything.
+        let output = std::process::Command::new(&self.python_exe)
+            .args(&["-c", "import sys; print(sys.implementation.cache_tag)"])
+            .output()
+            .with_context(|| format!("running {}", self.python_exe.display()))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "unable to resolve cache tag of {}",
+                self.python_exe.display()
+            ));
+        }
+
+        let cache_tag = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if cache_tag != self.cache_tag {
+            return Err(anyhow!(
+                "Python interpreter {} has cache tag {} but distribution has {}",
+                self.python_exe.display(),
+                cache_tag,
+                self.cache_tag
+            ));
+        }
+
+        let mut compiler = BytecodeCompiler::new(&self.python_exe)?;
+        self.verify_bytecode_magic(&mut compiler)?;
+
+        Ok(compiler)
+    }
+
+    fn create_packaging_policy(&self) -> Result<PythonPackagingPolicy> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        for triple in LINUX_TARGET_TRIPLES.iter() {
+            for ext in BROKEN_EXTENSIONS_LINUX.iter() {
+                policy.register_broken_extension(triple, ext);
+            }
+        }
+
+        for triple in MACOS_TARGET_TRIPLES.iter() {
+            for ext in BROKEN_EXTENSIONS_MACOS.iter() {
+                policy.register_broken_extension(triple, ext);
+            }
+        }
+
+        Ok(policy)
+    }
+
+    fn as_python_executable_builder(
+        &self,
+        logger: &slog::Logger,
+        host_triple: &str,
+        target_triple: &str,
+        name: &str,
+        libpython_link_mode: BinaryLibpythonLinkMode,
+        policy: &PythonPackagingPolicy,
+        config: &EmbeddedPythonConfig,
+    ) -> Result<Box<dyn PythonBinaryBuilder>> {
+        if self.resources_only {
+            return Err(anyhow!(
+                "Python distribution was only analyzed for resources and cannot build binaries"
+            ));
+        }
+
+        if !self
+            .compatible_target_triples()
+            .iter()
+            .any(|x| x == target_triple)
+        {
+            validate_target_triple(target_triple)?;
+
+            return Err(anyhow!(
+                "Python distribution is for {} but you requested {}",
+                self.target_triple,
+                target_triple
+            ));
+        }
+
+        let python_exe = self.python_exe.clone();
+
+        let (supports_static_libpython, supports_dynamic_libpython) =
+            libpython_link_support(&self.target_triple, self.libpython_shared_library.is_some());
+
+        let link_mode = match libpython_link_mode {
+            BinaryLibpythonLinkMode::Default => {
+                if supports_static_libpython {
+                    LibpythonLinkMode::Static
+                } else if supports_dynamic_libpython {
+                    LibpythonLinkMode::Dynamic
+                } else {
+                    return Err(anyhow!("no link modes supported; please report this bug"));
+                }
+            }
+            BinaryLibpythonLinkMode::Static => {
+                if !supports_static_libpython {
+                    return Err(anyhow!(
+                        "Python distribution does not support statically linking libpython"
+                    ));
+                }
+
+                LibpythonLinkMode::Static
+            }
+            BinaryLibpythonLinkMode::Dynamic => {
+                if !supports_dynamic_libpython {
+                    return Err(anyhow!(
+                        "Python distribution does not support dynamically linking libpython"
+                    ));
+                }
+
+                LibpythonLinkMode::Dynamic
+            }
+        };
+
+        // Loading from memory is only supported on Windows where symbols are
+        // declspec(dllexport) and the distribution is capable of loading
+        // shared library extensions.
+        let supports_in_memory_dynamically_linked_extension_loading = target_triple
+            .contains("pc-windows")
+            && self.python_symbol_visibility == "dllexport"
+            && self
+                .extension_module_loading
+                .contains(&"shared-library".to_string());
+
+        let mut builder = Box::new(StandalonePythonExecutableBuilder {
+            host_triple: host_triple.to_string(),
+            target_triple: target_triple.to_string(),
+            exe_name: name.to_string(),
+            // TODO can we avoid this clone()?
+            distribution: Arc::new(Box::new(self.clone())),
+            link_mode,
+            supports_in_memory_dynamically_linked_extension_loading,
+            packaging_policy: policy.clone(),
+            resources: PrePackagedResources::new(policy.get_resources_policy(), &self.cache_tag),
+            config: config.clone(),
+            python_exe,
+            excluded_object_files: BTreeSet::new(),
+            artifact_name_prefix: "".to_string(),
+            packed_resources_sidecar: None,
+            extra_linker_args: BTreeMap::new(),
+            split_debug_info: false,
+            link_description_path: None,
+            build_temp_dir: None,
+            named_configs: BTreeMap::new(),
+            config_selector_env: DEFAULT_CONFIG_SELECTOR_ENV.to_string(),
+            resource_compression: None,
+            policy_tallies: PolicyTallies::default(),
+            unresolvable_resource_policy: UnresolvableResourcePolicy::default(),
+            warnings: BuildWarnings::default(),
+        });
+
+        if policy.include_distribution_stdlib() {
+            builder.add_full_stdlib(logger, None)?;
+        } else {
+            builder.add_bootstrap_stdlib(logger)?;
+        }
+
+        Ok(builder)
+    }
+
+    fn iter_extension_modules<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a PythonExtensionModule> + 'a> {
+        Box::new(
+            self.extension_modules
+                .iter()
+                .map(|(_, exts)| exts.iter())
+                .flatten(),
+        )
+    }
+
+    fn extension_module_count(&self) -> usize {
+        self.extension_modules.len()
+    }
+
+    fn default_builtin_extension_count(&self) -> usize {
+        self.extension_modules
+            .values()
+            .filter(|variants| variants.iter().any(|e| e.builtin_default))
+            .count()
+    }
+
+    fn source_modules(&self) -> Result<Vec<PythonModuleSource>> {
+        self.py_modules
+            .iter()
+            .map(|(name, path)| {
+                let is_package = is_package_from_path(&path);
+
+                Ok(PythonModuleSource {
+                    name: name.clone(),
+                    source: DataLocation::Path(path.clone()),
+                    is_package,
+                    cache_tag: self.cache_tag.clone(),
+                    is_stdlib: true,
+                    is_test: is_stdlib_test_package(name),
+                })
+            })
+            .collect()
+    }
+
+    fn resource_datas(&self) -> Result<Vec<PythonPackageResource>> {
+        let mut res = Vec::new();
+
+        for (package, inner) in self.resources.iter() {
+            for (name, path) in inner.iter() {
+                res.push(PythonPackageResource {
+                    leaf_package: package.clone(),
+                    relative_name: name.clone(),
+                    data: DataLocation::Path(path.clone()),
+                    is_stdlib: true,
+                    is_test: is_stdlib_test_package(&package),
+                });
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Ensure pip is available to run in the distribution.
+    fn ensure_pip(&self, logger: &slog::Logger) -> Result<PathBuf> {
+        let dist_prefix = self.base_dir.join("python").join("install");
+        let python_paths = resolve_python_paths(&dist_prefix, &self.version);
+
+        let pip_path = python_paths.bin_dir.join(PIP_EXE_BASENAME);
+
+        if !pip_path.exists() {
+            warn!(logger, "{} doesnt exist", pip_path.display().to_string());
+            invoke_python_checked(&python_paths, &logger, &["-m", "ensurepip"])?;
+        }
+
+        Ok(pip_path)
+    }
+
+    fn resolve_distutils(
+        &self,
+        logger: &slog::Logger,
+        libpython_link_mode: LibpythonLinkMode,
+        dest_dir: &Path,
+        extra_python_paths: &[&Path],
+    ) -> Result<HashMap<String, String>> {
+        match libpython_link_mode {
+            // We need to patch distutils if the distribution is statically linked.
+            LibpythonLinkMode::Static => prepare_hacked_distutils(
+                logger,
+                &self.stdlib_path.join("distutils"),
+                dest_dir,
+                extra_python_paths,
+            ),
+            LibpythonLinkMode::Dynamic => Ok(HashMap::new()),
+        }
+    }
+
+    fn filter_compatible_python_resources(
+        &self,
+        logger: &slog::Logger,
+        warnings: &mut BuildWarnings,
+        resources: &[PythonResource],
+    ) -> Result<Vec<PythonResource>> {
+        Ok(resources
+            .iter()
+            .filter(|resource| match resource {
+                // Extension modules defined as shared libraries are only compatible
+                // with some configurations.
+                PythonResource::ExtensionModuleDynamicLibrary { .. } => {
+                    if self.is_extension_module_file_loadable() {
+                        true
+                    } else {
+                        warnings.warn(logger, &format!(
+                            "ignoring extension module {} because it isn't loadable for the target configuration",
+                            resource.full_name()));
+                        false
+                    }
+                }
+
+                // Only look at the raw object files if the distribution produces
+                // them.
+                // TODO have PythonDistribution expose API to determine this.
+                PythonResource::ExtensionModuleStaticallyLinked(_) =>
+                    self.link_mode == StandaloneDistributionLinkMode::Static,
+
+                PythonResource::ModuleSource { .. } => true,
+                PythonResource::ModuleBytecodeRequest { .. } => true,
+                PythonResource::ModuleBytecode { .. } => true,
+                PythonResource::Resource { .. } => true,
+                PythonResource::DistributionResource(_) => true,
+                PythonResource::EggFile(_) => false,
+                PythonResource::PathExtension(_) => false,
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+/// A self-contained Python executable before it is compiled.
+#[derive(Clone, Debug)]
+pub struct StandalonePythonExecutableBuilder {
+    /// The target triple we are running on.
+    host_triple: String,
+
+    /// The target triple we are building for.
+    target_triple: String,
+
+    /// The name of the executable to build.
+    exe_name: String,
+
+    /// The Python distribution being used to build this executable.
+    distribution: Arc<Box<StandaloneDistribution>>,
+
+    /// How libpython should be linked.
+    link_mode: LibpythonLinkMode,
+
+    /// Whether the built binary is capable of loading dynamically linked
+    /// extension modules from memory.
+    supports_in_memory_dynamically_linked_extension_loading: bool,
+
+    /// Policy to apply to added resources.
+    packaging_policy: PythonPackagingPolicy,
+
+    /// Python resources to be embedded in the binary.
+    resources: PrePackagedResources,
+
+    /// Configuration of the embedded Python interpreter.
+    config: EmbeddedPythonConfig,
+
+    /// Path to python executable that can be invoked at build time.
+    python_exe: PathBuf,
+
+    /// Object files to exclude from linking libpython.
+    ///
+    /// Paths are relative to the Python distribution.
+    excluded_object_files: BTreeSet<PathBuf>,
+
+    /// Prefix to apply to the names of generated build artifacts.
+    artifact_name_prefix: String,
+
+    /// Filename to install packed resources to instead of embedding them.
+    packed_resources_sidecar: Option<String>,
+
+    /// Extra linker arguments, keyed by target triple.
+    extra_linker_args: BTreeMap<String, Vec<String>>,
+
+    /// Whether to produce debug info that can be split from the binary.
+    split_debug_info: bool,
+
+    /// File to write a description of how libpython is linked to.
+    link_description_path: Option<PathBuf>,
+
+    /// Directory under which temporary build directories are created.
+    build_temp_dir: Option<PathBuf>,
+
+    /// Additional interpreter configurations selectable at run-time, keyed by name.
+    named_configs: BTreeMap<String, EmbeddedPythonConfig>,
+
+    /// Environment variable selecting a named configuration at run-time.
+    config_selector_env: String,
+
+    /// How to compress individual resources, if at all.
+    resource_compression: Option<ResourceCompressionOptions>,
+
+    /// Counts of distribution resources the packaging policy included or excluded.
+    policy_tallies: PolicyTallies,
+
+    /// How to handle resources whose data no longer exists when packaging.
+    unresolvable_resource_policy: UnresolvableResourcePolicy,
+
+    /// Warnings recorded while collecting resources.
+    warnings: BuildWarnings,
+}
+
+impl StandalonePythonExecutableBuilder {
+    /// Retain only resources for which a predicate returns true.
+    ///
+    /// Returns the number of resources removed.
+    pub fn retain_resources<F>(&mut self, f: F) -> Result<usize>
+    where
+        F: FnMut(&str, &PrePackagedResource) -> bool,
+    {
+        self.resources.retain_resources(f)
+    }
+
+    /// Obtain the raw allocator the built binary will actually use.
+    ///
+    /// Jemalloc isn't available on Windows MSVC targets. If it is requested
+    /// for such a target, the system allocator is used instead.
+    pub fn effective_raw_allocator(&self) -> RawAllocator {
+        self.effective_config_raw_allocator(&self.config)
+    }
+
+    /// Obtain the raw allocator the built binary will use for a configuration.
+    fn effective_config_raw_allocator(&self, config: &EmbeddedPythonConfig) -> RawAllocator {
+        if config.raw_allocator == RawAllocator::Jemalloc
+            && self.target_triple.contains("pc-windows-msvc")
+        {
+            RawAllocator::System
+        } else {
+            config.raw_allocator.clone()
+        }
+    }
+
+    /// Obtain the named configurations with their effective raw allocators.
+    ///
+    /// A warning is recorded for each configuration whose allocator is replaced.
+    fn effective_named_configs(
+        &self,
+        logger: &slog::Logger,
+        warnings: &mut BuildWarnings,
+    ) -> BTreeMap<String, EmbeddedPythonConfig> {
+        let mut named_configs = self.named_configs.clone();
+
+        for (name, config) in named_configs.iter_mut() {
+            let raw_allocator = self.effective_config_raw_allocator(config);
+            if raw_allocator != config.raw_allocator {
+                warnings.warn(
+                    logger,
+                    &format!(
+                        "jemalloc is not supported on {}; using the system allocator for config {}",
+                        self.target_triple, name
+                    ),
+                );
+                config.raw_allocator = raw_allocator;
+            }
+        }
+
+        named_configs
+    }
+
+    /// Add precompiled module bytecode to a location determined by the resources policy.
+    fn add_precompiled_module_bytecode(&mut self, module: &PythonModuleBytecode) -> Result<()> {
+        let location = match self.packaging_policy.get_resources_policy() {
+            PythonResourcesPolicy::InMemoryOnly
+            | PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => {
+                ConcreteResourceLocation::InMemory
+            }
+            PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => {
+                ConcreteResourceLocation::RelativePath(prefix.clone())
+            }
+        };
+
+        self.resources.add_python_module_bytecode(module, &location)
+    }
+
+    /// Obtain the names of extension modules that are part of the built binary.
+    ///
+    /// This includes builtin extension modules and extension modules added as
+    /// resources.
+    fn embedded_extension_module_names(&self) -> BTreeSet<String> {
+        let mut names = self
+            .resources
+            .builtin_extension_module_names()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        names.extend(
+            self.resources
+                .iter_resources()
+                .filter_map(|(name, resource)| match resource.flavor {
+                    ResourceFlavor::BuiltinExtensionModule | ResourceFlavor::Extension => {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                }),
+        );
+
+        names
+    }
+
+    /// Obtain license info for Python and the extension modules in this builder.
+    ///
+    /// Unlike the distribution's license info, extension modules that aren't
+    /// part of the built binary are not included.
+    pub fn license_infos(&self) -> BTreeMap<String, Vec<LicenseInfo>> {
+        let mut names = self.embedded_extension_module_names();
+        names.insert("python".to_string());
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                self.distribution
+                    .license_infos
+                    .get(&name)
+                    .map(|li| (name, li.clone()))
+            })
+            .collect()
+    }
+
+    /// Write a human-readable file containing license texts of embedded components.
+    ///
+    /// Each component from `license_infos()` gets a section header naming it and
+    /// its SPDX license identifiers. License texts shared by multiple components
+    /// are only written once and later sections refer back to them.
+    pub fn write_combined_notice(&self, path: &Path) -> Result<()> {
+        let license_infos = self.license_infos();
+        let separator = "=".repeat(80);
+        let mut seen: BTreeMap<&str, &str> = BTreeMap::new();
+        let mut sections = Vec::new();
+
+        for (name, infos) in &license_infos {
+            for li in infos {
+                let mut lines = vec![
+                    separator.clone(),
+                    name.clone(),
+                    format!("Licenses: {}", li.licenses.join(", ")),
+                    separator.clone(),
+                    "".to_string(),
+                ];
+
+                let text = li.license_text.trim();
+
+                if let Some(previous) = seen.get(text) {
+                    lines.push(format!("See license text for {} above.", previous));
+                } else {
+                    lines.push(text.to_string());
+                    seen.insert(text, name.as_str());
+                }
+
+                sections.push(lines.join("\n"));
+            }
+        }
+
+        std::fs::write(path, sections.join("\n\n") + "\n")
+            .with_context(|| format!("writing {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Remove all added resources, restoring the distribution's baseline.
+    ///
+    /// Every resource and extension module is removed, including those that
+    /// came from the distribution. The standard library is then added again
+    /// as it was when the builder was created: in full if the packaging
+    /// policy includes the distribution's standard library, otherwise only
+    /// the parts required to run the interpreter.
+    ///
+    /// Builder settings, such as the packaging policy, interpreter
+    /// configuration, resource priority, and libraries marked as host
+    /// provided, are retained.
+    /// Policy tallies are reset and then reflect the re-added baseline.
+    pub fn clear_added_resources(&mut self, logger: &slog::Logger) -> Result<()> {
+        self.resources.clear()?;
+        self.policy_tallies = PolicyTallies::default();
+
+        if self.packaging_policy.include_distribution_stdlib() {
+            self.add_full_stdlib(logger, None)
+        } else {
+            self.add_bootstrap_stdlib(logger)
+        }
+    }
+
+    /// Warn about extension modules excluded because they are broken on the target.
+    fn warn_broken_extension_modules(&mut self, logger: &slog::Logger, names: &[String]) {
+        for name in names {
+            self.warnings.warn(
+                logger,
+                &format!(
+                    "excluding extension module {} because it is broken on {}",
+                    name, self.target_triple
+                ),
+            );
+        }
+    }
+
+    /// Add only the parts of the standard library required to run the interpreter.
+    ///
+    /// This adds minimally required extension modules, the Python modules
+    /// the distribution's interpreter imports during initialization, and the
+    /// packages in `BOOTSTRAP_PACKAGES`.
+    pub fn add_bootstrap_stdlib(&mut self, logger: &slog::Logger) -> Result<()> {
+        let mut policy = self.packaging_policy.clone();
+        policy.set_extension_module_filter(ExtensionModuleFilter::Minimal);
+
+        let (extensions, broken) = policy.resolve_python_extension_modules(
+            self.distribution.extension_modules.values(),
+            &self.target_triple,
+        )?;
+        self.warn_broken_extension_modules(logger, &broken);
+
+        for ext in extensions {
+            self.add_distribution_extension_module(&ext)?;
+        }
+
+        let startup_modules = resolve_startup_module_names(&self.python_exe)?;
+
+        for source in self.distribution.source_modules()? {
+            if !startup_modules.contains(&source.name)
+                && !BOOTSTRAP_PACKAGES.iter().any(|name| {
+                    source.name == *name || source.name.starts_with(&format!("{}.", name))
+                })
+            {
+                continue;
+            }
+
+            if policy.filter_python_resource(&source.clone().into()) {
+                self.add_module_source(&source)?;
+            }
+
+            self.add_module_bytecode(&source.as_bytecode_module(BytecodeOptimizationLevel::Zero))?;
+        }
+
+        Ok(())
+    }
+
+    /// Build a Python library suitable for linking.
+    ///
+    /// This will take the underlying distribution, resources, and
+    /// configuration and produce a new executable binary.
+    fn resolve_python_linking_info(
+        &self,
+        logger: &slog::Logger,
+        opt_level: &str,
+        resources: &EmbeddedPythonResources,
+    ) -> Result<PythonLinkingInfo> {
+        let libpythonxy_filename;
+        let mut cargo_metadata: Vec<String> = Vec::new();
+        let mut linker_args = Vec::new();
+        let libpythonxy_data;
+        let libpython_filename: Option<PathBuf>;
+        let libpyembeddedconfig_data: Option<Vec<u8>>;
+        let libpyembeddedconfig_filename: Option<PathBuf>;
+        let mut libpython_info = None;
+
+        match self.link_mode {
+            LibpythonLinkMode::Static => {
+                let temp_dir = new_temp_dir_in(self.build_temp_dir.as_deref(), "build-exe")?;
+                let temp_dir_path = temp_dir.path();
+
+                warn!(
+                    logger,
+                    "generating custom link library containing Python..."
+                );
+                let library_info = link_libpython(
+                    logger,
+                    &self.distribution,
+                    resources,
+                    &temp_dir_path,
+                    &self.host_triple,
+                    &self.target_triple,
+                    opt_level,
+                    &self.excluded_object_files,
+                    &self.artifact_name_prefix,
+                    self.split_debug_info,
+                    self.build_temp_dir.as_deref(),
+                )?;
+
+                libpythonxy_filename =
+                    PathBuf::from(library_info.libpython_path.file_name().unwrap());
+                cargo_metadata.extend(library_info.cargo_metadata.clone());
+                linker_args.extend(library_info.linker_args.clone());
+
+                libpythonxy_data = std::fs::read(&library_info.libpython_path)?;
+                libpython_filename = None;
+                libpyembeddedconfig_filename = Some(PathBuf::from(
+                    library_info.libpyembeddedconfig_path.file_name().unwrap(),
+                ));
+                libpyembeddedconfig_data =
+                    Some(std::fs::read(&library_info.libpyembeddedconfig_path)?);
+                libpython_info = Some(library_info);
+            }
+
+            LibpythonLinkMode::Dynamic => {
+                libpythonxy_filename =
+                    PathBuf::from(format!("{}pythonXY.lib", self.artifact_name_prefix));
+                libpythonxy_data = Vec::new();
+                libpython_filename = self.distribution.libpython_shared_library.clone();
+                libpyembeddedconfig_filename = None;
+                libpyembeddedconfig_data = None;
+            }
+        }
+
+        if let Some(args) = self.extra_linker_args.get(&self.target_triple) {
+            linker_args.extend(args.iter().cloned());
+        }
+
+        if self.split_debug_info {
+            linker_args.extend(
+                split_debug_info_linker_args(&self.target_triple)
+                    .into_iter()
+                    .map(|arg| arg.to_string()),
+            );
+        }
+
+        if let (Some(path), Some(info)) = (&self.link_description_path, &libpython_info) {
+            warn!(logger, "writing link description to {}", path.display());
+            std::fs::write(path, info.describe_link(&cargo_metadata, &linker_args))
+                .with_context(|| format!("writing {}", path.display()))?;
+        }
+
+        Ok(PythonLinkingInfo {
+            libpythonxy_filename,
+            libpythonxy_data,
+            libpython_filename,
+            libpyembeddedconfig_filename,
+            libpyembeddedconfig_data,
+            cargo_metadata,
+            linker_args,
+        })
+    }
+}
+
+impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
+    fn clone_box(&self) -> Box<dyn PythonBinaryBuilder> {
+        Box::new(self.clone())
+    }
+
+    fn name(&self) -> String {
+        self.exe_name.clone()
+    }
+
+    fn libpython_link_mode(&self) -> LibpythonLinkMode {
+        self.link_mode
+    }
+
+    fn cache_tag(&self) -> &str {
+        self.distribution.cache_tag()
+    }
+
+    fn python_packaging_policy(&self) -> &PythonPackagingPolicy {
+        &self.packaging_policy
+    }
+
+    fn effective_policy_report(&self) -> PolicyReport {
+        PolicyReport {
+            extension_module_filter: self.packaging_policy.extension_module_filter().clone(),
+            resources_policy: self.packaging_policy.get_resources_policy().clone(),
+            broken_extensions: self
+                .packaging_policy
+                .broken_extensions(&self.target_triple)
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect(),
+            tallies: self.policy_tallies.clone(),
+        }
+    }
+
+    fn verify_source_bytecode_consistency(&self, logger: &slog::Logger) -> Result<Vec<String>> {
+        let mismatches = self
+            .resources
+            .find_source_bytecode_mismatches(&self.python_exe)?;
+
+        for mismatch in &mismatches {
+            warn!(logger, "inconsistent module: {}", mismatch);
+        }
+
+        Ok(mismatches)
+    }
+
+    fn python_exe_path(&self) -> &Path {
+        &self.python_exe
+    }
+
+    fn distribution_optimizations(&self) -> &str {
+        self.distribution.optimizations()
+    }
+
+    fn iter_resources<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a> {
+        Box::new(self.resources.iter_resources())
+    }
+
+    fn is_embedded_package(&self, name: &str) -> Option<bool> {
+        if self
+            .resources
+            .builtin_extension_module_names()
+            .any(|n| n == name)
+        {
+            return Some(false);
+        }
+
+        self.resources
+            .iter_resources()
+            .find(|(n, _)| n.as_str() == name)
+            .and_then(|(_, resource)| match resource.flavor {
+                ResourceFlavor::Module
+                | ResourceFlavor::BuiltinExtensionModule
+                | ResourceFlavor::Extension => Some(resource.is_package),
+                _ => None,
+            })
+    }
+
+    fn exported_symbols(&self) -> Vec<String> {
+        let mut symbols = self
+            .resources
+            .builtin_extensions()
+            .into_iter()
+            .map(|(_, init_fn)| init_fn)
+            .collect::<BTreeSet<_>>();
+
+        // Symbols with hidden visibility aren't exported.
+        let visibility = self.distribution.python_symbol_visibility.as_str();
+        if visibility == "dllexport" || visibility == "global-default" {
+            symbols.extend(LIBPYTHON_EMBEDDING_SYMBOLS.iter().map(|s| s.to_string()));
+        }
+
+        symbols.into_iter().collect()
+    }
+
+    fn builtin_extension_module_names<'a>(&'a self) -> Box<dyn Iterator<Item = &'a String> + 'a> {
+        Box::new(self.resources.builtin_extension_module_names())
+    }
+
+    fn pip_install(
+        &mut self,
+        logger: &slog::Logger,
+        verbose: bool,
+        install_args: &[String],
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PythonResource>> {
+        pip_install(
+            logger,
+            &mut self.warnings,
+            &**self.distribution,
+            self.link_mode,
+            verbose,
+            install_args,
+            extra_envs,
+        )
+    }
+
+    fn read_package_root(
+        &mut self,
+        logger: &slog::Logger,
+        path: &Path,
+        packages: &[String],
+    ) -> Result<Vec<PythonResource>> {
+        Ok(find_resources(
+            &logger,
+            &mut self.warnings,
+            &**self.distribution,
+            path,
+            None,
+        )?
+        .iter()
+        .filter_map(|x| {
+            if x.is_in_packages(packages) {
+                Some(x.clone())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>())
+    }
+
+    fn read_package_tarball(
+        &mut self,
+        logger: &slog::Logger,
+        path: &Path,
+        packages: &[String],
+    ) -> Result<Vec<PythonResource>> {
+        Ok(
+            find_resources_in_tarball(&logger, &mut self.warnings, &**self.distribution, path)?
+                .into_iter()
+                .filter(|x| x.is_in_packages(packages))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn read_virtualenv(
+        &mut self,
+        logger: &slog::Logger,
+        path: &Path,
+    ) -> Result<Vec<PythonResource>> {
+        read_virtualenv(logger, &mut self.warnings, &**self.distribution, path)
+    }
+
+    fn setup_py_install(
+        &mut self,
+        logger: &slog::Logger,
+        package_path: &Path,
+        verbose: bool,
+        extra_envs: &HashMap<String, String>,
+        extra_global_arguments: &[String],
+    ) -> Result<Vec<PythonResource>> {
+        setup_py_install(
+            logger,
+            &mut self.warnings,
+            &**self.distribution,
+            self.link_mode,
+            package_path,
+            verbose,
+            extra_envs,
+            extra_global_arguments,
+        )
+    }
+
+    fn add_in_memory_module_source(&mut self, module: &PythonModuleSource) -> Result<()> {
+        self.resources
+            .add_python_module_source(module, &ConcreteResourceLocation::InMemory)
+    }
+
+    fn add_relative_path_module_source(
+        &mut self,
+        prefix: &str,
+        module: &PythonModuleSource,
+    ) -> Result<()> {
+        self.resources.add_python_module_source(
+            module,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        )
+    }
+
+    fn add_in_memory_module_bytecode(
+        &mut self,
+        module: &PythonModuleBytecodeFromSource,
+    ) -> Result<()> {
+        self.resources
+            .add_python_module_bytecode_from_source(module, &ConcreteResourceLocation::InMemory)
+    }
+
+    fn add_relative_path_module_bytecode(
+        &mut self,
+        prefix: &str,
+        module: &PythonModuleBytecodeFromSource,
+    ) -> Result<()> {
+        self.resources.add_python_module_bytecode_from_source(
+            module,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        )
+    }
+
+    fn add_in_memory_package_resource(&mut self, resource: &PythonPackageResource) -> Result<()> {
+        self.resources
+            .add_python_package_resource(resource, &ConcreteResourceLocation::InMemory)
+    }
+
+    fn add_relative_path_package_resource(
+        &mut self,
+        prefix: &str,
+        resource: &PythonPackageResource,
+    ) -> Result<()> {
+        self.resources.add_python_package_resource(
+            resource,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        )
+    }
+
+    fn add_in_memory_package_distribution_resource(
+        &mut self,
+        resource: &PythonPackageDistributionResource,
+    ) -> Result<()> {
+        self.resources
+            .add_python_package_distribution_resource(resource, &ConcreteResourceLocation::InMemory)
+    }
+
+    fn add_relative_path_package_distribution_resource(
+        &mut self,
+        prefix: &str,
+        resource: &PythonPackageDistributionResource,
+    ) -> Result<()> {
+        self.resources.add_python_package_distribution_resource(
+            resource,
+            &ConcreteResourceLocation::RelativePath(prefix.to_string()),
+        )
+    }
+
+    fn add_builtin_distribution_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        self.resources
+            .add_builtin_distribution_extension_module(&extension_module)
+    }
+
+    fn add_in_memory_distribution_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        if !self.supports_in_memory_dynamically_linked_extension_loading {
+            return Err(anyhow!(
+                "loading extension modules from memory not supported by this build configuration"
+            ));
+        }
+
+        self.resources
+            .add_in_memory_distribution_extension_module(extension_module)
+    }
+
+    fn add_relative_path_distribution_extension_module(
+        &mut self,
+        prefix: &str,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        if self.distribution.is_extension_module_file_loadable() {
+            self.resources
+                .add_relative_path_distribution_extension_module(prefix, extension_module)
+        } else {
+            Err(anyhow!(
+                "loading extension modules from files not supported by this build configuration"
+            ))
+        }
+    }
+
+    fn add_distribution_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        // Distribution extensions are special in that we allow them to be
+        // builtin extensions, even if it violates the resources policy that prohibits
+        // memory loading.
+
+        // Builtins always get added as such.
+        if extension_module.builtin_default {
+            return self.add_builtin_distribution_extension_module(&extension_module);
+        }
+
+        match self.packaging_policy.get_resources_policy().clone() {
+            PythonResourcesPolicy::InMemoryOnly => match self.link_mode {
+                LibpythonLinkMode::Static => {
+                    self.add_builtin_distribution_extension_module(&extension_module)
+                }
+                LibpythonLinkMode::Dynamic => {
+                    self.add_in_memory_distribution_extension_module(&extension_module)
+                }
+            },
+            PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => match self.link_mode {
+                LibpythonLinkMode::Static => {
+                    self.add_builtin_distribution_extension_module(&extension_module)
+                }
+                LibpythonLinkMode::Dynamic => {
+                    self.add_relative_path_distribution_extension_module(&prefix, &extension_module)
+                }
+            },
+            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(prefix) => {
+                match self.link_mode {
+                    LibpythonLinkMode::Static => {
+                        self.add_builtin_distribution_extension_module(&extension_module)
+                    }
+                    LibpythonLinkMode::Dynamic => {
+                        // Try in-memory and fall back to file-based if that fails.
+                        let mut res =
+                            self.add_in_memory_distribution_extension_module(&extension_module);
+
+                        if res.is_err() {
+                            res = self.add_relative_path_distribution_extension_module(
+                                &prefix,
+                                &extension_module,
+                            )
+                        }
+
+                        res
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_in_memory_dynamic_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        if self.supports_in_memory_dynamically_linked_extension_loading
+            && extension_module.shared_library.is_some()
+        {
+            self.resources
+                .add_in_memory_extension_module_shared_library(
+                    &extension_module.name,
+                    extension_module.is_package,
+                    &extension_module
+                        .shared_library
+                        .as_ref()
+                        .unwrap()
+                        .resolve()?,
+                    &[],
+                )
+        } else if !extension_module.object_file_data.is_empty() {
+            // TODO we shouldn't be adding a builtin extension module from this API.
+            self.resources
+                .add_builtin_extension_module(extension_module)
+        } else if extension_module.shared_library.is_some() {
+            Err(anyhow!(
+                "loading extension modules from memory not supported by this build configuration"
+            ))
+        } else {
+            Err(anyhow!(
+                "cannot load extension module from memory due to missing object files"
+            ))
+        }
+    }
+
+    fn add_relative_path_dynamic_extension_module(
+        &mut self,
+        prefix: &str,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        if extension_module.shared_library.is_none() {
+            return Err(anyhow!(
+                "extension module instance has no shared library data"
+            ));
+        }
+
+        if self.distribution.is_extension_module_file_loadable() {
+            self.resources
+                .add_relative_path_extension_module(extension_module, prefix)
+        } else {
+            Err(anyhow!(
+                "loading extension modules from files not supported by this build configuration"
+            ))
+        }
+    }
+
+    fn add_dynamic_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        if extension_module.shared_library.is_none() {
+            return Err(anyhow!(
+                "extension module instance has no shared library data"
+            ));
+        }
+
+        match self.packaging_policy.get_resources_policy().clone() {
+            PythonResourcesPolicy::InMemoryOnly => {
+                if self.supports_in_memory_dynamically_linked_extension_loading {
+                    self.resources
+                        .add_in_memory_extension_module_shared_library(
+                            &extension_module.name,
+                            extension_module.is_package,
+                            &extension_module
+                                .shared_library
+                                .as_ref()
+                                .unwrap()
+                                .resolve()?,
+                            &[],
+                        )
+                } else {
+                    Err(anyhow!("in-memory-only resources policy active but in-memory extension module importing not supported by this configuration: cannot load {}", extension_module.name))
+                }
+            }
+            PythonResourcesPolicy::FilesystemRelativeOnly(ref prefix) => {
+                if self.distribution.is_extension_module_file_loadable() {
+                    self.resources
+                        .add_relative_path_extension_module(extension_module, prefix)
+                } else {
+                    Err(anyhow!("filesystem-relative-only policy active but file-based extension module loading not supported by this configuration"))
+                }
+            }
+            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(ref prefix) => {
+                if self.supports_in_memory_dynamically_linked_extension_loading {
+                    self.resources
+                        .add_in_memory_extension_module_shared_library(
+                            &extension_module.name,
+                            extension_module.is_package,
+                            &extension_module
+                                .shared_library
+                                .as_ref()
+                                .unwrap()
+                                .resolve()?,
+                            &[],
+                        )
+                } else if self.distribution.is_extension_module_file_loadable() {
+                    self.resources
+                        .add_relative_path_extension_module(extension_module, prefix)
+                } else {
+                    Err(anyhow!("prefer-in-memory-fallback-filesystem-relative policy active but could not find a mechanism to add an extension module"))
+                }
+            }
+        }
+    }
+
+    fn add_static_extension_module(
+        &mut self,
+        extension_module: &PythonExtensionModule,
+    ) -> Result<()> {
+        self.resources
+            .add_builtin_extension_module(extension_module)
+    }
+
+    fn add_full_stdlib(
+        &mut self,
+        logger: &slog::Logger,
+        policy_override: Option<&PythonPackagingPolicy>,
+    ) -> Result<()> {
+        let policy = policy_override
+            .cloned()
+            .unwrap_or_else(|| self.packaging_policy.clone());
+
+        let mut included_extensions = BTreeSet::new();
+
+        let (extensions, broken) = policy.resolve_python_extension_modules(
+            self.distribution.extension_modules.values(),
+            &self.target_triple,
+        )?;
+        self.warn_broken_extension_modules(logger, &broken);
+
+        for ext in extensions {
+            self.add_distribution_extension_module(&ext)?;
+            included_extensions.insert(ext.name.clone());
+        }
+
+        self.policy_tallies.extension_modules_included += included_extensions.len();
+        self.policy_tallies.extension_modules_excluded +=
+            self.distribution.extension_modules.len() - included_extensions.len();
+
+        for source in self.distribution.source_modules()? {
+            if policy.filter_python_resource(&source.clone().into()) {
+                self.add_module_source(&source)?;
+                self.policy_tallies.module_sources_included += 1;
+            } else {
+                self.policy_tallies.module_sources_excluded += 1;
+            }
+
+            let optimize_level = policy.bytecode_optimization_level(&source.name);
+            let bytecode = source.as_bytecode_module(optimize_level);
+
+            if policy.filter_python_resource(&bytecode.clone().into()) {
+                // Distribution bytecode is only ever compiled at level 0.
+                let precompiled = if policy.reuse_distribution_bytecode()
+                    && optimize_level == BytecodeOptimizationLevel::Zero
+                {
+                    self.distribution.precompiled_bytecode(&source)?
+                } else {
+                    None
+                };
+
+                if let Some(precompiled) = precompiled {
+                    self.add_precompiled_module_bytecode(&precompiled)?;
+                } else {
+                    self.add_module_bytecode(&bytecode)?;
+                }
+                self.policy_tallies.module_bytecode_included += 1;
+            } else {
+                self.policy_tallies.module_bytecode_excluded += 1;
+            }
+        }
+
+        for resource in self.distribution.resource_datas()? {
+            if policy.filter_python_resource(&resource.clone().into()) {
+                self.add_package_resource(&resource)?;
+                self.policy_tallies.package_resources_included += 1;
+            } else {
+                self.policy_tallies.package_resources_excluded += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn filter_resources_from_files(
+        &mut self,
+        logger: &slog::Logger,
+        files: &[&Path],
+        glob_patterns: &[&str],
+        exclude_regex_patterns: &[&str],
+    ) -> Result<()> {
+        self.resources
+            .filter_from_files(logger, files, glob_patterns, exclude_regex_patterns)
+    }
+
+    fn rewrite_relative_prefix(&mut self, old: &str, new: &str) -> Result<usize> {
+        self.resources.rewrite_relative_prefix(old, new)
+    }
+
+    fn set_resource_eager_load(&mut self, name: &str, eager: bool) -> Result<()> {
+        self.resources.set_eager_load(name, eager)
+    }
+
+    fn set_resource_priority(&mut self, names: &[String]) {
+        self.resources.set_priority(names);
+    }
+
+    fn set_library_host_provided(&mut self, name: &str, host_provided: bool) {
+        self.resources
+            .set_library_host_provided(name, host_provided);
+    }
+
+    fn exclude_object_file(&mut self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        if !self
+            .distribution
+            .object_file_relative_paths()
+            .contains(path)
+        {
+            return Err(anyhow!(
+                "object file {} is not present in the Python distribution",
+                path.display()
+            ));
+        }
+
+        self.warnings.warn(
+            logger,
+            &format!(
+                "excluding object file {} from libpython; this may break functionality",
+                path.display()
+            ),
+        );
+        self.excluded_object_files.insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    fn set_artifact_name_prefix(&mut self, prefix: &str) -> Result<()> {
+        validate_artifact_name_prefix(prefix)?;
+        self.artifact_name_prefix = prefix.to_string();
+
+        Ok(())
+    }
+
+    fn add_extra_linker_args(&mut self, target_triple: &str, args: &[String]) -> Result<()> {
+        for arg in args {
+            if arg.trim().is_empty() {
+                return Err(anyhow!("linker arguments cannot be empty"));
+            }
+
+            // Arguments are passed via RUSTFLAGS, which is split on whitespace.
+            if arg.chars().any(char::is_whitespace) {
+                return Err(anyhow!(
+                    "linker argument cannot contain whitespace: {:?}",
+                    arg
+                ));
+            }
+        }
+
+        self.extra_linker_args
+            .entry(target_triple.to_string())
+            .or_insert_with(Vec::new)
+            .extend(args.iter().cloned());
+
+        Ok(())
+    }
+
+    fn set_link_description_path(&mut self, path: Option<&Path>) {
+        self.link_description_path = path.map(|p| p.to_path_buf());
+    }
+
+    fn set_build_temp_dir(&mut self, path: Option<&Path>) {
+        self.build_temp_dir = path.map(|p| p.to_path_buf());
+    }
+
+    fn set_split_debug_info(&mut self, enabled: bool) {
+        self.split_debug_info = enabled;
+    }
+
+    fn set_resource_compression(&mut self, options: Option<ResourceCompressionOptions>) {
+        self.resource_compression = options;
+    }
+
+    fn set_unresolvable_resource_policy(&mut self, policy: UnresolvableResourcePolicy) {
+        self.unresolvable_resource_policy = policy;
+    }
+
+    fn set_warnings_as_errors(&mut self, enabled: bool) {
+        self.warnings.set_warnings_as_errors(enabled);
+    }
+
+    fn add_warning(&mut self, logger: &slog::Logger, message: &str) {
+        self.warnings.warn(logger, message);
+    }
+
+    fn set_packed_resources_sidecar(&mut self, filename: Option<&str>) {
+        self.packed_resources_sidecar = filename.map(|x| x.to_string());
+    }
+
+    fn set_run_module(&mut self, module: &str) {
+        self.config.run_mode = RunMode::Module {
+            module: module.to_string(),
+        };
+    }
+
+    fn set_run_code(&mut self, code: &str) {
+        self.config.run_mode = RunMode::Eval {
+            code: code.to_string(),
+        };
+    }
+
+    fn set_hash_seed(&mut self, seed: u64) -> Result<()> {
+        self.config.set_hash_seed(seed)
+    }
+
+    fn set_recursion_limit(&mut self, limit: u32) -> Result<()> {
+        self.config.set_recursion_limit(limit)
+    }
+
+    fn set_thread_stack_size(&mut self, size: u64) -> Result<()> {
+        self.config.set_thread_stack_size(size)
+    }
+
+    fn set_faulthandler(&mut self, enabled: bool) {
+        self.config.fault_handler = enabled;
+    }
+
+    fn set_dev_mode(&mut self, enabled: bool) {
+        self.config.development_mode = enabled;
+    }
+
+    fn set_pre_import_code(&mut self, code: &str) -> Result<()> {
+        check_source_compiles(&self.python_exe, code, "<pre-import>")
+            .map_err(|e| anyhow!("pre-import code failed to compile: {}", e))?;
+
+        self.config.pre_import_code = Some(code.to_string());
+
+        Ok(())
+    }
+
+    fn add_named_config(&mut self, name: &str, config: EmbeddedPythonConfig) -> Result<()> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("invalid configuration name: {:?}", name));
+        }
+
+        config
+            .validate()
+            .with_context(|| format!("validating configuration {}", name))?;
+
+        if let Some(code) = &config.pre_import_code {
+            check_source_compiles(&self.python_exe, code, "<pre-import>").map_err(|e| {
+                anyhow!(
+                    "pre-import code of configuration {} failed to compile: {}",
+                    name,
+                    e
+                )
+            })?;
+        }
+
+        self.named_configs.insert(name.to_string(), config);
+
+        Ok(())
+    }
+
+    fn set_config_selector_env(&mut self, name: &str) -> Result<()> {
+        if name.is_empty()
+            || name.contains('=')
+            || name.contains('\0')
+            || name.contains('"')
+            || name.contains('\\')
+        {
+            return Err(anyhow!("invalid environment variable name: {:?}", name));
+        }
+
+        self.config_selector_env = name.to_string();
+
+        Ok(())
+    }
+
+    fn sys_paths(&self) -> Vec<String> {
+        self.config.sys_paths.clone()
+    }
+
+    fn add_relative_path_sys_paths(&mut self) -> Vec<String> {
+        let mut prefixes = BTreeSet::new();
+
+        for (_, resource) in self.resources.iter_resources() {
+            if let Some((prefix, _)) = &resource.relative_path_module_source {
+                prefixes.insert(prefix.clone());
+            }
+            for bytecode in &[
+                &resource.relative_path_bytecode,
+                &resource.relative_path_bytecode_opt1,
+                &resource.relative_path_bytecode_opt2,
+            ] {
+                if let Some((prefix, _, _)) = bytecode {
+                    prefixes.insert(prefix.clone());
+                }
+            }
+            if let Some((prefix, _, _)) = &resource.relative_path_extension_module_shared_library {
+                prefixes.insert(prefix.clone());
+            }
+        }
+
+        prefixes
+            .iter()
+            .map(|prefix| self.config.add_relative_path_sys_path(prefix))
+            .collect()
+    }
+
+    fn requires_jemalloc(&self) -> bool {
+        std::iter::once(&self.config)
+            .chain(self.named_configs.values())
+            .any(|config| self.effective_config_raw_allocator(config) == RawAllocator::Jemalloc)
+    }
+
+    fn as_embedded_python_binary_data(
+        &self,
+        logger: &slog::Logger,
+        opt_level: &str,
+    ) -> Result<EmbeddedPythonBinaryData> {
+        let mut warnings = self.warnings.clone();
+        let mut prepackaged = Cow::Borrowed(&self.resources);
+
+        if fast_build_enabled() {
+            warnings.warn(
+                logger,
+                &format!(
+                    "{} is set; skipping bytecode compilation (imports will be slower; do not distribute this binary)",
+                    FAST_BUILD_ENV
+                ),
+            );
+
+            let count = prepackaged.to_mut().replace_bytecode_with_source();
+            info!(
+                logger,
+                "embedding source instead of bytecode for {} resources", count
+            );
+        }
+
+        let omitted = if self.unresolvable_resource_policy == UnresolvableResourcePolicy::Fail
+            || prepackaged
+                .iter_resources()
+                .all(|(_, resource)| resource.is_resolvable())
+        {
+            vec![]
+        } else {
+            prepackaged.to_mut().remove_unresolvable_resources()?
+        };
+
+        for name in &omitted {
+            warnings.warn(
+                logger,
+                &format!(
+                    "omitting data of resource {} because it no longer exists",
+                    name
+                ),
+            );
+        }
+
+        let mut config = self.config.clone();
+        let raw_allocator = self.effective_raw_allocator();
+        if raw_allocator != config.raw_allocator {
+            warnings.warn(
+                logger,
+                &format!(
+                    "jemalloc is not supported on {}; using the system allocator",
+                    self.target_triple
+                ),
+            );
+            config.raw_allocator = raw_allocator;
+        }
+
+        let named_configs = self.effective_named_configs(logger, &mut warnings);
+
+        let mut resources = prepackaged.package(logger, &self.python_exe, &mut warnings)?;
+        warnings.check()?;
+        let mut extra_files = resources.extra_install_files()?;
+
+        if self.unresolvable_resource_policy == UnresolvableResourcePolicy::SkipAndReport {
+            let mut data = omitted.join("\n").into_bytes();
+            if !data.is_empty() {
+                data.push(b'\n');
+            }
+
+            extra_files.add_file(
+                Path::new(OMITTED_RESOURCES_FILENAME),
+                &FileContent {
+                    data,
+                    executable: false,
+                },
+            )?;
+        }
+        let linking_info = self.resolve_python_linking_info(logger, opt_level, &resources)?;
+
+        if let Some(options) = &self.resource_compression {
+            let count = resources.compress_resources(options)?;
+            info!(logger, "compressed {} resources", count);
+        }
+
+        let resources = EmbeddedResourcesBlobs::try_from(resources)?;
+
+        if self.link_mode == LibpythonLinkMode::Dynamic {
+            if let Some(p) = &self.distribution.libpython_shared_library {
+                let manifest_path = Path::new(p.file_name().unwrap());
+                let content = FileContent {
+                    data: std::fs::read(&p)?,
+                    executable: false,
+                };
+
+                extra_files.add_file(&manifest_path, &content)?;
+            }
+        }
+
+        let packed_resources_sidecar = self
+            .packed_resources_sidecar
+            .as_ref()
+            .map(|filename| format!("{}{}", self.artifact_name_prefix, filename));
+
+        if let Some(filename) = &packed_resources_sidecar {
+            let content = FileContent {
+                data: resources.resources.clone(),
+                executable: false,
+            };
+
+            extra_files.add_file(&Path::new(filename), &content)?;
+        }
+
+        Ok(EmbeddedPythonBinaryData {
+            config,
+            linking_info,
+            resources,
+            extra_files,
+            host: self.host_triple.clone(),
+            target: self.target_triple.clone(),
+            artifact_name_prefix: self.artifact_name_prefix.clone(),
+            packed_resources_sidecar,
+            named_configs,
+            config_selector_env: self.config_selector_env.clone(),
+            split_debug_info: self.split_debug_info,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use {
+        super::*, crate::py_packaging::binary::accelerator_pair_problems,
+        crate::py_packaging::distribution::DistributionFlavor,
+        crate::python_distributions::PYTHON_DISTRIBUTIONS, crate::testutil::*,
+        python_packaging::policy::ExtensionModuleFilter,
+    };
+
+    /// Defines construction options for a `StandalonePythonExecutableBuilder`.
+    ///
+    /// This is mostly intended to be used by tests, to reduce boilerplate for
+    /// constructing instances.
+    pub struct StandalonePythonExecutableBuilderOptions {
+        pub logger: Option<slog::Logger>,
+        pub host_triple: String,
+        pub target_triple: String,
+        pub distribution_flavor: DistributionFlavor,
+        pub app_name: String,
+        pub libpython_link_mode: BinaryLibpythonLinkMode,
+        pub extension_module_filter: ExtensionModuleFilter,
+    }
+
+    impl Default for StandalonePythonExecutableBuilderOptions {
+        fn default() -> Self {
+            Self {
+                logger: None,
+                host_triple: env!("HOST").to_string(),
+                target_triple: env!("HOST").to_string(),
+                distribution_flavor: DistributionFlavor::Standalone,
+                app_name: "testapp".to_string(),
+                libpython_link_mode: BinaryLibpythonLinkMode::Default,
+                extension_module_filter: ExtensionModuleFilter::Minimal,
+            }
+        }
+    }
+
+    impl StandalonePythonExecutableBuilderOptions {
+        fn new_builder(
+            &self,
+        ) -> Result<(
+            Arc<Box<StandaloneDistribution>>,
+            Box<dyn PythonBinaryBuilder>,
+        )> {
+            let logger = if let Some(logger) = &self.logger {
+                logger.clone()
+            } else {
+                get_logger()?
+            };
+
+            let record = PYTHON_DISTRIBUTIONS
+                .find_distribution(&self.target_triple, &self.distribution_flavor)
+                .ok_or_else(|| anyhow!("could not find Python distribution"))?;
+
+            let distribution = get_distribution(&record.location)?;
+
+            let mut policy = PythonPackagingPolicy::default();
+            policy.set_extension_module_filter(self.extension_module_filter.clone());
+
+            let config = EmbeddedPythonConfig::default();
+
+            Ok((
+                distribution.clone(),
+                distribution.as_python_executable_builder(
+                    &logger,
+                    &self.host_triple,
+                    &self.target_triple,
+                    &self.app_name,
+                    self.libpython_link_mode.clone(),
+                    &policy,
+                    &config,
+                )?,
+            ))
+        }
+    }
+
+    // TODO switch users to StandalonePythonExecutableBuilderOptions
+    pub fn get_standalone_executable_builder() -> Result<StandalonePythonExecutableBuilder> {
+        let distribution = get_default_distribution()?;
+
+        // Link mode is static unless we're a dynamic distribution on Windows.
+        let link_mode = if distribution.target_triple.contains("pc-windows")
+            && distribution.python_symbol_visibility == "dllexport"
+        {
+            LibpythonLinkMode::Dynamic
+        } else {
+            LibpythonLinkMode::Static
+        };
+
+        let resources = PrePackagedResources::new(
+            &PythonResourcesPolicy::InMemoryOnly,
+            &distribution.cache_tag,
+        );
+
+        let mut packaging_policy = distribution.create_packaging_policy()?;
+        packaging_policy.set_extension_module_filter(ExtensionModuleFilter::Minimal);
+        packaging_policy.set_resources_policy(PythonResourcesPolicy::InMemoryOnly);
+
+        let config = EmbeddedPythonConfig::default();
+
+        let python_exe = distribution.python_exe.clone();
+
+        let mut builder = StandalonePythonExecutableBuilder {
+            host_triple: env!("HOST").to_string(),
+            target_triple: env!("HOST").to_string(),
+            exe_name: "testapp".to_string(),
+            distribution: distribution.clone(),
+            link_mode,
+            supports_in_memory_dynamically_linked_extension_loading: false,
+            packaging_policy,
+            resources,
+            config,
+            python_exe,
+            excluded_object_files: BTreeSet::new(),
+            artifact_name_prefix: "".to_string(),
+            packed_resources_sidecar: None,
+            extra_linker_args: BTreeMap::new(),
+            split_debug_info: false,
+            link_description_path: None,
+            build_temp_dir: None,
+            named_configs: BTreeMap::new(),
+            config_selector_env: DEFAULT_CONFIG_SELECTOR_ENV.to_string(),
+            resource_compression: None,
+            policy_tallies: PolicyTallies::default(),
+            unresolvable_resource_policy: UnresolvableResourcePolicy::default(),
+            warnings: BuildWarnings::default(),
+        };
+
+        builder.add_full_stdlib(&get_logger()?, None)?;
+
+        Ok(builder)
+    }
+
+    pub fn get_embedded(logger: &slog::Logger) -> Result<EmbeddedPythonBinaryData> {
+        let exe = get_standalone_executable_builder()?;
+        exe.as_embedded_python_binary_data(logger, "0")
+    }
+
+    #[test]
+    fn test_clear_added_resources() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+
+        let baseline_resources = builder
+            .iter_resources()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let baseline_builtins = builder
+            .builtin_extension_module_names()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let cache_tag = builder.cache_tag().to_string();
+        builder.add_module_source(&PythonModuleSource {
+            name: "custom_module".to_string(),
+            source: DataLocation::Memory(b"import os".to_vec()),
+            is_package: false,
+            cache_tag,
+            is_stdlib: false,
+            is_test: false,
+        })?;
+        assert!(builder
+            .iter_resources()
+            .any(|(name, _)| name == "custom_module"));
+
+        builder.clear_added_resources(&get_logger()?)?;
+
+        assert_eq!(
+            builder
+                .iter_resources()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>(),
+            baseline_resources
+        );
+        assert_eq!(
+            builder
+                .builtin_extension_module_names()
+                .cloned()
+                .collect::<Vec<_>>(),
+            baseline_builtins
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_embedded_files() -> Result<()> {
+        let logger = get_logger()?;
+        let embedded = get_embedded(&logger)?;
+        let temp_dir = new_temp_dir("test")?;
+
+        embedded.write_files(temp_dir.path())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_run_mode() -> Result<()> {
+        let logger = get_logger()?;
+        let mut embedded = get_embedded(&logger)?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let paths = embedded.write_files(temp_dir.path())?;
+
+        embedded.set_run_mode(RunMode::Module {
+            module: "myapp".to_string(),
+        });
+        let config_rs = embedded.write_config_rs(temp_dir.path())?;
+
+        assert_eq!(config_rs, paths.config_rs);
+        assert!(std::fs::read_to_string(&config_rs)?.contains("\"myapp\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_package_tarball() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+        let path = temp_dir.path().join("packages.tar.gz");
+
+        {
+            let encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(&path)?,
+                flate2::Compression::default(),
+            );
+            let mut tar = tar::Builder::new(encoder);
+
+            for (name, data) in &[
+                ("foo/__init__.py", "".as_bytes()),
+                ("foo/bar.py", "import foo".as_bytes()),
+                ("foo/data.txt", "data".as_bytes()),
+                ("other/__init__.py", "".as_bytes()),
+            ] {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar.append_data(&mut header, name, *data)?;
+            }
+
+            tar.into_inner()?.finish()?;
+        }
+
+        let resources = builder.read_package_tarball(&logger, &path, &["foo".to_string()])?;
+        let names = resources
+            .iter()
+            .map(|r| r.full_name())
+            .collect::<BTreeSet<_>>();
+
+        assert!(names.contains("foo"));
+        assert!(names.contains("foo.bar"));
+        assert!(names.contains("foo.data.txt"));
+        assert!(!names.contains("other"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_package_roots_conflict() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let src = temp_dir.path().join("src");
+        let vendor = temp_dir.path().join("vendor");
+        for (root, name) in &[
+            (&src, "app.py"),
+            (&src, "shared.py"),
+            (&vendor, "shared.py"),
+        ] {
+            std::fs::create_dir_all(root)?;
+            std::fs::write(root.join(name), b"")?;
+        }
+
+        let packages = vec!["app".to_string(), "shared".to_string()];
+        let roots = [src.as_path(), vendor.as_path()];
+
+        let err = builder
+            .add_package_roots(&logger, &roots, &packages, true)
+            .unwrap_err();
+        assert!(err.to_string().contains("shared is provided by both"));
+
+        builder.add_package_roots(&logger, &roots, &packages, false)?;
+
+        let resources = builder.iter_resources().collect::<BTreeMap<_, _>>();
+        assert!(resources.contains_key(&"app".to_string()));
+        assert_eq!(
+            resources[&"shared".to_string()].in_memory_source,
+            Some(DataLocation::Path(src.join("shared.py")))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exported_symbols() -> Result<()> {
+        let builder = get_standalone_executable_builder()?;
+        let symbols = builder.exported_symbols();
+
+        let builtins = builder.resources.builtin_extensions();
+        assert!(!builtins.is_empty());
+        for (_, init_fn) in builtins {
+            assert!(symbols.contains(&init_fn));
+        }
+
+        let visibility = builder.distribution.python_symbol_visibility.as_str();
+        assert_eq!(
+            symbols.contains(&"Py_Initialize".to_string()),
+            visibility == "dllexport" || visibility == "global-default"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_tunables() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        builder.set_hash_seed(42)?;
+        builder.set_recursion_limit(5000)?;
+        builder.set_thread_stack_size(1048576)?;
+        builder.set_faulthandler(true);
+        builder.set_dev_mode(true);
+        assert!(builder.set_recursion_limit(1).is_err());
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let temp_dir = new_temp_dir("test")?;
+        let paths = embedded.write_files(temp_dir.path())?;
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+
+        assert!(config_rs.contains("hash_seed: Some(42)"));
+        assert!(config_rs.contains("recursion_limit: Some(5000)"));
+        assert!(config_rs.contains("thread_stack_size: Some(1048576)"));
+        assert!(config_rs.contains("fault_handler: true"));
+        assert!(config_rs.contains("development_mode: true"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_import_code() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        let err = builder
+            .set_pre_import_code("import sys\nsys.meta_path.insert(0,\n")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("pre-import code failed to compile"));
+        assert!(builder.config.pre_import_code.is_none());
+
+        // Code containing raw string delimiters must be escaped properly.
+        builder.set_pre_import_code("import sys\nsys.meta_path.insert(0, object())  # \"###\n")?;
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let temp_dir = new_temp_dir("test")?;
+        let paths = embedded.write_files(temp_dir.path())?;
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+
+        assert!(config_rs.contains(
+            "pre_import_code: Some(\"import sys\\nsys.meta_path.insert(0, object())  # \\\"###\\n\".to_string())"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_configs() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        let verbose = EmbeddedPythonConfig {
+            verbose: 1,
+            ..builder.config.clone()
+        };
+
+        assert!(builder.add_named_config("", verbose.clone()).is_err());
+        assert!(builder.add_named_config("a b", verbose.clone()).is_err());
+        assert!(builder
+            .add_named_config(
+                "invalid",
+                EmbeddedPythonConfig {
+                    recursion_limit: Some(10),
+                    ..builder.config.clone()
+                }
+            )
+            .is_err());
+        assert!(builder.set_config_selector_env("A=B").is_err());
+        assert!(builder.set_config_selector_env("A\"B").is_err());
+        assert!(builder.set_config_selector_env("A\\B").is_err());
+
+        builder.add_named_config("verbose", verbose)?;
+        builder.set_config_selector_env("APP_MODE")?;
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let temp_dir = new_temp_dir("test")?;
+        let paths = embedded.write_files(temp_dir.path())?;
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+
+        assert!(config_rs.contains("pub fn default_python_config"));
+        assert!(config_rs.contains("\"verbose\" => Some(pyembed::PythonConfig {"));
+        assert!(config_rs.contains("std::env::var(\"APP_MODE\")"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_artifact_name_prefix() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        for prefix in &["a/b", "a-b", "1a", "a b", "a.b"] {
+            assert!(builder.set_artifact_name_prefix(prefix).is_err());
+        }
+        builder.set_artifact_name_prefix("")?;
+        builder.set_artifact_name_prefix("app_")?;
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let temp_dir = new_temp_dir("test")?;
+        let paths = embedded.write_files(temp_dir.path())?;
+
+        assert_eq!(
+            paths.embedded_resources,
+            temp_dir.path().join("app_packed-resources")
+        );
+        assert_eq!(
+            paths.module_names,
+            temp_dir.path().join("app_py-module-names")
+        );
+        assert_eq!(
+            paths.config_rs,
+            temp_dir.path().join("app_default_python_config.rs")
+        );
+        assert!(paths
+            .libpython
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .contains("app_pythonXY"));
+
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+        assert!(config_rs.contains("pub static APP_PACKED_RESOURCES: &[u8] = include_bytes!("));
+        assert!(config_rs.contains("packed_resources: APP_PACKED_RESOURCES,"));
+        assert!(config_rs.contains("pub fn app_default_python_config<'a>()"));
+        assert!(config_rs.contains("pub fn app_named_python_config<'a>("));
+        assert!(config_rs.contains("pub fn app_selected_python_config<'a>()"));
+        assert!(config_rs.contains("Err(_) => Ok(app_default_python_config()),"));
+        assert!(!config_rs.contains("fn default_python_config"));
+
+        assert_eq!(
+            embedded
+                .linking_info
+                .cargo_metadata
+                .contains(&"cargo:rustc-link-lib=static=app_pyembeddedconfig".to_string()),
+            builder.link_mode == LibpythonLinkMode::Static
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_linker_args() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let target_triple = builder.target_triple.clone();
+
+        assert!(builder
+            .add_extra_linker_args(&target_triple, &["".to_string()])
+            .is_err());
+        assert!(builder
+            .add_extra_linker_args(&target_triple, &["-Wl\n".to_string()])
+            .is_err());
+        assert!(builder
+            .add_extra_linker_args(&target_triple, &["-Wl,-rpath,/a b".to_string()])
+            .is_err());
+
+        builder.add_extra_linker_args(&target_triple, &["-Wl,--no-as-needed".to_string()])?;
+        builder.add_extra_linker_args("other-triple", &["/DEBUG".to_string()])?;
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let linker_args = &embedded.linking_info.linker_args;
+        assert!(linker_args.contains(&"-Wl,--no-as-needed".to_string()));
+        assert!(!linker_args.contains(&"/DEBUG".to_string()));
+        assert!(!embedded
+            .linking_info
+            .cargo_metadata
+            .iter()
+            .any(|line| line.starts_with("cargo:rustc-link-arg")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_embedded_package() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+
+        assert_eq!(builder.is_embedded_package("email"), Some(true));
+        assert_eq!(builder.is_embedded_package("email.message"), Some(false));
+        assert_eq!(builder.is_embedded_package("does_not_exist"), None);
+
+        builder.add_in_memory_module_source(&PythonModuleSource {
+            name: "foo".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: true,
+            cache_tag: builder.cache_tag().to_string(),
+            is_stdlib: false,
+            is_test: false,
+        })?;
+        assert_eq!(builder.is_embedded_package("foo"), Some(true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_relative_path_sys_paths() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        assert!(builder.add_relative_path_sys_paths().is_empty());
+        assert!(builder.sys_paths().is_empty());
+
+        builder.add_relative_path_module_source(
+            "lib",
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: builder.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+        )?;
+
+        assert_eq!(builder.add_relative_path_sys_paths(), vec!["$ORIGIN/lib"]);
+        assert_eq!(builder.sys_paths(), vec!["$ORIGIN/lib"]);
+        assert!(builder.config.filesystem_importer);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_description() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+        let path = temp_dir.path().join("link.txt");
+        let target_triple = builder.target_triple.clone();
+
+        builder.link_mode = LibpythonLinkMode::Static;
+        builder.add_extra_linker_args(&target_triple, &["-Wl,--no-as-needed".to_string()])?;
+        builder.set_link_description_path(Some(&path));
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+
+        let description = std::fs::read_to_string(&path)?;
+        let lines = description.lines().collect::<Vec<_>>();
+
+        let command_after = |heading: &str| {
+            lines
+                .iter()
+                .position(|l| l.starts_with(heading))
+                .map(|i| lines[i + 1])
+        };
+
+        let compile = command_after("# compile ").unwrap();
+        assert!(compile.contains("\"-c\""));
+        assert!(compile.contains("config.c"));
+
+        let archive = command_after("# archive ").unwrap();
+        assert!(archive.contains("pyembeddedconfig"));
+        assert!(lines
+            .iter()
+            .filter(|l| l.starts_with("# archive "))
+            .any(|l| l.contains("pythonXY")));
+
+        let link = command_after("# link ").unwrap();
+        for arg in &embedded.linking_info.linker_args {
+            assert!(link.contains(&format!("{:?}", arg)));
+        }
+        for line in &embedded.linking_info.cargo_metadata {
+            if let Some(name) = line.splitn(2, "cargo:rustc-link-lib=static=").nth(1) {
+                assert!(link.contains(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_temp_dir() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+        let build_dir = temp_dir.path().join("build");
+        std::fs::create_dir(&build_dir)?;
+        let path = temp_dir.path().join("link.txt");
+
+        builder.link_mode = LibpythonLinkMode::Static;
+        builder.set_build_temp_dir(Some(&build_dir));
+        builder.set_link_description_path(Some(&path));
+        builder.as_embedded_python_binary_data(&logger, "0")?;
+
+        let description = std::fs::read_to_string(&path)?;
+        let archive = description
+            .lines()
+            .find(|line| line.starts_with("# archive "))
+            .map(|line| &line["# archive ".len()..])
+            .unwrap();
+        assert!(Path::new(archive).starts_with(&build_dir));
+
+        // Temporary build directories are removed once the build finishes.
+        assert_eq!(std::fs::read_dir(&build_dir)?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_debug_info() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let args = split_debug_info_linker_args(&builder.target_triple)
+            .into_iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>();
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let linker_args = &embedded.linking_info.linker_args;
+        assert!(!embedded.split_debug_info);
+        assert!(args.iter().all(|arg| !linker_args.contains(arg)));
+
+        builder.set_split_debug_info(true);
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let linker_args = &embedded.linking_info.linker_args;
+        assert!(embedded.split_debug_info);
+        assert!(args.iter().all(|arg| linker_args.contains(arg)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_compression() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        for resource in
+            python_packed_resources::parser::load_resources(&embedded.resources.resources)
+                .map_err(|e| anyhow!(e))?
+        {
+            assert!(!resource.map_err(|e| anyhow!(e))?.zstd_compressed);
+        }
+
+        builder.set_resource_compression(Some(ResourceCompressionOptions {
+            min_size: 1,
+            ..ResourceCompressionOptions::default()
+        }));
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        let mut compressed = 0;
+        for resource in
+            python_packed_resources::parser::load_resources(&embedded.resources.resources)
+                .map_err(|e| anyhow!(e))?
+        {
+            if resource.map_err(|e| anyhow!(e))?.zstd_compressed {
+                compressed += 1;
+            }
+        }
+        assert!(compressed > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unresolvable_resource_policy() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let path = temp_dir.path().join("gone.py");
+        std::fs::write(&path, b"x = 1\n")?;
+        builder.add_in_memory_module_source(&PythonModuleSource {
+            name: "gone".to_string(),
+            source: DataLocation::Path(path.clone()),
+            is_package: false,
+            cache_tag: builder.cache_tag().to_string(),
+            is_stdlib: false,
+            is_test: false,
+        })?;
+        std::fs::remove_file(&path)?;
+
+        assert!(builder
+            .as_embedded_python_binary_data(&logger, "0")
+            .is_err());
+
+        builder.set_unresolvable_resource_policy(UnresolvableResourcePolicy::SkipAndReport);
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+
+        let (_, content) = embedded
+            .extra_files
+            .entries()
+            .find(|(path, _)| path.as_path() == Path::new(OMITTED_RESOURCES_FILENAME))
+            .unwrap();
+        assert_eq!(content.data, b"gone\n");
+        // The builder itself is unchanged.
+        assert!(builder
+            .resources
+            .iter_resources()
+            .any(|(name, _)| name == "gone"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effective_raw_allocator_windows() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        builder.target_triple = "x86_64-pc-windows-msvc".to_string();
+        builder.config.raw_allocator = RawAllocator::Jemalloc;
+
+        assert_eq!(builder.effective_raw_allocator(), RawAllocator::System);
+        assert!(!builder.requires_jemalloc());
+
+        builder.config.raw_allocator = RawAllocator::Rust;
+        assert_eq!(builder.effective_raw_allocator(), RawAllocator::Rust);
+
+        builder.add_named_config(
+            "jemalloc",
+            EmbeddedPythonConfig {
+                raw_allocator: RawAllocator::Jemalloc,
+                ..builder.config.clone()
+            },
+        )?;
+        assert!(!builder.requires_jemalloc());
+
+        let (logger, drain) = get_capturing_logger();
+        let mut warnings = builder.warnings.clone();
+        let named_configs = builder.effective_named_configs(&logger, &mut warnings);
+        assert_eq!(
+            named_configs.get("jemalloc").unwrap().raw_allocator,
+            RawAllocator::System
+        );
+        assert!(drain
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|m| m.contains("using the system allocator for config jemalloc")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effective_raw_allocator_linux() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        builder.target_triple = "x86_64-unknown-linux-gnu".to_string();
+        builder.config.raw_allocator = RawAllocator::Jemalloc;
+
+        assert_eq!(builder.effective_raw_allocator(), RawAllocator::Jemalloc);
+        assert!(builder.requires_jemalloc());
+
+        builder.config.raw_allocator = RawAllocator::System;
+        assert!(!builder.requires_jemalloc());
+        builder.add_named_config(
+            "jemalloc",
+            EmbeddedPythonConfig {
+                raw_allocator: RawAllocator::Jemalloc,
+                ..builder.config.clone()
+            },
+        )?;
+        assert!(builder.requires_jemalloc());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_warnings_as_errors() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let path = temp_dir.path().join("gone.py");
+        std::fs::write(&path, b"x = 1\n")?;
+        builder.add_in_memory_module_source(&PythonModuleSource {
+            name: "gone".to_string(),
+            source: DataLocation::Path(path.clone()),
+            is_package: false,
+            cache_tag: builder.cache_tag().to_string(),
+            is_stdlib: false,
+            is_test: false,
+        })?;
+        std::fs::remove_file(&path)?;
+        builder.set_unresolvable_resource_policy(UnresolvableResourcePolicy::SkipWithWarning);
+
+        builder.as_embedded_python_binary_data(&logger, "0")?;
+
+        builder.set_warnings_as_errors(true);
+        let err = builder
+            .as_embedded_python_binary_data(&logger, "0")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("omitting data of resource gone because it no longer exists"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_object_file() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        assert!(builder
+            .exclude_object_file(&logger, Path::new("does/not/exist.o"))
+            .is_err());
+        assert!(builder.warnings.warnings().is_empty());
+
+        let path = builder
+            .distribution
+            .object_file_relative_paths()
+            .into_iter()
+            .next()
+            .unwrap();
+        builder.exclude_object_file(&logger, &path)?;
+
+        assert!(builder.excluded_object_files.contains(&path));
+        assert_eq!(
+            builder.warnings.warnings(),
+            &[format!(
+                "excluding object file {} from libpython; this may break functionality",
+                path.display()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_sidecar() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        builder.set_packed_resources_sidecar(Some("app.resources"));
+
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        assert!(embedded.extra_files.has_path(Path::new("app.resources")));
+
+        let temp_dir = new_temp_dir("test")?;
+        let paths = embedded.write_files(temp_dir.path())?;
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+        assert!(config_rs.contains("packed_resources: &[]"));
+        assert!(config_rs.contains("app.resources"));
+
+        builder.set_artifact_name_prefix("app_")?;
+        let embedded = builder.as_embedded_python_binary_data(&logger, "0")?;
+        assert!(embedded
+            .extra_files
+            .has_path(Path::new("app_app.resources")));
+        assert!(!embedded.extra_files.has_path(Path::new("app.resources")));
+
+        let paths = embedded.write_files(temp_dir.path())?;
+        let config_rs = std::fs::read_to_string(&paths.config_rs)?;
+        assert!(config_rs.contains("\"app_app.resources\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_annotations() -> Result<()> {
+        let distribution = get_default_distribution()?;
+
+        for module in distribution.source_modules()? {
+            assert!(module.is_stdlib);
+
+            if module.name.starts_with("test") {
+                assert!(module.is_test);
+            }
+        }
+
+        for resource in distribution.resource_datas()? {
+            assert!(resource.is_stdlib);
+            if resource.leaf_package.starts_with("test") {
+                assert!(resource.is_test);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimal_extensions_present() -> Result<()> {
+        let builder: StandalonePythonExecutableBuilder = get_standalone_executable_builder()?;
+
+        let expected = builder
+            .distribution
+            .extension_modules
+            .iter()
+            .filter_map(|(_, extensions)| {
+                if extensions.default_variant().is_minimally_required() {
+                    Some(extensions.default_variant().name.clone())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Sanity check.
+        assert!(expected.contains(&"_io".to_string()));
+
+        for name in &expected {
+            // All extensions annotated as required in the distribution are marked
+            // as built-ins.
+            assert!(builder.builtin_extension_module_names().any(|x| x == name));
+
+            // Built-in extension modules shouldn't be annotated as resources.
+            assert!(!builder.iter_resources().any(|(x, _)| x == name));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_full_stdlib_policy_override() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+
+        // The default policy doesn't include package resources.
+        assert!(!builder
+            .iter_resources()
+            .any(|(_, r)| r.in_memory_resources.is_some()));
+
+        let mut policy = builder.python_packaging_policy().clone();
+        policy.set_include_distribution_resources(true);
+        builder.add_full_stdlib(&get_logger()?, Some(&policy))?;
+
+        assert!(builder
+            .iter_resources()
+            .any(|(_, r)| r.in_memory_resources.is_some()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effective_policy_report() -> Result<()> {
+        let builder = get_standalone_executable_builder()?;
+        let report = builder.effective_policy_report();
+
+        assert_eq!(
+            report.extension_module_filter,
+            ExtensionModuleFilter::Minimal
+        );
+        assert_eq!(report.resources_policy, PythonResourcesPolicy::InMemoryOnly);
+
+        let tallies = &report.tallies;
+        assert!(tallies.extension_modules_included > 0);
+        assert!(tallies.extension_modules_excluded > 0);
+        assert_eq!(
+            tallies.extension_modules_included + tallies.extension_modules_excluded,
+            builder.distribution.extension_modules.len()
+        );
+        assert!(tallies.module_sources_included > 0);
+        // Test modules are excluded by default.
+        assert!(tallies.module_sources_excluded > 0);
+        // Package resources are excluded by default.
+        assert_eq!(tallies.package_resources_included, 0);
+        assert!(tallies.package_resources_excluded > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_source_bytecode_consistency() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+
+        // Bytecode compiled from source is consistent by construction.
+        assert!(builder
+            .verify_source_bytecode_consistency(&logger)?
+            .is_empty());
+
+        let mut compiler = BytecodeCompiler::new(&builder.python_exe)?;
+
+        // Bytecode compiled from another path embeds a different filename
+        // in its code objects, including nested ones. That isn't a mismatch.
+        let relocated = "def f():\n    return 1\n";
+
+        for (name, source, compiled_source, compiled_filename) in &[
+            ("consistent", "x = 1\n", "x = 1\n", "consistent"),
+            ("inconsistent", "x = 1\n", "x = 2\n", "inconsistent"),
+            (
+                "relocated",
+                relocated,
+                relocated,
+                "/build/elsewhere/relocated.py",
+            ),
+        ] {
+            builder.resources.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: DataLocation::Memory(source.as_bytes().to_vec()),
+                    is_package: false,
+                    cache_tag: builder.cache_tag().to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+
+            let bytecode = compiler.compile(
+                compiled_source.as_bytes(),
+                compiled_filename,
+                BytecodeOptimizationLevel::Zero,
+                CompileMode::Bytecode,
+            )?;
+
+            builder.resources.add_python_module_bytecode(
+                &PythonModuleBytecode::new(
+                    name,
+                    BytecodeOptimizationLevel::Zero,
+                    false,
+                    builder.cache_tag(),
+                    &bytecode,
+                ),
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        assert_eq!(
+            builder.verify_source_bytecode_consistency(&logger)?,
+            vec!["inconsistent: bytecode (optimization level 0) does not match source".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_tar_zst_file_sha256() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+        let path = temp_dir.path().join("python.tar.zst");
+        let extract_dir = temp_dir.path().join("extract");
+        std::fs::write(&path, b"truncated")?;
+
+        let digest = hex::encode(Sha256::digest(b"truncated"));
+        for expected in &[digest.clone(), digest.to_uppercase()] {
+            let options = DistributionExtractOptions {
+                archive_buffer_size: 4,
+                expected_sha256: Some(expected.clone()),
+                ..DistributionExtractOptions::default()
+            };
+            assert_eq!(archive_file_sha256(&path, &options)?, digest);
+        }
+
+        let err = StandaloneDistribution::from_tar_zst_file_with_options(
+            &logger,
+            &path,
+            &extract_dir,
+            &DistributionExtractOptions {
+                expected_sha256: Some(hex::encode(Sha256::digest(b"complete"))),
+                ..DistributionExtractOptions::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("sha256 of "));
+        assert!(!extract_dir.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_python_json_versions() -> Result<()> {
+        let dist = get_default_distribution()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let mut value: serde_json::Value = serde_json::from_slice(&std::fs::read(
+            dist.base_dir.join("python").join("PYTHON.json"),
+        )?)?;
+        let o = value.as_object_mut().unwrap();
+        o.remove("python_config_vars");
+
+        o.insert("version".to_string(), "5".into());
+        let v5_path = temp_dir.path().join("v5.json");
+        std::fs::write(&v5_path, serde_json::to_vec(&value)?)?;
+
+        let o = value.as_object_mut().unwrap();
+        o.insert("version".to_string(), "6".into());
+        o.insert(
+            "python_config_vars".to_string(),
+            serde_json::json!({"SOABI": "cpython-38-x86_64-linux-gnu"}),
+        );
+        o.insert("future_field".to_string(), true.into());
+        let v6_path = temp_dir.path().join("v6.json");
+        std::fs::write(&v6_path, serde_json::to_vec(&value)?)?;
+
+        let v5 = parse_python_json(&v5_path)?;
+        assert_eq!(v5.version, "5");
+        assert!(v5.python_config_vars.is_none());
+        assert!(!v5.unknown_fields.contains_key("future_field"));
+
+        let v6 = parse_python_json(&v6_path)?;
+        assert_eq!(v6.version, "6");
+        assert_eq!(v6.python_exe, v5.python_exe);
+        assert_eq!(
+            v6.python_config_vars
+                .as_ref()
+                .and_then(|vars| vars.get("SOABI"))
+                .map(|x| x.as_str()),
+            Some("cpython-38-x86_64-linux-gnu")
+        );
+        assert!(v6.unknown_fields.contains_key("future_field"));
+
+        let o = value.as_object_mut().unwrap();
+        o.insert("version".to_string(), "7".into());
+        let v7_path = temp_dir.path().join("v7.json");
+        std::fs::write(&v7_path, serde_json::to_vec(&value)?)?;
+        assert_eq!(
+            parse_python_json(&v7_path)
+                .unwrap_err()
+                .downcast_ref::<DistributionError>(),
+            Some(&DistributionError::UnsupportedJsonVersion {
+                found: "7".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_python_json_cached() -> Result<()> {
+        let dist = get_default_distribution()?;
+        let temp_dir = new_temp_dir("test")?;
+        let path = temp_dir.path().join("PYTHON.json");
+
+        std::fs::copy(dist.base_dir.join("python").join("PYTHON.json"), &path)?;
+
+        let first = parse_python_json_cached(&path)?;
+        let second = parse_python_json_cached(&path)?;
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Changing the file invalidates the cached entry.
+        let mut data = std::fs::read(&path)?;
+        data.push(b'\n');
+        std::fs::write(&path, &data)?;
+        let third = parse_python_json_cached(&path)?;
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(first.python_exe, third.python_exe);
+
+        // A deleted file isn't served from the cache.
+        std::fs::remove_file(&path)?;
+        assert!(parse_python_json_cached(&path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extraction_marker() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+        let extract_dir = temp_dir.path().join("extract");
+        let options = DistributionExtractOptions::default();
+
+        let make_tar = |files: &[(&str, &[u8])]| -> Result<Vec<u8>> {
+            let mut builder = tar::Builder::new(Vec::new());
+            for (path, data) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                builder.append_data(&mut header, path, *data)?;
+            }
+            Ok(builder.into_inner()?)
+        };
+
+        let first = make_tar(&[("python/PYTHON.json", b"{}"), ("python/old.txt", b"old")])?;
+        let second = make_tar(&[("python/PYTHON.json", b"{}"), ("python/new.txt", b"new")])?;
+        let first_digest = hex::encode(Sha256::digest(&first));
+        let second_digest = hex::encode(Sha256::digest(&second));
+
+        let extract = |data: &[u8], digest: &str| -> Result<bool> {
+            extract_archive_cached(&extract_dir, Some(digest), |dest| {
+                extract_tar(&logger, data, dest, &options)
+            })
+        };
+
+        assert!(extract(&first, &first_digest)?);
+        assert!(!extract(&first, &first_digest)?);
+        assert_eq!(
+            std::fs::read_to_string(extract_dir.join(EXTRACTION_MARKER))?,
+            first_digest
+        );
+
+        // Swapping the archive re-extracts and removes the old files.
+        assert!(extract(&second, &second_digest)?);
+        assert!(!extract_dir.join("python").join("old.txt").exists());
+        assert_eq!(
+            std::fs::read(extract_dir.join("python").join("new.txt"))?,
+            b"new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(extract_dir.join(EXTRACTION_MARKER))?,
+            second_digest
+        );
+        assert!(!extract(&second, &second_digest)?);
+
+        // A missing marker forces re-extraction.
+        std::fs::remove_file(extract_dir.join(EXTRACTION_MARKER))?;
+        assert!(extract(&second, &second_digest)?);
+
+        // Without a digest, an existing extraction is reused.
+        assert!(!extract_archive_cached(&extract_dir, None, |dest| {
+            extract_tar(&logger, &first[..], dest, &options)
+        })?);
+        assert!(extract_dir.join("python").join("new.txt").exists());
+
+        // Reader based extraction has no digest and reuses the extraction too.
+        // Analyzing the fixture fails, but nothing was extracted.
+        assert!(StandaloneDistribution::from_tar_with_options(
+            &logger,
+            &first[..],
+            &extract_dir,
+            &options
+        )
+        .is_err());
+        assert!(!extract_dir.join("python").join("old.txt").exists());
+        assert!(extract_dir.join("python").join("new.txt").exists());
+
+        // Without an existing extraction, extraction happens and no marker is
+        // written.
+        std::fs::remove_dir_all(extract_dir.join("python"))?;
+        std::fs::remove_file(extract_dir.join(EXTRACTION_MARKER))?;
+        assert!(extract_archive_cached(&extract_dir, None, |dest| {
+            extract_tar(&logger, &first[..], dest, &options)
+        })?);
+        assert!(extract_dir.join("python").join("old.txt").exists());
+        assert!(!extract_dir.join(EXTRACTION_MARKER).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_tar_zst_bytes() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+        let extract_dir = temp_dir.path().join("extract");
+        let data = include_bytes!("testdata/version4.tar.zst");
+
+        // The fixture is a valid archive holding an unsupported distribution.
+        let err =
+            StandaloneDistribution::from_tar_zst_bytes(&logger, data, &extract_dir).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected version 5 or 6 standalone distribution; found version 4"
+        );
+        assert_eq!(
+            err.downcast_ref::<DistributionError>(),
+            Some(&DistributionError::UnsupportedJsonVersion {
+                found: "4".to_string()
+            })
+        );
+        assert!(extract_dir.join("python").join("PYTHON.json").exists());
+
+        let err =
+            StandaloneDistribution::from_directory_with_logger(&logger, &extract_dir).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DistributionError>(),
+            Some(&DistributionError::UnsupportedJsonVersion {
+                found: "4".to_string()
+            })
+        );
+        assert_eq!(
+            std::fs::read_to_string(extract_dir.join(EXTRACTION_MARKER))?,
+            hex::encode(Sha256::digest(data))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_tar_compressed_files() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        for (filename, data) in &[
+            (
+                "version4.tar.zst",
+                &include_bytes!("testdata/version4.tar.zst")[..],
+            ),
+            (
+                "version4.tar.gz",
+                &include_bytes!("testdata/version4.tar.gz")[..],
+            ),
+            (
+                "version4.tar.xz",
+                &include_bytes!("testdata/version4.tar.xz")[..],
+            ),
+        ] {
+            let path = temp_dir.path().join(filename);
+            std::fs::write(&path, data)?;
+            let extract_dir = temp_dir.path().join(format!("{}.extract", filename));
+
+            // All fixtures hold the same unsupported distribution.
+            let err = StandaloneDistribution::from_tar_zst_file(&logger, &path, &extract_dir)
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "expected version 5 or 6 standalone distribution; found version 4"
+            );
+            assert_eq!(
+                std::fs::read(extract_dir.join("python").join("PYTHON.json"))?,
+                b"{\"version\": \"4\"}\n"
+            );
+        }
+
+        let err = StandaloneDistribution::from_tar_gz(
+            &logger,
+            &include_bytes!("testdata/version4.tar.gz")[..],
+            &temp_dir.path().join("gz-stream"),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("found version 4"));
+
+        let err = StandaloneDistribution::from_tar_xz(
+            &logger,
+            &include_bytes!("testdata/version4.tar.xz")[..],
+            &temp_dir.path().join("xz-stream"),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("found version 4"));
+
+        let path = temp_dir.path().join("python.tar.bz2");
+        std::fs::write(&path, b"")?;
+        assert!(StandaloneDistribution::from_tar_zst_file(
+            &logger,
+            &path,
+            &temp_dir.path().join("bz2"),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_bytecode_magic() -> Result<()> {
+        let dist = get_default_distribution()?;
+        let mut compiler = dist.create_bytecode_compiler()?;
+
+        dist.verify_bytecode_magic(&mut compiler)?;
+
+        let mut mismatched = (**dist).clone();
+        mismatched.python_bytecode_magic_number = "00000000".to_string();
+        let err = mismatched.verify_bytecode_magic(&mut compiler).unwrap_err();
+        assert!(err.to_string().ends_with("but distribution has 00000000"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invoke_python_output() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+        let python_paths = resolve_python_paths(
+            &distribution.base_dir.join("python").join("install"),
+            &distribution.version,
+        );
+
+        let status = invoke_python(
+            &python_paths,
+            &logger,
+            &[
+                "-c",
+                "import sys; print('stdout'); print('stderr', file=sys.stderr)",
+            ],
+        )?;
+        assert!(status.success());
+
+        let (capturing_logger, drain) = get_capturing_logger();
+        let status = invoke_python(
+            &python_paths,
+            &capturing_logger,
+            &[
+                "-c",
+                "import sys; sys.stdout.buffer.write(b'a\\xffb\\n'); sys.stderr.buffer.write(b'c\\xfed')",
+            ],
+        )?;
+        assert!(status.success());
+        let messages = drain.messages.lock().unwrap();
+        assert!(messages.contains(&"a\u{fffd}b".to_string()));
+        assert!(messages.contains(&"c\u{fffd}d".to_string()));
+        drop(messages);
+
+        let status = invoke_python(&python_paths, &logger, &["-c", "import sys; sys.exit(3)"])?;
+        assert_eq!(status.code(), Some(3));
+        assert!(
+            invoke_python_checked(&python_paths, &logger, &["-c", "import sys; sys.exit(3)"])
+                .is_err()
+        );
+        invoke_python_checked(&python_paths, &logger, &["-c", "pass"])?;
+
+        let mut missing = python_paths.clone();
+        missing.python_exe = python_paths.python_exe.with_file_name("does-not-exist");
+        let err = invoke_python(&missing, &logger, &["-c", "pass"]).unwrap_err();
+        assert!(format!("{}", err).contains("does-not-exist -c pass"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest() -> Result<()> {
+        let temp_dir = new_temp_dir("test")?;
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("python").join("lib"))?;
+        std::fs::write(root.join("python").join("a.txt"), b"a")?;
+        std::fs::write(root.join("python").join("lib").join("b.txt"), b"b")?;
+
+        let manifest = format_manifest(&file_tree_digests(root)?);
+        let expected = parse_manifest(&manifest)?;
+        assert_eq!(
+            expected.keys().collect::<Vec<_>>(),
+            vec!["python/a.txt", "python/lib/b.txt"]
+        );
+        assert!(manifest_differences(&expected, &file_tree_digests(root)?).is_empty());
+
+        std::fs::write(root.join("python").join("a.txt"), b"tampered")?;
+        std::fs::remove_file(root.join("python").join("lib").join("b.txt"))?;
+        std::fs::write(root.join("python").join("c.txt"), b"c")?;
+        assert_eq!(
+            manifest_differences(&expected, &file_tree_digests(root)?),
+            vec![
+                "modified: python/a.txt",
+                "removed: python/lib/b.txt",
+                "added: python/c.txt"
+            ]
+        );
+
+        assert!(parse_manifest("not a manifest\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_precompiled_bytecode() -> Result<()> {
+        let mut dist = (**get_default_distribution()?).clone();
+        let temp_dir = new_temp_dir("test")?;
+
+        let source_path = temp_dir.path().join("foo.py");
+        std::fs::write(&source_path, b"x = 1\n")?;
+        let metadata = std::fs::metadata(&source_path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as u32;
+
+        let pyc_path = temp_dir.path().join("foo.pyc");
+        dist.py_bytecode.insert("foo".to_string(), pyc_path.clone());
+
+        let source = PythonModuleSource {
+            name: "foo".to_string(),
+            source: DataLocation::Path(source_path),
+            is_package: false,
+            cache_tag: dist.cache_tag.clone(),
+            is_stdlib: true,
+            is_test: false,
+        };
+
+        let magic = hex::decode(&dist.python_bytecode_magic_number)?;
+        let write_pyc = |magic: &[u8], flags: u32, size: u32| -> Result<()> {
+            let mut data = magic.to_vec();
+            data.extend(&flags.to_le_bytes());
+            data.extend(&mtime.to_le_bytes());
+            data.extend(&size.to_le_bytes());
+            data.extend(b"code");
+            std::fs::write(&pyc_path, data)?;
+            Ok(())
+        };
+
+        write_pyc(&magic, 0, 6)?;
+        let bytecode = dist.precompiled_bytecode(&source)?.unwrap();
+        assert_eq!(bytecode.resolve_bytecode()?, b"code");
+        assert!(bytecode.is_stdlib);
+
+        // Stale relative to source.
+        write_pyc(&magic, 0, 7)?;
+        assert!(dist.precompiled_bytecode(&source)?.is_none());
+
+        // Incompatible magic number.
+        write_pyc(b"\0\0\r\n", 0, 6)?;
+        assert!(dist.precompiled_bytecode(&source)?.is_none());
+
+        // Hash based.
+        write_pyc(&magic, 1, 0)?;
+        assert!(dist.precompiled_bytecode(&source)?.is_some());
+        write_pyc(&magic, 3, 0)?;
+        assert!(dist.precompiled_bytecode(&source)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_optimization_level_patterns() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+
+        let mut policy = distribution.create_packaging_policy()?;
+        policy.set_include_test(true);
+        policy.add_bytecode_optimization_level("test.*", BytecodeOptimizationLevel::Two)?;
+
+        let builder = distribution.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            env!("HOST"),
+            "testapp",
+            BinaryLibpythonLinkMode::Default,
+            &policy,
+            &EmbeddedPythonConfig::default(),
+        )?;
+
+        let resources = builder.iter_resources().collect::<BTreeMap<_, _>>();
+
+        let test_support = resources.get(&"test.support".to_string()).unwrap();
+        assert!(test_support.in_memory_bytecode.is_none());
+        assert!(test_support.in_memory_bytecode_opt2.is_some());
+
+        let json = resources.get(&"json".to_string()).unwrap();
+        assert!(json.in_memory_bytecode.is_some());
+        assert!(json.in_memory_bytecode_opt2.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_distribution_stdlib() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+
+        let mut policy = distribution.create_packaging_policy()?;
+        policy.set_include_distribution_stdlib(false);
+
+        let builder = distribution.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            env!("HOST"),
+            "testapp",
+            BinaryLibpythonLinkMode::Default,
+            &policy,
+            &EmbeddedPythonConfig::default(),
+        )?;
+
+        assert!(builder
+            .iter_resources()
+            .any(|(name, _)| name == "importlib"));
+        assert!(builder
+            .iter_resources()
+            .any(|(name, _)| name == "encodings"));
+        assert!(!builder.iter_resources().any(|(name, _)| name == "json"));
+        assert!(!builder.iter_resources().any(|(name, _)| name == "email"));
+        assert!(builder.builtin_extension_module_names().any(|x| x == "_io"));
+
+        let names = builder
+            .iter_resources()
+            .map(|(name, _)| name.clone())
+            .collect::<BTreeSet<_>>();
+        for name in &[
+            "encodings.utf_8",
+            "importlib.util",
+            "os",
+            "stat",
+            "_collections_abc",
+        ] {
+            assert!(names.contains(*name), "{} is missing", name);
+        }
+        if cfg!(windows) {
+            assert!(names.contains("ntpath"));
+        } else {
+            assert!(names.contains("posixpath"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_directory_light() -> Result<()> {
+        let logger = get_logger()?;
+        let full = get_default_distribution()?;
+
+        let light = StandaloneDistribution::from_directory_light(&logger, &full.base_dir)?;
+
+        assert_eq!(light.py_modules, full.py_modules);
+        assert_eq!(light.resources, full.resources);
+        assert!(light.extension_modules.is_empty());
+        assert!(light.objs_core.is_empty());
+
+        assert!(light
+            .as_python_executable_builder(
+                &logger,
+                env!("HOST"),
+                env!("HOST"),
+                "testapp",
+                BinaryLibpythonLinkMode::Default,
+                &PythonPackagingPolicy::default(),
+                &EmbeddedPythonConfig::default(),
+            )
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_directory_stdlib_only() -> Result<()> {
+        let logger = get_logger()?;
+        let full = get_default_distribution()?;
+
+        let stdlib = StandaloneDistribution::from_directory_stdlib_only(&full.base_dir)?;
+
+        assert_eq!(stdlib.py_modules, full.py_modules);
+        assert_eq!(stdlib.resources, full.resources);
+        assert_eq!(stdlib.cache_tag, full.cache_tag);
+        assert_eq!(stdlib.version, full.version);
+        assert!(stdlib.py_bytecode.is_empty());
+        assert!(stdlib.license_infos.is_empty());
+        assert!(stdlib.extension_modules.is_empty());
+        assert!(stdlib.libpython_shared_library.is_none());
+
+        assert_eq!(stdlib.source_modules()?.len(), full.source_modules()?.len());
+
+        let res = stdlib.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            env!("HOST"),
+            "testapp",
+            BinaryLibpythonLinkMode::Default,
+            &PythonPackagingPolicy::default(),
+            &EmbeddedPythonConfig::default(),
+        );
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Python distribution was only analyzed for resources and cannot build binaries"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_target_triple() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+
+        let res = distribution.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            "x86_64-unknown-linux-gun",
+            "testapp",
+            BinaryLibpythonLinkMode::Default,
+            &distribution.create_packaging_policy()?,
+            &EmbeddedPythonConfig::default(),
+        );
+        let err = format!("{}", res.err().unwrap());
+        assert!(err.starts_with("unrecognized target triple: x86_64-unknown-linux-gun;"));
+        assert!(err.contains("x86_64-unknown-linux-gnu"));
+
+        validate_target_triple("x86_64-pc-windows-msvc")?;
+        validate_target_triple("aarch64-apple-darwin")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_with_backoff() -> Result<()> {
+        let logger = get_logger()?;
+
+        // Transient failures are retried until the operation succeeds.
+        let mut calls = 0;
+        let value = retry_with_backoff(&logger, 4, std::time::Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(anyhow!("sharing violation"))
+            } else {
+                Ok(calls)
+            }
+        })?;
+        assert_eq!(value, 3);
+        assert_eq!(calls, 3);
+
+        // Persistent failures propagate the final error.
+        let mut calls = 0;
+        let res: Result<()> =
+            retry_with_backoff(&logger, 3, std::time::Duration::from_millis(1), || {
+                calls += 1;
+                Err(anyhow!("failure {}", calls))
+            });
+        assert_eq!(calls, 3);
+        assert_eq!(res.unwrap_err().to_string(), "failure 3");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_hacked_base() -> Result<()> {
+        let temp_dir = new_temp_dir("test")?;
+
+        let mut dist = (**get_default_distribution()?).clone();
+        dist.venv_base = temp_dir.path().join("hacked_base");
+
+        // Cleaning up a missing directory is a no-op.
+        dist.cleanup_hacked_base()?;
+
+        std::fs::create_dir_all(dist.venv_base.join("lib"))?;
+        std::fs::write(dist.venv_base.join("lib").join("file"), b"data")?;
+        dist.cleanup_hacked_base()?;
+        assert!(!dist.venv_base.exists());
+
+        std::fs::create_dir_all(&dist.venv_base)?;
+        {
+            let _guard = dist.hacked_base_cleanup_guard()?;
+            assert!(dist.venv_base.exists());
+        }
+        assert!(!dist.venv_base.exists());
+
+        // The directory is only removed once no guard is using it.
+        std::fs::create_dir_all(&dist.venv_base)?;
+        let first = dist.hacked_base_cleanup_guard()?;
+        let second = dist.hacked_base_cleanup_guard()?;
+        drop(first);
+        assert!(dist.venv_base.exists());
+        drop(second);
+        assert!(!dist.venv_base.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hint_unoptimized_distribution() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        let mut dist = (**builder.distribution).clone();
+        dist.optimizations = "noopt".to_string();
+        builder.distribution = Arc::new(Box::new(dist));
+        assert_eq!(builder.distribution_optimizations(), "noopt");
+
+        let hint = "Python distribution is not built with PGO or LTO; consider using an optimized distribution for release builds";
+
+        let (logger, drain) = get_capturing_logger();
+        builder.hint_unoptimized_distribution(&logger, false);
+        assert!(drain.messages.lock().unwrap().is_empty());
+
+        builder.hint_unoptimized_distribution(&logger, true);
+        assert_eq!(*drain.messages.lock().unwrap(), vec![hint.to_string()]);
+
+        let mut dist = (**builder.distribution).clone();
+        dist.optimizations = "pgo+lto".to_string();
+        builder.distribution = Arc::new(Box::new(dist));
+        let (logger, drain) = get_capturing_logger();
+        builder.hint_unoptimized_distribution(&logger, true);
+        assert!(drain.messages.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_test_packages() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        assert!(dist.stdlib_test_packages().contains(&"test".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_suffixes() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        assert_eq!(dist.module_suffixes().source, vec![".py".to_string()]);
+        assert_eq!(
+            dist.module_suffixes().source,
+            dist.python_module_suffixes()?.source
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_datas_excluding() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        let all = dist.resource_datas()?;
+        let json_count = all
+            .iter()
+            .filter(|r| r.relative_name.ends_with(".json"))
+            .count();
+
+        let filtered = dist.resource_datas_excluding(&["*.json"])?;
+        assert_eq!(filtered.len(), all.len() - json_count);
+        assert!(!filtered.iter().any(|r| r.relative_name.ends_with(".json")));
+
+        let first = &all[0];
+        let filtered = dist.resource_datas_excluding(&[&format!(
+            "{}/{}",
+            first.leaf_package, first.relative_name
+        )])?;
+        assert_eq!(filtered.len(), all.len() - 1);
+
+        assert!(dist.resource_datas_excluding(&["[invalid"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatible_target_triples() -> Result<()> {
+        let glibc = vec![
+            "glibc-dynamic".to_string(),
+            "glibc-max-symbol-version:2.19".to_string(),
+        ];
+        let musl = vec!["static".to_string()];
+
+        assert_eq!(
+            compatible_target_triples("x86_64-pc-windows-msvc-shared", &[]),
+            vec!["x86_64-pc-windows-msvc".to_string()]
+        );
+        assert_eq!(
+            compatible_target_triples("x86_64-unknown-linux-gnu", &glibc),
+            vec![
+                "x86_64-unknown-linux-gnu".to_string(),
+                "x86_64-pc-linux-gnu".to_string(),
+                "x86_64-redhat-linux-gnu".to_string(),
+                "x86_64-suse-linux-gnu".to_string(),
+            ]
+        );
+        assert_eq!(
+            compatible_target_triples("x86_64-unknown-linux-musl", &musl),
+            vec![
+                "x86_64-unknown-linux-musl".to_string(),
+                "x86_64-alpine-linux-musl".to_string(),
+                "x86_64-chimera-linux-musl".to_string(),
+            ]
+        );
+        // Without the C runtime features, nothing else is known to be compatible.
+        assert_eq!(
+            compatible_target_triples("x86_64-unknown-linux-gnu", &[]),
+            vec!["x86_64-unknown-linux-gnu".to_string()]
+        );
+
+        let dist = get_default_distribution()?;
+        assert!(dist
+            .compatible_target_triples()
+            .contains(&env!("HOST").to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_triple_compatible() -> Result<()> {
+        assert!(is_target_triple_compatible(
+            "x86_64-unknown-linux-gnu",
+            &[],
+            "x86_64-unknown-linux-gnu"
+        ));
+        assert!(is_target_triple_compatible(
+            "x86_64-pc-windows-msvc-shared",
+            &[],
+            "x86_64-pc-windows-msvc"
+        ));
+        assert!(!is_target_triple_compatible(
+            "x86_64-unknown-linux-gnu",
+            &[],
+            "aarch64-unknown-linux-gnu"
+        ));
+        // Equivalent triples are accepted.
+        assert!(is_target_triple_compatible(
+            "x86_64-unknown-linux-gnu",
+            &["glibc-dynamic".to_string()],
+            "x86_64-pc-linux-gnu"
+        ));
+        assert!(is_target_triple_compatible(
+            "x86_64-unknown-linux-musl",
+            &["static".to_string()],
+            "x86_64-alpine-linux-musl"
+        ));
+        assert!(!is_target_triple_compatible(
+            "x86_64-unknown-linux-gnu",
+            &[],
+            "x86_64-pc-linux-gnu"
+        ));
+        assert!(!is_target_triple_compatible(
+            "x86_64-unknown-linux-gnu",
+            &["glibc-dynamic".to_string()],
+            "x86_64-unknown-linux-musl"
+        ));
+
+        let dist = get_default_distribution()?;
+        assert!(is_target_triple_compatible(
+            &dist.target_triple,
+            &dist.crt_features,
+            env!("HOST")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_extensions() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        dist.require_extensions(&["_ssl", "_hashlib", "_ctypes"])?;
+
+        let res = dist.require_extensions(&["_ssl", "does_not_exist"]);
+        assert!(res.is_err());
+        let message = format!("{}", res.unwrap_err());
+        assert!(message.contains("does_not_exist"));
+        assert!(!message.contains("_ssl"));
+
+        Ok(())
     }
 
-    fn add_relative_path_dynamic_extension_module(
-        &mut self,
-        prefix: &str,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        if extension_module.shared_library.is_none() {
-            return Err(anyhow!(
-                "extension module instance has no shared library data"
-            ));
-        }
+    #[test]
+    fn test_include_test_package() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
 
-        if self.distribution.is_extension_module_file_loadable() {
-            self.resources
-                .add_relative_path_extension_module(extension_module, prefix)
-        } else {
-            Err(anyhow!(
-                "loading extension modules from files not supported by this build configuration"
-            ))
-        }
-    }
+        let test_packages = distribution.list_test_packages();
+        assert!(test_packages.contains(&"json.tests"));
+        assert!(test_packages.contains(&"test.support"));
+
+        let mut policy = distribution.create_packaging_policy()?;
+        policy.set_include_test(false);
+        policy.include_test_package("json.tests");
+
+        let builder = distribution.as_python_executable_builder(
+            &logger,
+            env!("HOST"),
+            env!("HOST"),
+            "testapp",
+            BinaryLibpythonLinkMode::Default,
+            &policy,
+            &EmbeddedPythonConfig::default(),
+        )?;
+
+        let tests = builder
+            .iter_resources()
+            .filter(|(name, _)| is_stdlib_test_package(name))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
 
-    fn add_dynamic_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        if extension_module.shared_library.is_none() {
-            return Err(anyhow!(
-                "extension module instance has no shared library data"
-            ));
-        }
+        assert!(tests.contains(&"json.tests".to_string()));
+        assert!(tests
+            .iter()
+            .all(|name| name == "json.tests" || name.starts_with("json.tests.")));
 
-        match self.packaging_policy.get_resources_policy().clone() {
-            PythonResourcesPolicy::InMemoryOnly => {
-                if self.supports_in_memory_dynamically_linked_extension_loading {
-                    self.resources
-                        .add_in_memory_extension_module_shared_library(
-                            &extension_module.name,
-                            extension_module.is_package,
-                            &extension_module
-                                .shared_library
-                                .as_ref()
-                                .unwrap()
-                                .resolve()?,
-                        )
-                } else {
-                    Err(anyhow!("in-memory-only resources policy active but in-memory extension module importing not supported by this configuration: cannot load {}", extension_module.name))
-                }
-            }
-            PythonResourcesPolicy::FilesystemRelativeOnly(ref prefix) => {
-                if self.distribution.is_extension_module_file_loadable() {
-                    self.resources
-                        .add_relative_path_extension_module(extension_module, prefix)
-                } else {
-                    Err(anyhow!("filesystem-relative-only policy active but file-based extension module loading not supported by this configuration"))
-                }
-            }
-            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(ref prefix) => {
-                if self.supports_in_memory_dynamically_linked_extension_loading {
-                    self.resources
-                        .add_in_memory_extension_module_shared_library(
-                            &extension_module.name,
-                            extension_module.is_package,
-                            &extension_module
-                                .shared_library
-                                .as_ref()
-                                .unwrap()
-                                .resolve()?,
-                        )
-                } else if self.distribution.is_extension_module_file_loadable() {
-                    self.resources
-                        .add_relative_path_extension_module(extension_module, prefix)
-                } else {
-                    Err(anyhow!("prefer-in-memory-fallback-filesystem-relative policy active but could not find a mechanism to add an extension module"))
-                }
-            }
-        }
+        Ok(())
     }
 
-    fn add_static_extension_module(
-        &mut self,
-        extension_module: &PythonExtensionModule,
-    ) -> Result<()> {
-        self.resources
-            .add_builtin_extension_module(extension_module)
+    #[test]
+    fn test_inittab_build_inputs() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        let (source, cflags) = dist.inittab_build_inputs();
+        assert!(source.starts_with(&dist.base_dir));
+        assert!(source.exists());
+        assert_eq!(cflags, dist.inittab_cflags.as_slice());
+
+        Ok(())
     }
 
-    fn filter_resources_from_files(
-        &mut self,
-        logger: &slog::Logger,
-        files: &[&Path],
-        glob_patterns: &[&str],
-    ) -> Result<()> {
-        self.resources
-            .filter_from_files(logger, files, glob_patterns)
+    #[test]
+    fn test_build_info_debug() -> Result<()> {
+        let dist = get_default_distribution()?;
+        let build_info = dist.build_info_debug();
+
+        assert!(!build_info.core_objects().is_empty());
+        assert!(dist
+            .inittab_object
+            .ends_with(Path::new(build_info.inittab_object())));
+        assert!(build_info.extension_variants().contains_key("_ssl"));
+
+        let json: serde_json::Value = serde_json::from_str(&build_info.to_json()?)?;
+        assert!(json.get("core").is_some());
+        assert!(json.get("extensions").is_some());
+
+        Ok(())
     }
 
-    fn requires_jemalloc(&self) -> bool {
-        self.config.raw_allocator == RawAllocator::Jemalloc
+    #[test]
+    fn test_validate_object_file_architectures() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        dist.validate_object_file_architectures()?;
+
+        let mut wrong = (**dist).clone();
+        wrong.target_triple = "powerpc64-unknown-linux-gnu".to_string();
+
+        let res = wrong.validate_object_file_architectures();
+        assert!(res.is_err());
+        assert!(format!("{}", res.unwrap_err()).contains("wrong architecture"));
+
+        Ok(())
     }
 
-    fn as_embedded_python_binary_data(
-        &self,
-        logger: &slog::Logger,
-        opt_level: &str,
-    ) -> Result<EmbeddedPythonBinaryData> {
-        let resources = self.resources.package(logger, &self.python_exe)?;
-        let mut extra_files = resources.extra_install_files()?;
-        let linking_info = self.resolve_python_linking_info(logger, opt_level, &resources)?;
-        let resources = EmbeddedResourcesBlobs::try_from(resources)?;
+    #[test]
+    fn test_extension_loading_capability() -> Result<()> {
+        let dist = get_default_distribution()?;
 
-        if self.link_mode == LibpythonLinkMode::Dynamic {
-            if let Some(p) = &self.distribution.libpython_shared_library {
-                let manifest_path = Path::new(p.file_name().unwrap());
-                let content = FileContent {
-                    data: std::fs::read(&p)?,
-                    executable: false,
-                };
+        assert_eq!(
+            dist.extension_loading_capability("does_not_exist"),
+            ExtensionLoadingCapability::Unavailable
+        );
 
-                extra_files.add_file(&manifest_path, &content)?;
+        for (name, variants) in &dist.extension_modules {
+            let capability = dist.extension_loading_capability(name);
+
+            if variants.iter().any(|em| !em.object_file_data.is_empty()) {
+                assert!(
+                    capability == ExtensionLoadingCapability::BuiltinOnly
+                        || capability == ExtensionLoadingCapability::BuiltinOrSharedLibrary
+                );
+            }
+
+            if !dist.is_extension_module_file_loadable() {
+                assert_ne!(capability, ExtensionLoadingCapability::SharedLibraryOnly);
+                assert_ne!(
+                    capability,
+                    ExtensionLoadingCapability::BuiltinOrSharedLibrary
+                );
             }
         }
 
-        Ok(EmbeddedPythonBinaryData {
-            config: self.config.clone(),
-            linking_info,
-            resources,
-            extra_files,
-            host: self.host_triple.clone(),
-            target: self.target_triple.clone(),
-        })
+        Ok(())
     }
-}
 
-#[cfg(test)]
-pub mod tests {
-    use {
-        super::*, crate::py_packaging::distribution::DistributionFlavor,
-        crate::python_distributions::PYTHON_DISTRIBUTIONS, crate::testutil::*,
-        python_packaging::policy::ExtensionModuleFilter,
-    };
+    #[test]
+    fn test_retain_resources() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
 
-    /// Defines construction options for a `StandalonePythonExecutableBuilder`.
-    ///
-    /// This is mostly intended to be used by tests, to reduce boilerplate for
-    /// constructing instances.
-    pub struct StandalonePythonExecutableBuilderOptions {
-        pub logger: Option<slog::Logger>,
-        pub host_triple: String,
-        pub target_triple: String,
-        pub distribution_flavor: DistributionFlavor,
-        pub app_name: String,
-        pub libpython_link_mode: BinaryLibpythonLinkMode,
-        pub extension_module_filter: ExtensionModuleFilter,
+        let before = builder.iter_resources().count();
+        let removed = builder.retain_resources(|name, _| name.starts_with("email"))?;
+
+        assert!(removed > 0);
+        assert_eq!(builder.iter_resources().count(), before - removed);
+        assert!(builder.iter_resources().any(|(name, _)| name == "email"));
+        assert!(builder
+            .iter_resources()
+            .all(|(name, _)| name.starts_with("email")));
+
+        Ok(())
     }
 
-    impl Default for StandalonePythonExecutableBuilderOptions {
-        fn default() -> Self {
-            Self {
-                logger: None,
-                host_triple: env!("HOST").to_string(),
-                target_triple: env!("HOST").to_string(),
-                distribution_flavor: DistributionFlavor::Standalone,
-                app_name: "testapp".to_string(),
-                libpython_link_mode: BinaryLibpythonLinkMode::Default,
-                extension_module_filter: ExtensionModuleFilter::Minimal,
+    #[test]
+    fn test_write_combined_notice() -> Result<()> {
+        let builder = get_standalone_executable_builder()?;
+        let temp_dir = new_temp_dir("test")?;
+        let path = temp_dir.path().join("NOTICE");
+
+        builder.write_combined_notice(&path)?;
+        let notice = std::fs::read_to_string(&path)?;
+
+        for (name, infos) in builder.license_infos() {
+            assert!(notice.contains(&format!("\n{}\nLicenses: ", name)));
+
+            for li in infos {
+                assert!(notice.contains(&li.licenses.join(", ")));
             }
         }
-    }
 
-    impl StandalonePythonExecutableBuilderOptions {
-        fn new_builder(
-            &self,
-        ) -> Result<(
-            Arc<Box<StandaloneDistribution>>,
-            Box<dyn PythonBinaryBuilder>,
-        )> {
-            let logger = if let Some(logger) = &self.logger {
-                logger.clone()
-            } else {
-                get_logger()?
-            };
+        assert!(builder.license_infos().len() > 1);
 
-            let record = PYTHON_DISTRIBUTIONS
-                .find_distribution(&self.target_triple, &self.distribution_flavor)
-                .ok_or_else(|| anyhow!("could not find Python distribution"))?;
+        Ok(())
+    }
 
-            let distribution = get_distribution(&record.location)?;
+    #[test]
+    fn test_license_lookup() -> Result<()> {
+        let distribution = get_default_distribution()?;
 
-            let mut policy = PythonPackagingPolicy::default();
-            policy.set_extension_module_filter(self.extension_module_filter.clone());
+        let core = distribution.core_licenses().unwrap();
+        assert!(!core.is_empty());
+        assert!(distribution.extension_licenses("python").is_none());
+        assert!(distribution.extension_licenses("does_not_exist").is_none());
 
-            let config = EmbeddedPythonConfig::default();
+        let ssl = distribution.extension_licenses("_ssl").unwrap();
+        assert_eq!(ssl.len(), distribution.license_infos["_ssl"].len());
+        assert!(ssl
+            .iter()
+            .any(|li| li.licenses.iter().any(|l| l == "OpenSSL")));
+        assert!(ssl.iter().all(|li| !li.license_text.is_empty()));
 
-            Ok((
-                distribution.clone(),
-                distribution.as_python_executable_builder(
-                    &logger,
-                    &self.host_triple,
-                    &self.target_triple,
-                    &self.app_name,
-                    self.libpython_link_mode.clone(),
-                    &policy,
-                    &config,
-                )?,
-            ))
-        }
+        Ok(())
     }
 
-    // TODO switch users to StandalonePythonExecutableBuilderOptions
-    pub fn get_standalone_executable_builder() -> Result<StandalonePythonExecutableBuilder> {
+    #[test]
+    fn test_extension_module_link_requirements() -> Result<()> {
         let distribution = get_default_distribution()?;
 
-        // Link mode is static unless we're a dynamic distribution on Windows.
-        let link_mode = if distribution.target_triple.contains("pc-windows")
-            && distribution.python_symbol_visibility == "dllexport"
-        {
-            LibpythonLinkMode::Dynamic
-        } else {
-            LibpythonLinkMode::Static
-        };
+        assert!(distribution
+            .extension_module_variants("does_not_exist")
+            .is_none());
 
-        let resources = PrePackagedResources::new(
-            &PythonResourcesPolicy::InMemoryOnly,
-            &distribution.cache_tag,
-        );
+        let variants = distribution.extension_module_variants("_ssl").unwrap();
+        assert!(!variants.is_empty());
 
-        let mut packaging_policy = distribution.create_packaging_policy()?;
-        packaging_policy.set_extension_module_filter(ExtensionModuleFilter::Minimal);
-        packaging_policy.set_resources_policy(PythonResourcesPolicy::InMemoryOnly);
+        let ssl = distribution
+            .iter_extension_module_link_requirements()
+            .filter(|(name, _, _)| *name == "_ssl")
+            .collect::<Vec<_>>();
+        assert_eq!(ssl.len(), variants.iter().count());
 
-        let config = EmbeddedPythonConfig::default();
+        for (_, em, links) in ssl {
+            assert_eq!(links, em.link_libraries);
 
-        let python_exe = distribution.python_exe.clone();
+            let names = links.iter().map(|l| l.name.as_str()).collect::<Vec<_>>();
+            assert!(names.iter().any(|n| n.contains("ssl")));
+            assert!(names.iter().any(|n| n.contains("crypto")));
+            assert!(links
+                .iter()
+                .filter(|l| l.name.contains("ssl") || l.name.contains("crypto"))
+                .all(|l| l.static_library.is_some() || l.dynamic_library.is_some()));
+        }
 
-        let mut builder = StandalonePythonExecutableBuilder {
-            host_triple: env!("HOST").to_string(),
-            target_triple: env!("HOST").to_string(),
-            exe_name: "testapp".to_string(),
-            distribution: distribution.clone(),
-            link_mode,
-            supports_in_memory_dynamically_linked_extension_loading: false,
-            packaging_policy: packaging_policy.clone(),
-            resources,
-            config,
-            python_exe,
-        };
+        Ok(())
+    }
 
-        builder.add_distribution_resources(&packaging_policy)?;
+    #[test]
+    fn test_builder_license_infos() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        let distribution = builder.distribution.clone();
+
+        assert!(builder.license_infos().contains_key("python"));
+
+        assert!(!builder.license_infos().contains_key("_ssl"));
+
+        builder.add_distribution_extension_module(
+            distribution
+                .extension_modules
+                .get("_ssl")
+                .unwrap()
+                .default_variant(),
+        )?;
+        let infos = builder.license_infos();
+        let ssl = infos.get("_ssl").unwrap();
+        assert!(ssl
+            .iter()
+            .any(|li| li.licenses.iter().any(|l| l == "OpenSSL")));
 
-        Ok(builder)
-    }
+        builder.retain_resources(|resource_name, _| resource_name != "_ssl")?;
+        assert!(!builder.license_infos().contains_key("_ssl"));
 
-    pub fn get_embedded(logger: &slog::Logger) -> Result<EmbeddedPythonBinaryData> {
-        let exe = get_standalone_executable_builder()?;
-        exe.as_embedded_python_binary_data(logger, "0")
+        Ok(())
     }
 
     #[test]
-    fn test_write_embedded_files() -> Result<()> {
-        let logger = get_logger()?;
-        let embedded = get_embedded(&logger)?;
-        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+    fn test_collect_licenses() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        let distribution = builder.distribution.clone();
 
-        embedded.write_files(temp_dir.path())?;
+        let minimal = distribution.collect_licenses(&builder)?;
+        assert!(!minimal.is_empty());
+
+        let mut policy = builder.python_packaging_policy().clone();
+        policy.set_extension_module_filter(ExtensionModuleFilter::All);
+        builder.add_full_stdlib(&get_logger()?, Some(&policy))?;
+
+        let all = distribution.collect_licenses(&builder)?;
+        assert!(all.len() > minimal.len());
+
+        let filenames = all
+            .iter()
+            .map(|li| li.license_filename.clone())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(filenames.len(), all.len());
 
         Ok(())
     }
 
     #[test]
-    fn test_stdlib_annotations() -> Result<()> {
-        let distribution = get_default_distribution()?;
+    fn test_accelerator_pair_problems() {
+        let names = |names: &[&str]| names.iter().map(|x| x.to_string()).collect::<BTreeSet<_>>();
+
+        assert!(accelerator_pair_problems(&names(&[])).is_empty());
+        assert!(accelerator_pair_problems(&names(&["_json", "json", "_ssl", "ssl"])).is_empty());
+        assert_eq!(
+            accelerator_pair_problems(&names(&["_pickle", "ssl", "json"])),
+            vec![
+                "json is present without _json; its slower pure Python implementation will be used"
+                    .to_string(),
+                "_pickle is present without pickle".to_string(),
+                "ssl is present without _ssl, which it requires".to_string(),
+            ]
+        );
+    }
 
-        for module in distribution.source_modules()? {
-            assert!(module.is_stdlib);
+    #[test]
+    fn test_check_accelerator_pairs() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+        let distribution = builder.distribution.clone();
+
+        // The minimal extension module filter excludes _ssl.
+        let missing = "ssl is present without _ssl, which it requires".to_string();
+        assert!(builder.check_accelerator_pairs().contains(&missing));
+
+        builder.add_distribution_extension_module(
+            distribution
+                .extension_modules
+                .get("_ssl")
+                .unwrap()
+                .default_variant(),
+        )?;
+        let before = builder.check_accelerator_pairs();
+        assert!(!before.contains(&missing));
+
+        builder.retain_resources(|name, _| name != "ssl")?;
+        let after = builder.check_accelerator_pairs();
+        assert_eq!(after.len(), before.len() + 1);
+        assert!(after.contains(&"_ssl is present without ssl".to_string()));
 
-            if module.name.starts_with("test") {
-                assert!(module.is_test);
-            }
-        }
+        Ok(())
+    }
 
-        for resource in distribution.resource_datas()? {
-            assert!(resource.is_stdlib);
-            if resource.leaf_package.starts_with("test") {
-                assert!(resource.is_test);
-            }
+    #[test]
+    fn test_unreachable_resources() -> Result<()> {
+        let logger = get_logger()?;
+        let mut builder = get_standalone_executable_builder()?;
+        builder.retain_resources(|_, _| false)?;
+
+        let cache_tag = builder.cache_tag().to_string();
+        for (name, source, is_package) in &[
+            ("app", "from pkg import a\n", false),
+            ("pkg", "", true),
+            ("pkg.a", "import helper\n", false),
+            ("pkg.b", "", false),
+            ("helper", "x = 1\n", false),
+            ("orphan", "import app\n", false),
+        ] {
+            builder.add_module_source(&PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(source.as_bytes().to_vec()),
+                is_package: *is_package,
+                cache_tag: cache_tag.clone(),
+                is_stdlib: false,
+                is_test: false,
+            })?;
         }
 
+        assert_eq!(
+            builder.unreachable_resources(&logger, &["app".to_string()])?,
+            vec!["orphan".to_string(), "pkg.b".to_string()]
+        );
+
         Ok(())
     }
 
     #[test]
-    fn test_minimal_extensions_present() -> Result<()> {
-        let builder: StandalonePythonExecutableBuilder = get_standalone_executable_builder()?;
-
-        let expected = builder
-            .distribution
-            .extension_modules
-            .iter()
-            .filter_map(|(_, extensions)| {
-                if extensions.default_variant().is_minimally_required() {
-                    Some(extensions.default_variant().name.clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        // Sanity check.
-        assert!(expected.contains(&"_io".to_string()));
+    fn test_set_main_module_source() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+
+        assert!(builder.set_main_module_source("def broken(:\n").is_err());
+        assert!(!builder
+            .iter_resources()
+            .any(|(name, _)| name == MAIN_MODULE_NAME));
+
+        builder.set_main_module_source("print('hello')\n")?;
+        assert!(builder
+            .iter_resources()
+            .any(|(name, _)| name == MAIN_MODULE_NAME));
+        assert!(!builder.iter_resources().any(|(name, _)| name == "__main__"));
+        assert_eq!(
+            builder.config.run_mode,
+            RunMode::Module {
+                module: MAIN_MODULE_NAME.to_string()
+            }
+        );
 
-        for name in &expected {
-            // All extensions annotated as required in the distribution are marked
-            // as built-ins.
-            assert!(builder.builtin_extension_module_names().any(|x| x == name));
+        Ok(())
+    }
 
-            // Built-in extension modules shouldn't be annotated as resources.
-            assert!(!builder.iter_resources().any(|(x, _)| x == name));
-        }
+    #[test]
+    fn test_add_generated_resource() -> Result<()> {
+        let mut builder = get_standalone_executable_builder()?;
+
+        builder.add_generated_resource("foo", "version.txt", &|| Ok(b"1.0".to_vec()))?;
+        assert!(builder
+            .iter_resources()
+            .any(|(name, resource)| name == "foo"
+                && resource
+                    .in_memory_resources
+                    .as_ref()
+                    .map(|x| x.contains_key("version.txt"))
+                    == Some(true)));
+
+        let res = builder.add_generated_resource("foo", "bad.txt", &|| Err(anyhow!("oops")));
+        assert!(res.is_err());
+        assert!(format!("{}", res.unwrap_err()).contains("bad.txt"));
 
         Ok(())
     }
@@ -2022,6 +6971,153 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_musl_dynamic_libpython() -> Result<()> {
+        let logger = get_logger()?;
+
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution("x86_64-unknown-linux-musl", &DistributionFlavor::Standalone)
+            .ok_or_else(|| anyhow!("could not find Python distribution"))?;
+        let distribution = get_distribution(&record.location)?;
+        let policy = distribution.create_packaging_policy()?;
+
+        let build = |distribution: &StandaloneDistribution, mode| {
+            distribution.as_python_executable_builder(
+                &logger,
+                env!("HOST"),
+                "x86_64-unknown-linux-musl",
+                "testapp",
+                mode,
+                &policy,
+                &EmbeddedPythonConfig::default(),
+            )
+        };
+
+        assert!(build(&**distribution, BinaryLibpythonLinkMode::Dynamic).is_err());
+
+        // Simulate a distribution providing a shared libpython.
+        let mut shared = (**distribution).clone();
+        shared.libpython_shared_library = Some(
+            shared
+                .base_dir
+                .join("python")
+                .join("install")
+                .join("lib")
+                .join(format!(
+                    "libpython{}.so",
+                    shared.python_major_minor_version()
+                )),
+        );
+
+        build(&shared, BinaryLibpythonLinkMode::Dynamic)?;
+        build(&shared, BinaryLibpythonLinkMode::Static)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_broken_extension_warnings() -> Result<()> {
+        let target_triple = "x86_64-unknown-linux-gnu";
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution(target_triple, &DistributionFlavor::Standalone)
+            .ok_or_else(|| anyhow!("could not find Python distribution"))?;
+        let distribution = get_distribution(&record.location)?;
+
+        let build = |filter: ExtensionModuleFilter| -> Result<Vec<String>> {
+            let (logger, drain) = get_capturing_logger();
+            let mut policy = distribution.create_packaging_policy()?;
+            policy.set_extension_module_filter(filter);
+
+            distribution.as_python_executable_builder(
+                &logger,
+                env!("HOST"),
+                target_triple,
+                "testapp",
+                BinaryLibpythonLinkMode::Default,
+                &policy,
+                &EmbeddedPythonConfig::default(),
+            )?;
+
+            let messages = drain.messages.lock().unwrap();
+
+            Ok(messages
+                .iter()
+                .filter(|message| message.starts_with("warning: excluding extension module"))
+                .cloned()
+                .collect())
+        };
+
+        assert!(build(ExtensionModuleFilter::All)?.contains(
+            &"warning: excluding extension module _crypt because it is broken on x86_64-unknown-linux-gnu"
+                .to_string()
+        ));
+        // _crypt isn't included by the minimal filter, so it isn't reported.
+        assert!(build(ExtensionModuleFilter::Minimal)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aarch64_target_triples() -> Result<()> {
+        let policy = get_default_distribution()?.create_packaging_policy()?;
+
+        assert!(policy
+            .broken_extensions("aarch64-unknown-linux-gnu")
+            .contains(&"_crypt"));
+        assert!(policy
+            .broken_extensions("aarch64-apple-darwin")
+            .contains(&"readline"));
+        assert!(policy
+            .broken_extensions("x86_64-apple-darwin")
+            .contains(&"readline"));
+        assert!(policy.broken_extensions("x86_64-apple-ios").is_empty());
+
+        assert_eq!(
+            libpython_link_support("aarch64-unknown-linux-gnu", false),
+            (true, true)
+        );
+        assert_eq!(
+            libpython_link_support("aarch64-apple-darwin", false),
+            (true, true)
+        );
+        assert_eq!(
+            libpython_link_support("x86_64-unknown-linux-musl", false),
+            (true, false)
+        );
+        assert_eq!(
+            libpython_link_support("x86_64-unknown-linux-musl", true),
+            (true, true)
+        );
+        assert_eq!(
+            libpython_link_support("x86_64-pc-windows-msvc-shared", true),
+            (false, true)
+        );
+        assert_eq!(
+            libpython_link_support("x86_64-pc-windows-msvc-static", false),
+            (true, false)
+        );
+        assert_eq!(
+            libpython_link_support("riscv64gc-unknown-linux-gnu", false),
+            (true, false)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_counts() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        assert_eq!(dist.extension_module_count(), dist.extension_modules.len());
+        assert!(dist.extension_module_count() <= dist.iter_extension_modules().count());
+
+        let builtins = dist.default_builtin_extension_count();
+        assert!(builtins > 0);
+        assert!(builtins <= dist.extension_module_count());
+
+        Ok(())
+    }
+
     #[test]
     fn test_windows_dynamic_extensions_sanity() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions {
@@ -2074,4 +7170,513 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_extract_tar_parallel() -> Result<()> {
+        let logger = get_logger()?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut files = vec![
+            ("python/PYTHON.json".to_string(), b"{}".to_vec()),
+            ("python/lib/a.py".to_string(), b"a = 1\n".to_vec()),
+            (
+                format!("python/lib/{}.py", "x".repeat(120)),
+                b"long".to_vec(),
+            ),
+        ];
+        for i in 0..20 {
+            files.push((format!("python/lib/pkg/m{}.py", i), vec![i as u8; 1000 * i]));
+        }
+        for (path, data) in &files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o444);
+            header.set_mtime(1_000_000);
+            builder.append_data(&mut header, path, data.as_slice())?;
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("a.py")?;
+        builder.append_data(&mut header, "python/lib/b.py", std::io::empty())?;
+        let archive = builder.into_inner()?;
+
+        let temp_dir = new_temp_dir("test")?;
+        let mut digests = vec![];
+
+        for threads in &[1, 4] {
+            let dest = temp_dir.path().join(format!("threads-{}", threads));
+            std::fs::create_dir_all(&dest)?;
+            let dest = std::fs::canonicalize(&dest)?;
+
+            let options = DistributionExtractOptions {
+                extract_threads: *threads,
+                materialize_symlinks: true,
+                ..DistributionExtractOptions::default()
+            };
+            extract_tar(&logger, archive.as_slice(), &dest, &options)?;
+
+            let a = dest.join("python").join("lib").join("a.py");
+            assert!(!std::fs::metadata(&a)?.permissions().readonly());
+            assert_eq!(
+                std::fs::read(dest.join("python").join("lib").join("b.py"))?,
+                b"a = 1\n"
+            );
+
+            digests.push(file_tree_digests(&dest)?);
+        }
+
+        assert_eq!(digests[0].len(), files.len() + 1);
+        assert_eq!(digests[0], digests[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tar_progress() -> Result<()> {
+        let logger = get_logger()?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in &["python/a.py", "python/b.py", "python/c.py"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(6);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, &b"a = 1\n"[..])?;
+        }
+        let archive = builder.into_inner()?;
+
+        let temp_dir = new_temp_dir("test")?;
+
+        for expected_entries in &[None, Some(3)] {
+            let calls = Arc::new(Mutex::new(vec![]));
+            let callback_calls = calls.clone();
+            let options = DistributionExtractOptions {
+                progress_callback: Some(ExtractProgressCallback(Arc::new(move |count, total| {
+                    callback_calls.lock().unwrap().push((count, total))
+                }))),
+                expected_entries: *expected_entries,
+                ..DistributionExtractOptions::default()
+            };
+
+            let dest = temp_dir.path().join(format!("{:?}", expected_entries));
+            std::fs::create_dir_all(&dest)?;
+            extract_tar(&logger, archive.as_slice(), &dest, &options)?;
+
+            assert_eq!(
+                *calls.lock().unwrap(),
+                vec![
+                    (1, *expected_entries),
+                    (2, *expected_entries),
+                    (3, *expected_entries)
+                ]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_zip_progress() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+        let dest = std::fs::canonicalize(temp_dir.path())?;
+
+        let mut zf = zip::ZipArchive::new(std::io::Cursor::new(
+            &include_bytes!("testdata/symlink.zip")[..],
+        ))?;
+        let total = zf.len();
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let callback_calls = calls.clone();
+        let options = DistributionExtractOptions {
+            materialize_symlinks: true,
+            progress_callback: Some(ExtractProgressCallback(Arc::new(move |count, total| {
+                callback_calls.lock().unwrap().push((count, total))
+            }))),
+            ..DistributionExtractOptions::default()
+        };
+        extract_zip(&logger, &mut zf, &dest, &options)?;
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            (1..=total)
+                .map(|count| (count, Some(total)))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tar_symlink_conversions() -> Result<()> {
+        let (logger, drain) = get_capturing_logger();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "python/lib/a.py", &b"a = 1\n"[..])?;
+        for link in &["python/lib/b.py", "python/lib/c.py"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_link_name("a.py")?;
+            builder.append_data(&mut header, link, std::io::empty())?;
+        }
+        let archive = builder.into_inner()?;
+
+        let temp_dir = new_temp_dir("test")?;
+        let dest = std::fs::canonicalize(temp_dir.path())?;
+
+        let options = DistributionExtractOptions {
+            materialize_symlinks: true,
+            ..DistributionExtractOptions::default()
+        };
+        extract_tar(&logger, archive.as_slice(), &dest, &options)?;
+
+        let messages = drain.messages.lock().unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|m| m.starts_with("converted symlink "))
+                .count(),
+            2
+        );
+        assert!(messages
+            .iter()
+            .any(|m| m == "converted 2 symlinks in distribution archive to file copies"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tar_materialize_symlinks() -> Result<()> {
+        let logger = get_logger()?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "python/lib/a.py", &b"a = 1\n"[..])?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("a.py")?;
+        builder.append_data(&mut header, "python/lib/b.py", std::io::empty())?;
+        let archive = builder.into_inner()?;
+
+        let temp_dir = new_temp_dir("test")?;
+
+        for materialize_symlinks in &[false, true] {
+            let dest = temp_dir
+                .path()
+                .join(format!("materialize-{}", materialize_symlinks));
+            std::fs::create_dir_all(&dest)?;
+            let dest = std::fs::canonicalize(&dest)?;
+
+            let options = DistributionExtractOptions {
+                materialize_symlinks: *materialize_symlinks,
+                ..DistributionExtractOptions::default()
+            };
+            extract_tar(&logger, archive.as_slice(), &dest, &options)?;
+
+            let link = dest.join("python").join("lib").join("b.py");
+            let file_type = std::fs::symlink_metadata(&link)?.file_type();
+            assert_eq!(file_type.is_symlink(), !*materialize_symlinks);
+            assert_eq!(file_type.is_file(), *materialize_symlinks);
+            assert_eq!(std::fs::read(&link)?, b"a = 1\n");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tar_preserve_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let logger = get_logger()?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o444);
+        builder.append_data(&mut header, "python/lib/a.py", &b"a = 1\n"[..])?;
+        let archive = builder.into_inner()?;
+
+        let temp_dir = new_temp_dir("test")?;
+
+        for preserve_permissions in &[false, true] {
+            let dest = temp_dir
+                .path()
+                .join(format!("preserve-{}", preserve_permissions));
+            std::fs::create_dir_all(&dest)?;
+
+            let options = DistributionExtractOptions {
+                preserve_permissions: *preserve_permissions,
+                ..DistributionExtractOptions::default()
+            };
+            extract_tar(&logger, archive.as_slice(), &dest, &options)?;
+
+            let permissions =
+                std::fs::metadata(dest.join("python").join("lib").join("a.py"))?.permissions();
+            assert_eq!(permissions.readonly(), *preserve_permissions);
+            if *preserve_permissions {
+                assert_eq!(permissions.mode() & 0o777, 0o444);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_zip_symlink() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+        let zip_data = include_bytes!("testdata/symlink.zip");
+
+        for materialize_symlinks in &[false, true] {
+            let dest = temp_dir
+                .path()
+                .join(format!("materialize-{}", materialize_symlinks));
+            std::fs::create_dir_all(&dest)?;
+            let dest = std::fs::canonicalize(dest)?;
+
+            let mut zf = zip::ZipArchive::new(std::io::Cursor::new(&zip_data[..]))?;
+            extract_zip(
+                &logger,
+                &mut zf,
+                &dest,
+                &DistributionExtractOptions {
+                    materialize_symlinks: *materialize_symlinks,
+                    ..DistributionExtractOptions::default()
+                },
+            )?;
+
+            let lib = dest.join("python").join("lib");
+            let target = lib.join("target.txt");
+            let link = lib.join("link.txt");
+
+            assert_eq!(std::fs::read(&target)?, b"symlink target\n");
+            assert_eq!(std::fs::read(&link)?, b"symlink target\n");
+            assert!(!std::fs::metadata(&target)?.permissions().readonly());
+            assert_eq!(
+                std::fs::symlink_metadata(&link)?.file_type().is_symlink(),
+                !*materialize_symlinks && cfg!(unix)
+            );
+        }
+
+        for materialize_symlinks in &[false, true] {
+            let dest = temp_dir
+                .path()
+                .join(format!("escape-{}", materialize_symlinks));
+            std::fs::create_dir_all(&dest)?;
+            let dest = std::fs::canonicalize(dest)?;
+
+            let mut zf = zip::ZipArchive::new(std::io::Cursor::new(
+                &include_bytes!("testdata/symlink-escape.zip")[..],
+            ))?;
+            let err = extract_zip(
+                &logger,
+                &mut zf,
+                &dest,
+                &DistributionExtractOptions {
+                    materialize_symlinks: *materialize_symlinks,
+                    ..DistributionExtractOptions::default()
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err.to_string(), "malicious symlink detected in archive");
+            assert!(!dest.join("python").join("lib").join("escape.txt").exists());
+        }
+
+        if cfg!(unix) {
+            for threads in &[1, 4] {
+                let dest = temp_dir.path().join(format!("parent-{}", threads));
+                std::fs::create_dir_all(&dest)?;
+                let dest = std::fs::canonicalize(dest)?;
+
+                let mut zf = zip::ZipArchive::new(std::io::Cursor::new(
+                    &include_bytes!("testdata/symlink-parent.zip")[..],
+                ))?;
+                let err = extract_zip(
+                    &logger,
+                    &mut zf,
+                    &dest,
+                    &DistributionExtractOptions {
+                        extract_threads: *threads,
+                        ..DistributionExtractOptions::default()
+                    },
+                )
+                .unwrap_err();
+                assert!(err.to_string().starts_with("refusing to extract "));
+                assert!(!dest
+                    .join("python")
+                    .join("real")
+                    .join("written.txt")
+                    .exists());
+            }
+        }
+
+        let zip_path = temp_dir.path().join("python.zip");
+        std::fs::write(&zip_path, &zip_data[..])?;
+        let extract_dir = temp_dir.path().join("extract");
+        let err = StandaloneDistribution::from_zip_file(
+            &logger,
+            &zip_path,
+            &extract_dir,
+            Some(&hex::encode(Sha256::digest(b"other"))),
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("sha256 of "));
+        assert!(!extract_dir.exists());
+
+        Ok(())
+    }
+
+    /// Write a zstd compressed tar archive of `files` to `path`.
+    fn write_tar_zst_fixture(path: &Path, files: &[(String, Vec<u8>)]) -> Result<()> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, data.as_slice())?;
+        }
+
+        std::fs::write(
+            path,
+            zstd::stream::encode_all(builder.into_inner()?.as_slice(), 3)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Extract the tar.zst archive at `path` into `dest` using the file reading path.
+    fn extract_tar_zst_file(
+        logger: &slog::Logger,
+        path: &Path,
+        dest: &Path,
+        options: &DistributionExtractOptions,
+    ) -> Result<()> {
+        let fh = std::fs::File::open(path)?;
+        let reader = BufReader::with_capacity(options.archive_buffer_size, fh);
+
+        extract_tar(logger, zstd_decoder(reader, options)?, dest, options)
+    }
+
+    #[test]
+    fn test_extract_buffer_sizes() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let files = (0..10)
+            .map(|i| {
+                (
+                    format!("python/lib/m{}.py", i),
+                    (0..100_000 * i)
+                        .map(|v| (v % 251) as u8)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let archive_path = temp_dir.path().join("python.tar.zst");
+        write_tar_zst_fixture(&archive_path, &files)?;
+
+        let default_options = DistributionExtractOptions::default();
+        let expected_sha256 = file_sha256(&archive_path, default_options.archive_buffer_size)?;
+
+        let mut digests = vec![];
+        for (i, size) in [1, default_options.zstd_buffer_size, 64 * 1024 * 1024]
+            .iter()
+            .enumerate()
+        {
+            let options = DistributionExtractOptions {
+                archive_buffer_size: *size,
+                zstd_buffer_size: *size,
+                ..DistributionExtractOptions::default()
+            };
+
+            assert_eq!(file_sha256(&archive_path, *size)?, expected_sha256);
+
+            let dest = temp_dir.path().join(format!("extract-{}", i));
+            std::fs::create_dir_all(&dest)?;
+            extract_tar_zst_file(&logger, &archive_path, &dest, &options)?;
+
+            for (name, data) in &files {
+                assert_eq!(&std::fs::read(dest.join(name))?, data);
+            }
+
+            digests.push(file_tree_digests(&dest)?);
+        }
+
+        assert_eq!(digests[0].len(), files.len());
+        assert_eq!(digests[0], digests[1]);
+        assert_eq!(digests[0], digests[2]);
+
+        Ok(())
+    }
+
+    /// Compares extraction times for various buffer sizes.
+    ///
+    /// Run with `cargo test --release bench_extract_buffer_sizes -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_extract_buffer_sizes() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = new_temp_dir("test")?;
+
+        let files = (0..2000)
+            .map(|i| {
+                (
+                    format!("python/lib/pkg{}/m{}.py", i % 50, i),
+                    (0..(i % 100) * 1000)
+                        .map(|v| ((v * 7 + i) % 251) as u8)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let archive_path = temp_dir.path().join("python.tar.zst");
+        write_tar_zst_fixture(&archive_path, &files)?;
+
+        for size in &[512, 8 * 1024, 128 * 1024, 1024 * 1024, 16 * 1024 * 1024] {
+            let options = DistributionExtractOptions {
+                archive_buffer_size: *size,
+                zstd_buffer_size: *size,
+                ..DistributionExtractOptions::default()
+            };
+
+            let dest = temp_dir.path().join(format!("extract-{}", size));
+            std::fs::create_dir_all(&dest)?;
+
+            let start = std::time::Instant::now();
+            extract_tar_zst_file(&logger, &archive_path, &dest, &options)?;
+            println!("buffer size {:>9}: {:?}", size, start.elapsed());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_progress() {
+        let logger = get_logger().unwrap();
+
+        let mut options = DistributionExtractOptions::default();
+        options.progress_interval = Some(100);
+        options.expected_size = Some(400);
+
+        let mut progress = ExtractProgress::new(&options);
+        progress.record(&logger, 50);
+        assert_eq!(progress.next_report, 100);
+        progress.record(&logger, 250);
+        assert_eq!(progress.entries, 2);
+        assert_eq!(progress.bytes, 300);
+        assert_eq!(progress.next_report, 400);
+        assert_eq!(progress.describe(), "2 entries, 300 of 400 bytes (75%)");
+
+        options.expected_size = None;
+        let progress = ExtractProgress::new(&options);
+        assert_eq!(progress.describe(), "0 entries, 0 bytes");
+    }
 }
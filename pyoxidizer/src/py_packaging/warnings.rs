@@ -0,0 +1,87 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Tracking of warnings emitted while building binaries.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    slog::warn,
+};
+
+/// Records warnings encountered when collecting and packaging resources.
+///
+/// Warnings are always logged. When warnings are treated as errors, `check()`
+/// fails with every recorded warning, so all problems are reported at once
+/// rather than just the first.
+#[derive(Clone, Debug, Default)]
+pub struct BuildWarnings {
+    warnings_as_errors: bool,
+    warnings: Vec<String>,
+}
+
+impl BuildWarnings {
+    pub fn new(warnings_as_errors: bool) -> Self {
+        Self {
+            warnings_as_errors,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Set whether recorded warnings cause `check()` to fail.
+    pub fn set_warnings_as_errors(&mut self, enabled: bool) {
+        self.warnings_as_errors = enabled;
+    }
+
+    /// Log and record a warning.
+    pub fn warn(&mut self, logger: &slog::Logger, message: &str) {
+        warn!(logger, "warning: {}", message);
+        self.warnings.push(message.to_string());
+    }
+
+    /// Obtain warnings recorded so far.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Fail if warnings are treated as errors and any were recorded.
+    pub fn check(&self) -> Result<()> {
+        if self.warnings_as_errors && !self.warnings.is_empty() {
+            Err(anyhow!(
+                "{} warnings treated as errors:\n{}",
+                self.warnings.len(),
+                self.warnings.join("\n")
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let logger = get_logger()?;
+
+        let mut lenient = BuildWarnings::default();
+        lenient.warn(&logger, "first");
+        lenient.check()?;
+
+        let mut strict = BuildWarnings::new(true);
+        strict.check()?;
+        strict.warn(&logger, "first");
+        strict.warn(&logger, "second");
+        assert_eq!(strict.warnings(), &["first", "second"]);
+        assert_eq!(
+            strict.check().unwrap_err().to_string(),
+            "2 warnings treated as errors:\nfirst\nsecond"
+        );
+
+        Ok(())
+    }
+}
@@ -120,6 +120,11 @@ impl FileManifest {
             extra_files.add_file(&Path::new(prefix).join(path), &content)?;
         }
 
+        for (path, content) in build.debug_files.entries() {
+            warn!(logger, "adding debug file {} to {}", path.display(), prefix);
+            extra_files.add_file(&Path::new(prefix).join(path), &content)?;
+        }
+
         self.manifest.add_manifest(&extra_files)?;
 
         Ok(())
@@ -57,6 +57,9 @@ impl BuildTarget for PythonEmbeddedResources {
             context.output_path.display()
         );
 
+        self.exe
+            .hint_unoptimized_distribution(&context.logger, context.release);
+
         let embedded = self
             .exe
             .as_embedded_python_binary_data(&context.logger, &context.opt_level)?;
@@ -97,6 +97,18 @@ impl BuildTarget for PythonExecutable {
         crate::app_packaging::resource::set_executable(&mut fh)
             .context("making binary executable")?;
 
+        for (path, _) in build.debug_files.entries() {
+            warn!(
+                &context.logger,
+                "writing debug info to {}",
+                context.output_path.join(path).display()
+            );
+        }
+        build
+            .debug_files
+            .write_to_path(&context.output_path)
+            .context("writing debug info")?;
+
         Ok(ResolvedTarget {
             run_mode: RunMode::Path { path: dest_path },
             output_path: context.output_path.clone(),
@@ -129,7 +141,7 @@ impl PythonExecutable {
 
     /// PythonExecutable.pip_install(args, extra_envs=None)
     pub fn starlark_pip_install(
-        &self,
+        &mut self,
         env: &Environment,
         args: &Value,
         extra_envs: &Value,
@@ -178,7 +190,7 @@ impl PythonExecutable {
 
     /// PythonExecutable.read_package_root(path, packages)
     pub fn starlark_read_package_root(
-        &self,
+        &mut self,
         env: &Environment,
         path: &Value,
         packages: &Value,
@@ -215,7 +227,7 @@ impl PythonExecutable {
     }
 
     /// PythonExecutable.read_virtualenv(path)
-    pub fn starlark_read_virtualenv(&self, env: &Environment, path: &Value) -> ValueResult {
+    pub fn starlark_read_virtualenv(&mut self, env: &Environment, path: &Value) -> ValueResult {
         let path = required_str_arg("path", &path)?;
 
         let context = env.get("CONTEXT").expect("CONTEXT not defined");
@@ -243,7 +255,7 @@ impl PythonExecutable {
 
     /// PythonExecutable.setup_py_install(package_path, extra_envs=None, extra_global_arguments=None)
     pub fn starlark_setup_py_install(
-        &self,
+        &mut self,
         env: &Environment,
         package_path: &Value,
         extra_envs: &Value,
@@ -1088,15 +1100,17 @@ impl PythonExecutable {
         }))
     }
 
-    /// PythonExecutable.filter_resources_from_files(files=None, glob_files=None)
+    /// PythonExecutable.filter_resources_from_files(files=None, glob_files=None, exclude_regex_patterns=None)
     pub fn starlark_filter_resources_from_files(
         &mut self,
         env: &Environment,
         files: &Value,
         glob_files: &Value,
+        exclude_regex_patterns: &Value,
     ) -> ValueResult {
         optional_list_arg("files", "string", &files)?;
         optional_list_arg("glob_files", "string", &glob_files)?;
+        optional_list_arg("exclude_regex_patterns", "string", &exclude_regex_patterns)?;
 
         let files = match files.get_type() {
             "list" => files
@@ -1113,14 +1127,32 @@ impl PythonExecutable {
             _ => panic!("type should have been validated above"),
         };
 
+        let exclude_regex_patterns = match exclude_regex_patterns.get_type() {
+            "list" => exclude_regex_patterns
+                .into_iter()?
+                .map(|x| x.to_string())
+                .collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
         let files_refs = files.iter().map(|x| x.as_ref()).collect::<Vec<&Path>>();
         let glob_files_refs = glob_files.iter().map(|x| x.as_ref()).collect::<Vec<&str>>();
+        let exclude_regex_patterns_refs = exclude_regex_patterns
+            .iter()
+            .map(|x| x.as_ref())
+            .collect::<Vec<&str>>();
 
         let context = env.get("CONTEXT").expect("CONTEXT not defined");
         let logger = context.downcast_apply(|x: &EnvironmentContext| x.logger.clone());
 
         self.exe
-            .filter_resources_from_files(&logger, &files_refs, &glob_files_refs)
+            .filter_resources_from_files(
+                &logger,
+                &files_refs,
+                &glob_files_refs,
+                &exclude_regex_patterns_refs,
+            )
             .map_err(|e| {
                 RuntimeError {
                     code: "RUNTIME_ERROR",
@@ -1144,7 +1176,7 @@ starlark_module! { python_executable_env =>
 
     #[allow(non_snake_case, clippy::ptr_arg)]
     PythonExecutable.pip_install(env env, this, args, extra_envs=None) {
-        this.downcast_apply(|exe: &PythonExecutable| {
+        this.downcast_apply_mut(|exe: &mut PythonExecutable| {
             exe.starlark_pip_install(&env, &args, &extra_envs)
         })
     }
@@ -1156,7 +1188,7 @@ starlark_module! { python_executable_env =>
         path,
         packages
     ) {
-        this.downcast_apply(|exe: &PythonExecutable| {
+        this.downcast_apply_mut(|exe: &mut PythonExecutable| {
             exe.starlark_read_package_root(&env, &path, &packages)
         })
     }
@@ -1167,7 +1199,7 @@ starlark_module! { python_executable_env =>
         this,
         path
     ) {
-        this.downcast_apply(|exe: &PythonExecutable| {
+        this.downcast_apply_mut(|exe: &mut PythonExecutable| {
             exe.starlark_read_virtualenv(&env, &path)
         })
     }
@@ -1180,7 +1212,7 @@ starlark_module! { python_executable_env =>
         extra_envs=None,
         extra_global_arguments=None
     ) {
-        this.downcast_apply(|exe: &PythonExecutable| {
+        this.downcast_apply_mut(|exe: &mut PythonExecutable| {
             exe.starlark_setup_py_install(&env, &package_path, &extra_envs, &extra_global_arguments)
         })
     }
@@ -1423,10 +1455,16 @@ starlark_module! { python_executable_env =>
         env env,
         this,
         files=None,
-        glob_files=None)
+        glob_files=None,
+        exclude_regex_patterns=None)
     {
         this.downcast_apply_mut(|exe: &mut PythonExecutable| {
-            exe.starlark_filter_resources_from_files(&env, &files, &glob_files)
+            exe.starlark_filter_resources_from_files(
+                &env,
+                &files,
+                &glob_files,
+                &exclude_regex_patterns,
+            )
         })
     }
 
@@ -1535,7 +1573,7 @@ mod tests {
 
     #[test]
     fn test_read_package_root_simple() -> Result<()> {
-        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let temp_dir = crate::environment::new_temp_dir("test")?;
 
         let root = temp_dir.path();
         std::fs::create_dir(root.join("bar"))?;
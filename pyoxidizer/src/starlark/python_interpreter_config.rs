@@ -247,10 +247,16 @@ impl EmbeddedPythonConfig {
             run_mode,
             terminfo_resolution,
             use_hash_seed,
+            hash_seed: None,
+            recursion_limit: None,
+            thread_stack_size: None,
+            fault_handler: false,
+            development_mode: false,
             user_site_directory,
             verbose: verbose.to_int().unwrap() as i32,
             write_bytecode,
             write_modules_directory_env,
+            pre_import_code: None,
         }))
     }
 }
@@ -347,6 +353,11 @@ mod tests {
             parser_debug: false,
             quiet: false,
             use_hash_seed: false,
+            hash_seed: None,
+            recursion_limit: None,
+            thread_stack_size: None,
+            fault_handler: false,
+            development_mode: false,
             verbose: 0,
             stdio_encoding_name: None,
             stdio_encoding_errors: None,
@@ -362,6 +373,7 @@ mod tests {
             user_site_directory: false,
             write_bytecode: false,
             write_modules_directory_env: None,
+            pre_import_code: None,
         };
 
         c.downcast_apply(|x: &EmbeddedPythonConfig| assert_eq!(x, &wanted));
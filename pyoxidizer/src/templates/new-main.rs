@@ -12,9 +12,16 @@ fn main() {
     // The following code is in a block so the MainPythonInterpreter is destroyed in an
     // orderly manner, before process exit.
     let code = {
-        // Load the default Python configuration as derived by the PyOxidizer config
-        // file used at build time.
-        let config = default_python_config();
+        // Load the Python configuration as derived by the PyOxidizer config
+        // file used at build time. This is the default configuration unless
+        // an environment variable selects a named configuration.
+        let config = match selected_python_config() {
+            Ok(config) => config,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                std::process::exit(1);
+            }
+        };
 
         // Construct a new Python interpreter using that config, handling any errors
         // from construction.
@@ -26,9 +26,40 @@ pub fn get_logger() -> Result<slog::Logger> {
     ))
 }
 
+/// A slog Drain that records messages in memory.
+#[derive(Clone, Default)]
+pub struct CaptureDrain {
+    pub messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl slog::Drain for CaptureDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        _values: &slog::OwnedKVList,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(format!("{}", record.msg()));
+
+        Ok(())
+    }
+}
+
+/// Obtain a logger recording messages of all levels into the returned drain.
+pub fn get_capturing_logger() -> (slog::Logger, CaptureDrain) {
+    let drain = CaptureDrain::default();
+
+    (Logger::root(drain.clone(), slog::o!()), drain)
+}
+
 lazy_static! {
     pub static ref DEFAULT_DISTRIBUTION_TEMP_DIR: tempdir::TempDir =
-        tempdir::TempDir::new("pyoxidizer-test").expect("unable to create temp directory");
+        crate::environment::new_temp_dir("test").expect("unable to create temp directory");
     static ref CACHED_DISTRIBUTIONS: Mutex<HashMap<PythonDistributionLocation, Arc<Box<StandaloneDistribution>>>> =
         Mutex::new(HashMap::new());
 }
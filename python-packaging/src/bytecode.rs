@@ -122,6 +122,47 @@ impl BytecodeCompiler {
 
         Ok(bytecode)
     }
+
+    /// Whether Python source compiles to code equivalent to existing bytecode.
+    ///
+    /// `bytecode` is marshalled code without a `.pyc` header. Code objects
+    /// are compared in Python with filenames normalized, so bytecode compiled
+    /// from a different path than `filename` still matches.
+    pub fn source_matches_bytecode(
+        self: &mut BytecodeCompiler,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        bytecode: &[u8],
+    ) -> Result<bool> {
+        let stdin = self.command.stdin.as_mut().expect("failed to get stdin");
+        let stdout = self.command.stdout.as_mut().expect("failed to get stdout");
+
+        let mut reader = BufReader::new(stdout);
+
+        stdin.write_all(b"compare\n")?;
+        stdin.write_all(filename.len().to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(source.len().to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(bytecode.len().to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(i32::from(optimize).to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(filename.as_bytes())?;
+        stdin.write_all(source)?;
+        stdin.write_all(bytecode)?;
+        stdin.flush()?;
+
+        let mut result = String::new();
+        reader.read_line(&mut result)?;
+
+        match result.trim_end() {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            _ => Err(anyhow!("unexpected bytecode comparison result: {}", result)),
+        }
+    }
 }
 
 impl Drop for BytecodeCompiler {
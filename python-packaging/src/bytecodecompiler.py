@@ -13,6 +13,7 @@ import marshal
 import os
 import re
 import sys
+import types
 
 
 RE_CODING = re.compile(b"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)")
@@ -25,6 +26,42 @@ stdin = sys.__stdin__.buffer
 stdout = sys.__stdout__.buffer
 
 
+def decode_source(source):
+    """Decode Python source bytes to str, honoring PEP 263 encodings.
+
+    Returns the source bytes with any BOM removed and the decoded str.
+    """
+    # Default source encoding is UTF-8. But per PEP 263, the first or second
+    # line of source can match a regular expression to define a custom
+    # encoding. We need to detect custom encodings and use it to decode
+    # the passed bytes to str.
+    encoding = "utf-8"
+
+    for line in source.splitlines()[0:2]:
+        m = RE_CODING.match(line)
+        if m:
+            encoding = m.group(1).decode("ascii")
+            break
+
+    # Someone has set us up the BOM! According to PEP 263 the file should
+    # be interpreted as UTF-8.
+    if source.startswith(b"\xef\xbb\xbf"):
+        encoding = "utf-8"
+        source = source[3:]
+
+    return source, source.decode(encoding)
+
+
+def normalize_code(code, filename):
+    """Set the filename of a code object and all code objects it contains."""
+    consts = tuple(
+        normalize_code(c, filename) if isinstance(c, types.CodeType) else c
+        for c in code.co_consts
+    )
+
+    return code.replace(co_filename=filename, co_consts=consts)
+
+
 while True:
     command = stdin.readline().rstrip()
 
@@ -48,26 +85,7 @@ while True:
 
         name = os.fsdecode(name)
 
-        # Default source encoding is UTF-8. But per PEP 263, the first or second
-        # line of source can match a regular expression to define a custom
-        # encoding. We need to detect custom encodings and use it to decode
-        # the passed bytes to str.
-        encoding = "utf-8"
-
-        for line in source.splitlines()[0:2]:
-            m = RE_CODING.match(line)
-            if m:
-                encoding = m.group(1).decode("ascii")
-                break
-
-        # Someone has set us up the BOM! According to PEP 263 the file should
-        # be interpreted as UTF-8.
-        if source.startswith(b"\xef\xbb\xbf"):
-            encoding = "utf-8"
-            source = source[3:]
-
-        source_bytes = source
-        source = source.decode(encoding)
+        source_bytes, source = decode_source(source)
 
         code = compile(source, name, "exec", optimize=optimize_level)
         bytecode = marshal.dumps(code)
@@ -90,5 +108,27 @@ while True:
         stdout.write(b"%d\n" % len(out))
         stdout.write(out)
         stdout.flush()
+    elif command == b"compare":
+        name_len = int(stdin.readline().rstrip())
+        source_len = int(stdin.readline().rstrip())
+        bytecode_len = int(stdin.readline().rstrip())
+        optimize_level = int(stdin.readline().rstrip())
+
+        name = os.fsdecode(stdin.read(name_len))
+        source = stdin.read(source_len)
+        bytecode = stdin.read(bytecode_len)
+
+        # Code objects embed the filename they were compiled with, which
+        # differs between build environments. So compare code objects with
+        # filenames normalized instead of comparing serialized bytecode.
+        _, source = decode_source(source)
+        compiled = compile(source, name, "exec", optimize=optimize_level)
+        loaded = marshal.loads(bytecode)
+
+        if normalize_code(compiled, name) == normalize_code(loaded, name):
+            stdout.write(b"1\n")
+        else:
+            stdout.write(b"0\n")
+        stdout.flush()
     else:
         raise Exception("invalid command: %s" % command)
@@ -8,15 +8,20 @@ Functionality for defining how Python resources should be packaged.
 
 use {
     crate::licensing::NON_GPL_LICENSES,
-    crate::resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
+    crate::resource::{
+        BytecodeOptimizationLevel, PythonExtensionModule, PythonExtensionModuleVariants,
+        PythonResource,
+    },
     anyhow::{anyhow, Result},
-    std::collections::HashMap,
+    regex::Regex,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeSet, HashMap},
     std::convert::TryFrom,
     std::iter::FromIterator,
 };
 
 /// Describes a policy for the location of Python resources.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum PythonResourcesPolicy {
     /// Only allow Python resources to be loaded from memory.
     ///
@@ -120,6 +125,25 @@ pub struct PythonPackagingPolicy {
     /// Whether to include test files.
     include_test: bool,
 
+    /// Test packages to include even when test files are otherwise excluded.
+    included_test_packages: BTreeSet<String>,
+
+    /// Whether to add the distribution's standard library when a builder is constructed.
+    ///
+    /// If false, only resources required to initialize the interpreter are added.
+    include_distribution_stdlib: bool,
+
+    /// Whether to reuse bytecode precompiled by the distribution.
+    ///
+    /// If false, bytecode is always compiled from source.
+    reuse_distribution_bytecode: bool,
+
+    /// Module name patterns and the bytecode optimization level to use for them.
+    ///
+    /// The first matching pattern wins. Modules not matching any pattern
+    /// receive level 0 bytecode.
+    bytecode_optimization_levels: Vec<(Regex, BytecodeOptimizationLevel)>,
+
     /// Mapping of target triple to list of extensions that don't work for that triple.
     ///
     /// Policy constructors can populate this with known broken extensions to
@@ -136,12 +160,21 @@ impl Default for PythonPackagingPolicy {
             include_distribution_sources: true,
             include_distribution_resources: false,
             include_test: false,
+            included_test_packages: BTreeSet::new(),
+            include_distribution_stdlib: true,
+            reuse_distribution_bytecode: false,
+            bytecode_optimization_levels: vec![],
             broken_extensions: HashMap::new(),
         }
     }
 }
 
 impl PythonPackagingPolicy {
+    /// Obtain the active extension module filter.
+    pub fn extension_module_filter(&self) -> &ExtensionModuleFilter {
+        &self.extension_module_filter
+    }
+
     /// Set the extension module filter to use.
     pub fn set_extension_module_filter(&mut self, filter: ExtensionModuleFilter) {
         self.extension_module_filter = filter;
@@ -180,6 +213,91 @@ impl PythonPackagingPolicy {
         self.include_test = include;
     }
 
+    /// Include a specific test package even if test files are excluded.
+    ///
+    /// The package and all its submodules and resources are included.
+    pub fn include_test_package(&mut self, name: &str) {
+        self.included_test_packages.insert(name.to_string());
+    }
+
+    /// Whether test files belonging to the named package or module are included.
+    fn is_test_included(&self, name: &str) -> bool {
+        self.include_test
+            || self
+                .included_test_packages
+                .iter()
+                .any(|package| name == package || name.starts_with(&format!("{}.", package)))
+    }
+
+    /// Whether the distribution's standard library should be added to new builders.
+    pub fn include_distribution_stdlib(&self) -> bool {
+        self.include_distribution_stdlib
+    }
+
+    /// Set whether to add the distribution's standard library to new builders.
+    ///
+    /// When disabled, builders only contain the extension modules and Python
+    /// modules required to bootstrap the interpreter.
+    pub fn set_include_distribution_stdlib(&mut self, include: bool) {
+        self.include_distribution_stdlib = include;
+    }
+
+    /// Whether bytecode precompiled by the distribution should be reused.
+    pub fn reuse_distribution_bytecode(&self) -> bool {
+        self.reuse_distribution_bytecode
+    }
+
+    /// Set whether to reuse bytecode precompiled by the distribution.
+    ///
+    /// When enabled, a distribution's `.pyc` files are embedded verbatim if
+    /// they are compatible with the distribution's interpreter. Modules lacking
+    /// a usable `.pyc` still have bytecode compiled from source.
+    pub fn set_reuse_distribution_bytecode(&mut self, reuse: bool) {
+        self.reuse_distribution_bytecode = reuse;
+    }
+
+    /// Use a bytecode optimization level for modules whose name matches a pattern.
+    ///
+    /// `pattern` is a glob where `*` matches any sequence of characters,
+    /// including `.`. e.g. `test.*` matches all submodules of `test`.
+    /// Patterns are consulted in the order they were added.
+    pub fn add_bytecode_optimization_level(
+        &mut self,
+        pattern: &str,
+        level: BytecodeOptimizationLevel,
+    ) -> Result<()> {
+        let re = format!(
+            "^{}$",
+            pattern
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".*")
+        );
+
+        self.bytecode_optimization_levels
+            .push((Regex::new(&re)?, level));
+
+        Ok(())
+    }
+
+    /// Resolve the bytecode optimization level to use for a named module.
+    pub fn bytecode_optimization_level(&self, name: &str) -> BytecodeOptimizationLevel {
+        self.bytecode_optimization_levels
+            .iter()
+            .find(|(re, _)| re.is_match(name))
+            .map(|(_, level)| *level)
+            .unwrap_or(BytecodeOptimizationLevel::Zero)
+    }
+
+    /// Obtain the names of extensions marked as broken for a target platform.
+    pub fn broken_extensions(&self, target_triple: &str) -> Vec<&str> {
+        match self.broken_extensions.get(target_triple) {
+            Some(extensions) => extensions.iter().map(|x| x.as_str()).collect(),
+            None => vec![],
+        }
+    }
+
     /// Mark an extension as broken on a target platform, preventing it from being used.
     pub fn register_broken_extension(&mut self, target_triple: &str, extension: &str) {
         if !self.broken_extensions.contains_key(target_triple) {
@@ -202,17 +320,19 @@ impl PythonPackagingPolicy {
     pub fn filter_python_resource(&self, resource: &PythonResource) -> bool {
         match resource {
             PythonResource::ModuleSource(module) => {
-                if !self.include_test && module.is_test {
+                if module.is_test && !self.is_test_included(&module.name) {
                     false
                 } else {
                     self.include_distribution_sources
                 }
             }
-            PythonResource::ModuleBytecodeRequest(module) => self.include_test || !module.is_test,
+            PythonResource::ModuleBytecodeRequest(module) => {
+                !module.is_test || self.is_test_included(&module.name)
+            }
             PythonResource::ModuleBytecode(_) => false,
             PythonResource::Resource(resource) => {
                 if self.include_distribution_resources {
-                    self.include_test || !resource.is_test
+                    !resource.is_test || self.is_test_included(&resource.leaf_package)
                 } else {
                     false
                 }
@@ -225,119 +345,176 @@ impl PythonPackagingPolicy {
         }
     }
 
+    /// Whether an extension is marked as broken on a target platform.
+    fn is_broken_extension(&self, target_triple: &str, name: &str) -> bool {
+        self.broken_extensions
+            .get(target_triple)
+            .map(|extensions| extensions.iter().any(|x| x == name))
+            .unwrap_or(false)
+    }
+
     /// Resolve Python extension modules that are compliant with the policy.
-    #[allow(clippy::if_same_then_else)]
+    ///
+    /// Returns the extension modules and the names of extension modules that
+    /// the policy would include but that are excluded because they are broken
+    /// on the target.
     pub fn resolve_python_extension_modules<'a>(
         &self,
         extensions_variants: impl Iterator<Item = &'a PythonExtensionModuleVariants>,
         target_triple: &str,
-    ) -> Result<Vec<PythonExtensionModule>> {
+    ) -> Result<(Vec<PythonExtensionModule>, Vec<String>)> {
         let mut res = vec![];
+        let mut broken = vec![];
 
         for variants in extensions_variants {
-            let name = &variants.default_variant().name;
+            let extensions = self.resolve_extension_module_variants(variants);
 
             // This extension is broken on this target. Ignore it.
-            if self
-                .broken_extensions
-                .get(target_triple)
-                .unwrap_or(&Vec::new())
-                .contains(name)
-            {
+            if self.is_broken_extension(target_triple, &variants.default_variant().name) {
+                if !extensions.is_empty() {
+                    broken.push(variants.default_variant().name.clone());
+                }
                 continue;
             }
 
-            // Always add minimally required extension modules, because things don't
-            // work if we don't do this.
-            let ext_variants =
-                PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
-                    if em.is_minimally_required() {
-                        Some(em.clone())
-                    } else {
-                        None
-                    }
-                }));
-
-            if !ext_variants.is_empty() {
+            res.extend(extensions);
+        }
+
+        Ok((res, broken))
+    }
+
+    /// Resolve the variants of a single extension module compliant with the policy.
+    #[allow(clippy::if_same_then_else)]
+    fn resolve_extension_module_variants(
+        &self,
+        variants: &PythonExtensionModuleVariants,
+    ) -> Vec<PythonExtensionModule> {
+        let mut res = vec![];
+
+        // Always add minimally required extension modules, because things don't
+        // work if we don't do this.
+        let ext_variants =
+            PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
+                if em.is_minimally_required() {
+                    Some(em.clone())
+                } else {
+                    None
+                }
+            }));
+
+        if !ext_variants.is_empty() {
+            res.push(
+                ext_variants
+                    .choose_variant(&self.preferred_extension_module_variants)
+                    .clone(),
+            );
+        }
+
+        match self.extension_module_filter {
+            // Nothing to do here since we added minimal extensions above.
+            ExtensionModuleFilter::Minimal => {}
+
+            ExtensionModuleFilter::All => {
                 res.push(
-                    ext_variants
+                    variants
                         .choose_variant(&self.preferred_extension_module_variants)
                         .clone(),
                 );
             }
 
-            match self.extension_module_filter {
-                // Nothing to do here since we added minimal extensions above.
-                ExtensionModuleFilter::Minimal => {}
-
-                ExtensionModuleFilter::All => {
+            ExtensionModuleFilter::NoLibraries => {
+                let ext_variants =
+                    PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
+                        if !em.requires_libraries() {
+                            Some(em.clone())
+                        } else {
+                            None
+                        }
+                    }));
+
+                if !ext_variants.is_empty() {
                     res.push(
-                        variants
+                        ext_variants
                             .choose_variant(&self.preferred_extension_module_variants)
                             .clone(),
                     );
                 }
+            }
 
-                ExtensionModuleFilter::NoLibraries => {
-                    let ext_variants = PythonExtensionModuleVariants::from_iter(
-                        variants.iter().filter_map(|em| {
-                            if !em.requires_libraries() {
+            ExtensionModuleFilter::NoGPL => {
+                let ext_variants =
+                    PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
+                        if em.link_libraries.is_empty() {
+                            Some(em.clone())
+                        // Public domain is always allowed.
+                        } else if em.license_public_domain == Some(true) {
+                            Some(em.clone())
+                        // Use explicit license list if one is defined.
+                        } else if let Some(ref licenses) = em.licenses {
+                            // We filter through an allow list because it is safer. (No new GPL
+                            // licenses can slip through.)
+                            if licenses
+                                .iter()
+                                .all(|license| NON_GPL_LICENSES.contains(&license.as_str()))
+                            {
                                 Some(em.clone())
                             } else {
                                 None
                             }
-                        }),
-                    );
-
-                    if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
-                    }
-                }
-
-                ExtensionModuleFilter::NoGPL => {
-                    let ext_variants = PythonExtensionModuleVariants::from_iter(
-                        variants.iter().filter_map(|em| {
-                            if em.link_libraries.is_empty() {
-                                Some(em.clone())
-                            // Public domain is always allowed.
-                            } else if em.license_public_domain == Some(true) {
-                                Some(em.clone())
-                            // Use explicit license list if one is defined.
-                            } else if let Some(ref licenses) = em.licenses {
-                                // We filter through an allow list because it is safer. (No new GPL
-                                // licenses can slip through.)
-                                if licenses
-                                    .iter()
-                                    .all(|license| NON_GPL_LICENSES.contains(&license.as_str()))
-                                {
-                                    Some(em.clone())
-                                } else {
-                                    None
-                                }
-                            } else {
-                                // In lack of evidence that it isn't GPL, assume GPL.
-                                // TODO consider improving logic here, like allowing known system
-                                // and framework libraries to be used.
-                                None
-                            }
-                        }),
+                        } else {
+                            // In lack of evidence that it isn't GPL, assume GPL.
+                            // TODO consider improving logic here, like allowing known system
+                            // and framework libraries to be used.
+                            None
+                        }
+                    }));
+
+                if !ext_variants.is_empty() {
+                    res.push(
+                        ext_variants
+                            .choose_variant(&self.preferred_extension_module_variants)
+                            .clone(),
                     );
-
-                    if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
-                    }
                 }
             }
         }
 
-        Ok(res)
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytecode_optimization_level() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            policy.bytecode_optimization_level("foo"),
+            BytecodeOptimizationLevel::Zero
+        );
+
+        policy.add_bytecode_optimization_level("test.*", BytecodeOptimizationLevel::Two)?;
+        policy.add_bytecode_optimization_level("*", BytecodeOptimizationLevel::One)?;
+
+        assert_eq!(
+            policy.bytecode_optimization_level("test.support"),
+            BytecodeOptimizationLevel::Two
+        );
+        assert_eq!(
+            policy.bytecode_optimization_level("test.support.script_helper"),
+            BytecodeOptimizationLevel::Two
+        );
+        assert_eq!(
+            policy.bytecode_optimization_level("test"),
+            BytecodeOptimizationLevel::One
+        );
+        assert_eq!(
+            policy.bytecode_optimization_level("testing"),
+            BytecodeOptimizationLevel::One
+        );
+
+        Ok(())
     }
 }
@@ -11,6 +11,7 @@ use {
     },
     crate::python_source::has_dunder_file,
     anyhow::{anyhow, Context, Result},
+    serde::{Deserialize, Serialize},
     std::collections::HashMap,
     std::convert::TryFrom,
     std::hash::BuildHasher,
@@ -21,7 +22,7 @@ use {
 /// Represents an abstract location for binary data.
 ///
 /// Data can be backed by memory or by a path in the filesystem.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum DataLocation {
     Path(PathBuf),
     Memory(Vec<u8>),
@@ -666,6 +667,60 @@ impl PythonExtensionModuleVariants {
 
         chosen
     }
+
+    /// Describe the differences between variants of this extension module.
+    ///
+    /// Returns a `VariantDescription` for each variant, in order.
+    pub fn describe(&self) -> Vec<VariantDescription> {
+        self.iter().map(VariantDescription::from).collect()
+    }
+}
+
+/// Describes a variant of an extension module.
+///
+/// This captures the properties that are important when choosing between
+/// variants, such as which libraries are linked and how they are licensed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariantDescription {
+    /// Name of the variant.
+    pub variant: Option<String>,
+    /// Names of non-system libraries this variant links against.
+    pub libraries: Vec<String>,
+    /// Names of system libraries this variant links against.
+    pub system_libraries: Vec<String>,
+    /// Names of frameworks this variant links against.
+    pub frameworks: Vec<String>,
+    /// SPDX license shortnames that apply to this variant.
+    pub licenses: Option<Vec<String>>,
+    /// Whether the variant and its library dependencies are in the public domain.
+    pub license_public_domain: Option<bool>,
+}
+
+impl From<&PythonExtensionModule> for VariantDescription {
+    fn from(em: &PythonExtensionModule) -> Self {
+        let mut libraries = Vec::new();
+        let mut system_libraries = Vec::new();
+        let mut frameworks = Vec::new();
+
+        for link in &em.link_libraries {
+            if link.framework {
+                frameworks.push(link.name.clone());
+            } else if link.system {
+                system_libraries.push(link.name.clone());
+            } else {
+                libraries.push(link.name.clone());
+            }
+        }
+
+        Self {
+            variant: em.variant.clone(),
+            libraries,
+            system_libraries,
+            frameworks,
+            licenses: em.licenses.clone(),
+            license_public_domain: em.license_public_domain,
+        }
+    }
 }
 
 /// Represents a Python .egg file.
@@ -868,4 +923,53 @@ mod tests {
         assert!(!bytecode.is_in_packages(&[]));
         assert!(!bytecode.is_in_packages(&["bar".to_string()]));
     }
+
+    #[test]
+    fn test_describe_variants() {
+        let em = PythonExtensionModule {
+            name: "_ssl".to_string(),
+            init_fn: Some("PyInit__ssl".to_string()),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![
+                LibraryDependency {
+                    name: "ssl".to_string(),
+                    static_library: None,
+                    dynamic_library: None,
+                    framework: false,
+                    system: false,
+                },
+                LibraryDependency {
+                    name: "pthread".to_string(),
+                    static_library: None,
+                    dynamic_library: None,
+                    framework: false,
+                    system: true,
+                },
+            ],
+            is_stdlib: true,
+            builtin_default: false,
+            required: false,
+            variant: Some("openssl".to_string()),
+            licenses: Some(vec!["OpenSSL".to_string()]),
+            license_texts: None,
+            license_public_domain: None,
+        };
+
+        let variants = PythonExtensionModuleVariants::from_iter(vec![em]);
+
+        assert_eq!(
+            variants.describe(),
+            vec![VariantDescription {
+                variant: Some("openssl".to_string()),
+                libraries: vec!["ssl".to_string()],
+                system_libraries: vec!["pthread".to_string()],
+                frameworks: vec![],
+                licenses: Some(vec!["OpenSSL".to_string()]),
+                license_public_domain: None,
+            }]
+        );
+    }
 }
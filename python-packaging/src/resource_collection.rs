@@ -16,6 +16,7 @@ use {
     },
     anyhow::{anyhow, Error, Result},
     python_packed_resources::data::{Resource, ResourceFlavor},
+    serde::{Deserialize, Serialize},
     std::borrow::Cow,
     std::collections::{BTreeMap, BTreeSet, HashMap},
     std::convert::TryFrom,
@@ -23,8 +24,30 @@ use {
     std::path::{Path, PathBuf},
 };
 
+/// Serializes `ResourceFlavor` using its numeric value in packed resources data.
+mod resource_flavor_serde {
+    use {
+        python_packed_resources::data::ResourceFlavor,
+        serde::{Deserialize, Deserializer, Serializer},
+        std::convert::TryFrom,
+    };
+
+    pub fn serialize<S: Serializer>(
+        flavor: &ResourceFlavor,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8((*flavor).into())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ResourceFlavor, D::Error> {
+        ResourceFlavor::try_from(u8::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Describes how Python module bytecode will be obtained.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum PythonModuleBytecodeProvider {
     /// Bytecode is already available.
     Provided(DataLocation),
@@ -32,14 +55,25 @@ pub enum PythonModuleBytecodeProvider {
     FromSource(DataLocation),
 }
 
+impl PythonModuleBytecodeProvider {
+    /// The location of the data bytecode is obtained from.
+    pub fn location(&self) -> &DataLocation {
+        match self {
+            PythonModuleBytecodeProvider::Provided(location) => location,
+            PythonModuleBytecodeProvider::FromSource(location) => location,
+        }
+    }
+}
+
 /// Represents a Python resource entry before it is packaged.
 ///
 /// Instances hold the same fields as `Resource` except fields holding
 /// content are backed by a `DataLocation` instead of `Vec<u8>`, since
 /// we want data resolution to be lazy. In addition, bytecode can either be
 /// provided verbatim or via source.
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct PrePackagedResource {
+    #[serde(with = "resource_flavor_serde")]
     pub flavor: ResourceFlavor,
     pub name: String,
     pub is_package: bool,
@@ -65,6 +99,7 @@ pub struct PrePackagedResource {
     pub relative_path_distribution_resources:
         Option<BTreeMap<String, (String, PathBuf, DataLocation)>>,
     pub relative_path_shared_library: Option<(String, DataLocation)>,
+    pub eager_load: bool,
 }
 
 impl<'a> TryFrom<&PrePackagedResource> for Resource<'a, u8> {
@@ -189,11 +224,167 @@ impl<'a> TryFrom<&PrePackagedResource> for Resource<'a, u8> {
             } else {
                 None
             },
+            eager_load: value.eager_load,
+            zstd_compressed: false,
         })
     }
 }
 
+/// Whether data at a location can still be resolved.
+fn is_location_resolvable(location: &DataLocation) -> bool {
+    match location {
+        DataLocation::Path(path) => path.is_file(),
+        DataLocation::Memory(_) => true,
+    }
+}
+
+/// Clear an optional field whose data location can no longer be resolved.
+///
+/// Returns the number of cleared fields.
+fn remove_if_unresolvable<T, F>(field: &mut Option<T>, location: F) -> usize
+where
+    F: Fn(&T) -> &DataLocation,
+{
+    if field
+        .as_ref()
+        .map_or(false, |value| !is_location_resolvable(location(value)))
+    {
+        *field = None;
+        1
+    } else {
+        0
+    }
+}
+
 impl PrePackagedResource {
+    /// Obtain every location this resource obtains data from.
+    pub fn data_locations(&self) -> Vec<&DataLocation> {
+        let mut res = Vec::new();
+
+        res.extend(self.in_memory_source.iter());
+        for provider in [
+            &self.in_memory_bytecode,
+            &self.in_memory_bytecode_opt1,
+            &self.in_memory_bytecode_opt2,
+        ]
+        .iter()
+        {
+            res.extend(provider.iter().map(|p| p.location()));
+        }
+        res.extend(self.in_memory_extension_module_shared_library.iter());
+        for resources in [
+            &self.in_memory_resources,
+            &self.in_memory_distribution_resources,
+        ]
+        .iter()
+        {
+            res.extend(resources.iter().flat_map(|r| r.values()));
+        }
+        res.extend(self.in_memory_shared_library.iter());
+
+        res.extend(self.relative_path_module_source.iter().map(|(_, l)| l));
+        for bytecode in [
+            &self.relative_path_bytecode,
+            &self.relative_path_bytecode_opt1,
+            &self.relative_path_bytecode_opt2,
+        ]
+        .iter()
+        {
+            res.extend(bytecode.iter().map(|(_, _, p)| p.location()));
+        }
+        res.extend(
+            self.relative_path_extension_module_shared_library
+                .iter()
+                .map(|(_, _, l)| l),
+        );
+        for resources in [
+            &self.relative_path_package_resources,
+            &self.relative_path_distribution_resources,
+        ]
+        .iter()
+        {
+            res.extend(resources.iter().flat_map(|r| r.values().map(|(_, _, l)| l)));
+        }
+        res.extend(self.relative_path_shared_library.iter().map(|(_, l)| l));
+
+        res
+    }
+
+    /// Whether all filesystem-backed data for this resource still exists.
+    pub fn is_resolvable(&self) -> bool {
+        self.data_locations()
+            .into_iter()
+            .all(is_location_resolvable)
+    }
+
+    /// Remove data whose filesystem-backed location no longer exists.
+    ///
+    /// Only fields referring to missing data are removed. Other data of the
+    /// resource is retained. Returns the number of removed fields.
+    pub fn remove_unresolvable_data(&mut self) -> usize {
+        let mut count = remove_if_unresolvable(&mut self.in_memory_source, |l| l)
+            + remove_if_unresolvable(&mut self.in_memory_bytecode, |p| p.location())
+            + remove_if_unresolvable(&mut self.in_memory_bytecode_opt1, |p| p.location())
+            + remove_if_unresolvable(&mut self.in_memory_bytecode_opt2, |p| p.location())
+            + remove_if_unresolvable(&mut self.in_memory_extension_module_shared_library, |l| l)
+            + remove_if_unresolvable(&mut self.in_memory_shared_library, |l| l)
+            + remove_if_unresolvable(&mut self.relative_path_module_source, |(_, l)| l)
+            + remove_if_unresolvable(&mut self.relative_path_bytecode, |(_, _, p)| p.location())
+            + remove_if_unresolvable(&mut self.relative_path_bytecode_opt1, |(_, _, p)| {
+                p.location()
+            })
+            + remove_if_unresolvable(&mut self.relative_path_bytecode_opt2, |(_, _, p)| {
+                p.location()
+            })
+            + remove_if_unresolvable(
+                &mut self.relative_path_extension_module_shared_library,
+                |(_, _, l)| l,
+            )
+            + remove_if_unresolvable(&mut self.relative_path_shared_library, |(_, l)| l);
+
+        for field in [
+            &mut self.in_memory_resources,
+            &mut self.in_memory_distribution_resources,
+        ]
+        .iter_mut()
+        {
+            if let Some(resources) = field {
+                let missing = resources
+                    .iter()
+                    .filter(|(_, l)| !is_location_resolvable(l))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+
+                for key in &missing {
+                    resources.remove(key);
+                }
+                count += missing.len();
+            }
+        }
+
+        for field in [
+            &mut self.relative_path_package_resources,
+            &mut self.relative_path_distribution_resources,
+        ]
+        .iter_mut()
+        {
+            if let Some(resources) = field {
+                let missing = resources
+                    .iter()
+                    .filter(|(_, (_, _, l))| !is_location_resolvable(l))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+
+                for key in &missing {
+                    resources.remove(key);
+                }
+                count += missing.len();
+            }
+        }
+
+        count
+    }
+
     /// Derive additional file installs to perform for filesystem-based resources.
     ///
     /// Returns 3-tuples denoting the relative resource path, data to materialize there,
@@ -425,15 +616,38 @@ pub struct PreparedPythonResources<'a> {
 impl<'a> PreparedPythonResources<'a> {
     /// Write resources to packed resources data, version 1.
     pub fn write_packed_resources_v1<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::writer::write_packed_resources_v1(
-            &self
-                .resources
-                .values()
-                .cloned()
-                .collect::<Vec<Resource<'a, u8>>>(),
-            writer,
-            None,
-        )
+        self.write_packed_resources_v1_ordered(writer, &[])
+    }
+
+    /// Write resources to packed resources data, version 1, honoring a priority order.
+    ///
+    /// Resources named in `priority` are written first, in the order given.
+    /// Remaining resources follow in their default order. Names in `priority`
+    /// not corresponding to a resource are ignored.
+    pub fn write_packed_resources_v1_ordered<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        priority: &[String],
+    ) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        let mut resources = Vec::with_capacity(self.resources.len());
+
+        for name in priority {
+            if let Some(resource) = self.resources.get(name) {
+                if seen.insert(name.as_str()) {
+                    resources.push(resource.clone());
+                }
+            }
+        }
+
+        resources.extend(
+            self.resources
+                .iter()
+                .filter(|(name, _)| !seen.contains(name.as_str()))
+                .map(|(_, resource)| resource.clone()),
+        );
+
+        python_packed_resources::writer::write_packed_resources_v1(&resources, writer, None)
     }
 }
 
@@ -443,7 +657,7 @@ impl<'a> PreparedPythonResources<'a> {
 /// bytecode, etc) into a collection of ``Resource`` so they can be
 /// serialized to the *Python packed resources* format. This type
 /// exists to facilitate doing this.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PythonResourceCollector {
     policy: PythonResourcesPolicy,
     resources: BTreeMap<String, PrePackagedResource>,
@@ -508,6 +722,278 @@ impl PythonResourceCollector {
         Ok(())
     }
 
+    /// Remove data of resources whose filesystem-backed data no longer exists.
+    ///
+    /// Only the missing data is removed from each resource. Resources left
+    /// without any data are removed entirely. Returns the names of changed
+    /// resources.
+    pub fn remove_unresolvable_data(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        let mut empty = Vec::new();
+
+        for (name, resource) in self.resources.iter_mut() {
+            if resource.remove_unresolvable_data() > 0 {
+                changed.push(name.clone());
+
+                if resource.data_locations().is_empty() {
+                    empty.push(name.clone());
+                }
+            }
+        }
+
+        for name in empty {
+            self.resources.remove(&name);
+        }
+
+        changed
+    }
+
+    /// Change the prefix of resources installed relative to the produced binary.
+    ///
+    /// All relative path resources recorded with prefix `old` will be installed
+    /// with prefix `new` instead. Returns the number of resources changed.
+    pub fn rewrite_relative_prefix(&mut self, old: &str, new: &str) -> Result<usize> {
+        let rewrite_path = |path: &Path| -> Result<PathBuf> {
+            let suffix = path
+                .strip_prefix(old)
+                .map_err(|_| anyhow!("path {} does not have prefix {}", path.display(), old))?;
+
+            Ok(PathBuf::from(new).join(suffix))
+        };
+
+        let mut count = 0;
+
+        for resource in self.resources.values_mut() {
+            let mut changed = false;
+
+            if let Some((prefix, _)) = &mut resource.relative_path_module_source {
+                if prefix == old {
+                    *prefix = new.to_string();
+                    changed = true;
+                }
+            }
+
+            for entry in [
+                &mut resource.relative_path_bytecode,
+                &mut resource.relative_path_bytecode_opt1,
+                &mut resource.relative_path_bytecode_opt2,
+            ]
+            .iter_mut()
+            {
+                if let Some((prefix, _, _)) = entry {
+                    if prefix == old {
+                        *prefix = new.to_string();
+                        changed = true;
+                    }
+                }
+            }
+
+            if let Some((prefix, path, _)) =
+                &mut resource.relative_path_extension_module_shared_library
+            {
+                if prefix == old {
+                    *path = rewrite_path(path)?;
+                    *prefix = new.to_string();
+                    changed = true;
+                }
+            }
+
+            for resources in [
+                &mut resource.relative_path_package_resources,
+                &mut resource.relative_path_distribution_resources,
+            ]
+            .iter_mut()
+            {
+                if let Some(resources) = resources {
+                    for (prefix, path, _) in resources.values_mut() {
+                        if prefix == old {
+                            *path = rewrite_path(path)?;
+                            *prefix = new.to_string();
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            if let Some((prefix, _)) = &mut resource.relative_path_shared_library {
+                if prefix == old {
+                    *prefix = new.to_string();
+                    changed = true;
+                }
+            }
+
+            if changed {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Replace requests to compile bytecode with the source they would be compiled from.
+    ///
+    /// Modules lacking source gain source at the location bytecode would have
+    /// been installed to. Bytecode derived from source is then removed, so no
+    /// bytecode compilation is needed at packaging time. Bytecode is instead
+    /// compiled from source at run-time. Explicitly provided bytecode is retained.
+    ///
+    /// Returns the number of resources changed.
+    pub fn replace_bytecode_with_source(&mut self) -> usize {
+        let mut count = 0;
+
+        for resource in self.resources.values_mut() {
+            let mut changed = false;
+
+            for entry in [
+                &mut resource.in_memory_bytecode,
+                &mut resource.in_memory_bytecode_opt1,
+                &mut resource.in_memory_bytecode_opt2,
+            ]
+            .iter_mut()
+            {
+                if let Some(PythonModuleBytecodeProvider::FromSource(location)) = entry {
+                    if resource.in_memory_source.is_none() {
+                        resource.in_memory_source = Some(location.clone());
+                    }
+
+                    **entry = None;
+                    changed = true;
+                }
+            }
+
+            for entry in [
+                &mut resource.relative_path_bytecode,
+                &mut resource.relative_path_bytecode_opt1,
+                &mut resource.relative_path_bytecode_opt2,
+            ]
+            .iter_mut()
+            {
+                if let Some((prefix, _, PythonModuleBytecodeProvider::FromSource(location))) = entry
+                {
+                    if resource.relative_path_module_source.is_none() {
+                        resource.relative_path_module_source =
+                            Some((prefix.clone(), location.clone()));
+                    }
+
+                    **entry = None;
+                    changed = true;
+                }
+            }
+
+            if changed {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Find modules whose provided bytecode is not the compilation of their source.
+    ///
+    /// Only modules having both source and explicitly provided bytecode are
+    /// examined: bytecode derived from source is consistent by construction.
+    /// The source is compiled at the optimization level of each bytecode entry
+    /// and the resulting code objects are compared against the provided
+    /// bytecode in Python. Code filenames are ignored, since they depend on
+    /// where bytecode was compiled.
+    ///
+    /// Returns a description of each mismatch found.
+    pub fn find_source_bytecode_mismatches(&self, python_exe: &Path) -> Result<Vec<String>> {
+        let mut candidates = Vec::new();
+
+        for (name, resource) in &self.resources {
+            if resource.flavor != ResourceFlavor::Module {
+                continue;
+            }
+
+            let source = match (
+                &resource.in_memory_source,
+                &resource.relative_path_module_source,
+            ) {
+                (Some(location), _) => location,
+                (None, Some((_, location))) => location,
+                (None, None) => continue,
+            };
+
+            let providers = [
+                (
+                    BytecodeOptimizationLevel::Zero,
+                    resource.in_memory_bytecode.as_ref(),
+                ),
+                (
+                    BytecodeOptimizationLevel::One,
+                    resource.in_memory_bytecode_opt1.as_ref(),
+                ),
+                (
+                    BytecodeOptimizationLevel::Two,
+                    resource.in_memory_bytecode_opt2.as_ref(),
+                ),
+                (
+                    BytecodeOptimizationLevel::Zero,
+                    resource.relative_path_bytecode.as_ref().map(|(_, _, p)| p),
+                ),
+                (
+                    BytecodeOptimizationLevel::One,
+                    resource
+                        .relative_path_bytecode_opt1
+                        .as_ref()
+                        .map(|(_, _, p)| p),
+                ),
+                (
+                    BytecodeOptimizationLevel::Two,
+                    resource
+                        .relative_path_bytecode_opt2
+                        .as_ref()
+                        .map(|(_, _, p)| p),
+                ),
+            ];
+
+            for (level, provider) in providers.iter() {
+                if let Some(PythonModuleBytecodeProvider::Provided(bytecode)) = provider {
+                    candidates.push((name, source, *level, bytecode));
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut compiler = BytecodeCompiler::new(python_exe)?;
+        let mut mismatches = Vec::new();
+
+        for (name, source, level, bytecode) in candidates {
+            if !compiler.source_matches_bytecode(
+                &source.resolve()?,
+                name,
+                level,
+                &bytecode.resolve()?,
+            )? {
+                mismatches.push(format!(
+                    "{}: bytecode (optimization level {}) does not match source",
+                    name,
+                    i32::from(level)
+                ));
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Set whether a resource should be loaded eagerly at run-time.
+    ///
+    /// Resources are loaded lazily by default.
+    pub fn set_eager_load(&mut self, name: &str, eager: bool) -> Result<()> {
+        let resource = self
+            .resources
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("resource {} does not exist", name))?;
+
+        resource.eager_load = eager;
+
+        Ok(())
+    }
+
     /// Obtain an iterator over the resources in this collector.
     pub fn iter_resources(&self) -> impl Iterator<Item = (&String, &PrePackagedResource)> {
         Box::new(self.resources.iter())
@@ -859,6 +1345,11 @@ impl PythonResourceCollector {
     }
 
     /// Add a shared library to be loaded from a location.
+    ///
+    /// Shared libraries are commonly required by multiple extension modules. So
+    /// adding a library with the same name and content as an existing one is a
+    /// no-op. Adding a library with the same name and different content is an
+    /// error.
     pub fn add_shared_library(
         &mut self,
         name: &str,
@@ -876,6 +1367,23 @@ impl PythonResourceCollector {
                 ..PrePackagedResource::default()
             });
 
+        let existing = match location {
+            ConcreteResourceLocation::InMemory => entry.in_memory_shared_library.as_ref(),
+            ConcreteResourceLocation::RelativePath(_) => entry
+                .relative_path_shared_library
+                .as_ref()
+                .map(|(_, existing)| existing),
+        };
+
+        if let Some(existing) = existing {
+            if existing != data && existing.resolve()? != data.resolve()? {
+                return Err(anyhow!(
+                    "shared library {} already added with different content",
+                    name
+                ));
+            }
+        }
+
         match location {
             ConcreteResourceLocation::InMemory => {
                 entry.in_memory_shared_library = Some(data.clone());
@@ -1149,6 +1657,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_packed_resources_v1_ordered() -> Result<()> {
+        let mut resources = BTreeMap::new();
+        for name in &["a", "b", "c"] {
+            resources.insert(
+                name.to_string(),
+                Resource {
+                    flavor: ResourceFlavor::Module,
+                    name: Cow::Owned(name.to_string()),
+                    ..Resource::default()
+                },
+            );
+        }
+
+        let prepared = PreparedPythonResources {
+            resources,
+            extra_files: vec![],
+        };
+
+        let mut data = Vec::new();
+        prepared.write_packed_resources_v1_ordered(
+            &mut data,
+            &["c".to_string(), "missing".to_string(), "c".to_string()],
+        )?;
+
+        let names = python_packed_resources::parser::load_resources(&data)
+            .map_err(|e| anyhow!(e))?
+            .map(|r| r.map(|r| r.name.to_string()).map_err(|e| anyhow!(e)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(names, vec!["c", "a", "b"]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_populate_parent_packages_in_memory_source() -> Result<()> {
         let mut h = BTreeMap::new();
@@ -1519,6 +2061,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_replace_bytecode_with_source() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![42]),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert_eq!(r.replace_bytecode_with_source(), 1);
+        assert_eq!(
+            r.resources.get("foo"),
+            Some(&PrePackagedResource {
+                flavor: ResourceFlavor::Module,
+                name: "foo".to_string(),
+                in_memory_source: Some(DataLocation::Memory(vec![42])),
+                is_package: false,
+                ..PrePackagedResource::default()
+            })
+        );
+        assert_eq!(r.replace_bytecode_with_source(), 0);
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_bytecode_module_parents() -> Result<()> {
         let mut r =
@@ -1643,6 +2218,174 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_rewrite_relative_prefix() -> Result<()> {
+        let mut c = PythonResourceCollector::new(
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string()),
+            DEFAULT_CACHE_TAG,
+        );
+
+        c.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![42]),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+        c.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "resource.txt".to_string(),
+                data: DataLocation::Memory(vec![43]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+        c.add_python_module_source(
+            &PythonModuleSource {
+                name: "bar".to_string(),
+                source: DataLocation::Memory(vec![44]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("other".to_string()),
+        )?;
+
+        assert_eq!(c.rewrite_relative_prefix("lib", "vendor")?, 1);
+
+        let files = c
+            .derive_file_installs()?
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("other/bar.py"),
+                PathBuf::from("vendor/foo/__init__.py"),
+                PathBuf::from("vendor/foo/resource.txt"),
+            ]
+        );
+
+        assert_eq!(c.rewrite_relative_prefix("lib", "vendor")?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_eager_load() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![42]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert!(!r.resources.get("foo").unwrap().eager_load);
+        r.set_eager_load("foo", true)?;
+
+        let resource = Resource::try_from(r.resources.get("foo").unwrap())?;
+        assert!(resource.eager_load);
+
+        assert!(r.set_eager_load("missing", true).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_unresolvable_data() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("test")?;
+        let missing = DataLocation::Path(temp_dir.path().join("missing.py"));
+
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "partial".to_string(),
+                source: DataLocation::Memory(vec![42]),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "partial".to_string(),
+                relative_name: "missing.txt".to_string(),
+                data: missing.clone(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "partial".to_string(),
+                relative_name: "present.txt".to_string(),
+                data: DataLocation::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "gone".to_string(),
+                source: missing,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert!(!r.resources.get("partial").unwrap().is_resolvable());
+        assert_eq!(
+            r.remove_unresolvable_data(),
+            vec!["gone".to_string(), "partial".to_string()]
+        );
+
+        // Only the missing data of a resource is removed.
+        let partial = r.resources.get("partial").unwrap();
+        assert!(partial.is_resolvable());
+        assert_eq!(
+            partial.in_memory_source,
+            Some(DataLocation::Memory(vec![42]))
+        );
+        assert_eq!(
+            partial
+                .in_memory_resources
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["present.txt"]
+        );
+
+        // Resources left without data are removed.
+        assert!(!r.resources.contains_key("gone"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_find_dunder_file() -> Result<()> {
         let mut r =
@@ -147,6 +147,8 @@ pub enum ResourceField {
     RelativeFilesystemExtensionModuleSharedLibrary = 0x13,
     RelativeFilesystemPackageResources = 0x14,
     RelativeFilesystemDistributionResource = 0x15,
+    EagerLoad = 0x16,
+    ZstdCompressed = 0x17,
 }
 
 impl Into<u8> for ResourceField {
@@ -174,6 +176,8 @@ impl Into<u8> for ResourceField {
             ResourceField::RelativeFilesystemExtensionModuleSharedLibrary => 0x13,
             ResourceField::RelativeFilesystemPackageResources => 0x14,
             ResourceField::RelativeFilesystemDistributionResource => 0x15,
+            ResourceField::EagerLoad => 0x16,
+            ResourceField::ZstdCompressed => 0x17,
             ResourceField::EndOfEntry => 0xff,
         }
     }
@@ -206,6 +210,8 @@ impl TryFrom<u8> for ResourceField {
             0x13 => Ok(ResourceField::RelativeFilesystemExtensionModuleSharedLibrary),
             0x14 => Ok(ResourceField::RelativeFilesystemPackageResources),
             0x15 => Ok(ResourceField::RelativeFilesystemDistributionResource),
+            0x16 => Ok(ResourceField::EagerLoad),
+            0x17 => Ok(ResourceField::ZstdCompressed),
             0xff => Ok(ResourceField::EndOfEntry),
             _ => Err("invalid field type"),
         }
@@ -282,6 +288,16 @@ where
 
     /// Mapping of Python package distribution files to relative filesystem paths for those resources.
     pub relative_path_distribution_resources: Option<HashMap<Cow<'a, str>, Cow<'a, Path>>>,
+
+    /// Whether the resource should be loaded eagerly instead of on demand.
+    ///
+    /// This is a hint to the consumer of the resources data.
+    pub eager_load: bool,
+
+    /// Whether in-memory data of this resource is compressed with zstd.
+    ///
+    /// If set, every `in_memory_*` data field must be decompressed before use.
+    pub zstd_compressed: bool,
 }
 
 impl<'a, X> Default for Resource<'a, X>
@@ -310,6 +326,8 @@ where
             relative_path_extension_module_shared_library: None,
             relative_path_package_resources: None,
             relative_path_distribution_resources: None,
+            eager_load: false,
+            zstd_compressed: false,
         }
     }
 }
@@ -419,6 +437,8 @@ where
                         )
                     }))
                 }),
+            eager_load: self.eager_load,
+            zstd_compressed: self.zstd_compressed,
         }
     }
 }
@@ -163,6 +163,12 @@ impl<'a> ResourceParserIterator<'a> {
                 ResourceField::IsNamespacePackage => {
                     current_resource.is_namespace_package = true;
                 }
+                ResourceField::EagerLoad => {
+                    current_resource.eager_load = true;
+                }
+                ResourceField::ZstdCompressed => {
+                    current_resource.zstd_compressed = true;
+                }
                 ResourceField::InMemorySource => {
                     let l = self
                         .reader
@@ -1351,6 +1357,8 @@ mod tests {
             relative_path_extension_module_shared_library: Some(Cow::from(Path::new("em_path"))),
             relative_path_package_resources: Some(relative_path_resources),
             relative_path_distribution_resources: Some(relative_path_distribution),
+            eager_load: true,
+            zstd_compressed: true,
         };
 
         let mut data = Vec::new();
@@ -1367,6 +1375,8 @@ mod tests {
         assert_eq!(entry.flavor, ResourceFlavor::Module);
         assert!(entry.is_package);
         assert!(entry.is_namespace_package);
+        assert!(entry.eager_load);
+        assert!(entry.zstd_compressed);
         assert_eq!(entry.in_memory_source.as_ref().unwrap().as_ref(), b"source");
         assert_eq!(
             entry.in_memory_bytecode.as_ref().unwrap().as_ref(),
@@ -226,6 +226,14 @@ The number of files being described is contained in a `u32` that immediately
 follows this byte. Following this `u32` is an array of `(u16, u32)` denoting
 the distribution file name and filesystem path to that distribution file.
 
+`0x16` - Eager load flag. If encountered, the resource should be loaded
+eagerly instead of on demand. This is a hint: consumers are free to
+ignore it.
+
+`0x17` - Zstandard compressed flag. If encountered, the data of every
+in-memory field of the resource is compressed with zstd and must be
+decompressed before use.
+
 ## Resource Flavors
 
 The data format allows defining different types/flavors of resources.
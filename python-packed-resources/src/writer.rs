@@ -151,6 +151,14 @@ where
             index += 1;
         }
 
+        if self.eager_load {
+            index += 1;
+        }
+
+        if self.zstd_compressed {
+            index += 1;
+        }
+
         if self.in_memory_source.is_some() {
             index += 5;
         }
@@ -243,6 +251,8 @@ where
             ResourceField::ModuleName => self.name.as_bytes().len(),
             ResourceField::IsPackage => 0,
             ResourceField::IsNamespacePackage => 0,
+            ResourceField::EagerLoad => 0,
+            ResourceField::ZstdCompressed => 0,
             ResourceField::InMemorySource => {
                 if let Some(source) = &self.in_memory_source {
                     source.len()
@@ -384,6 +394,8 @@ where
             ResourceField::ModuleName => 1,
             ResourceField::IsPackage => 0,
             ResourceField::IsNamespacePackage => 0,
+            ResourceField::EagerLoad => 0,
+            ResourceField::ZstdCompressed => 0,
             ResourceField::InMemorySource => {
                 if self.in_memory_source.is_some() {
                     1
@@ -535,6 +547,16 @@ where
                 .context("writing is_namespace field")?;
         }
 
+        if self.eager_load {
+            dest.write_u8(ResourceField::EagerLoad.into())
+                .context("writing eager_load field")?;
+        }
+
+        if self.zstd_compressed {
+            dest.write_u8(ResourceField::ZstdCompressed.into())
+                .context("writing zstd_compressed field")?;
+        }
+
         if let Some(source) = &self.in_memory_source {
             let l =
                 u32::try_from(source.len()).context("converting in-memory source length to u32")?;